@@ -0,0 +1,274 @@
+use std::{
+    cell::{Ref, RefCell},
+    collections::HashMap,
+    rc::{Rc, Weak},
+    time::{Duration, Instant, SystemTime},
+};
+
+use super::error::{self, Result};
+use super::rendering::{
+    geometry,
+    gltf_import::{self, GltfScene},
+    loader::load_texture_from_path,
+    material::{Material, MaterialDef, MaterialId},
+    shader, Texture,
+};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+thread_local! {
+    static ASSET_MANAGER: RefCell<Option<AssetManager>> = RefCell::new(None);
+}
+
+/// Shared handle to a loaded asset. The asset stays cached as long as one handle is alive,
+/// a hot reload swaps the content behind every handle.
+pub struct Handle<T>(Rc<RefCell<T>>);
+
+impl<T> Handle<T> {
+    pub fn get(&self) -> Ref<T> {
+        self.0.borrow()
+    }
+}
+
+impl<T> Clone for Handle<T> {
+    fn clone(&self) -> Self {
+        Handle(Rc::clone(&self.0))
+    }
+}
+
+struct Entry<T> {
+    asset: Weak<RefCell<T>>,
+    modified: Option<SystemTime>,
+}
+
+impl<T> Entry<T> {
+    fn new(handle: &Handle<T>, path: &str) -> Self {
+        Entry {
+            asset: Rc::downgrade(&handle.0),
+            modified: modified(path),
+        }
+    }
+}
+
+pub struct AssetManager {
+    textures: HashMap<String, Entry<Texture>>,
+    models: HashMap<String, Entry<GltfScene>>,
+    materials: HashMap<String, Entry<Material>>,
+    shader_files: HashMap<String, Option<SystemTime>>,
+    last_poll: Instant,
+}
+
+pub fn init() {
+    ASSET_MANAGER.with(|am| {
+        let mut am = am.borrow_mut();
+        if am.is_some() {
+            panic!("asset_manager already initialized")
+        }
+        *am = Some(AssetManager::new());
+    });
+}
+
+pub fn cleanup() {
+    ASSET_MANAGER.with(|am| {
+        am.borrow_mut().take();
+    });
+}
+
+pub fn get_asset_manager<T: FnMut(&mut AssetManager) -> S, S>(mut f: T) -> S {
+    ASSET_MANAGER.with(|am| {
+        if let Some(am) = am.borrow_mut().as_mut() {
+            f(am)
+        } else {
+            panic!("asset_manager not initialized");
+        }
+    })
+}
+
+/// Reloads every asset whose file changed on disk, checks at most every `POLL_INTERVAL`.
+pub fn poll_changes() {
+    get_asset_manager(|am| am.poll_changes());
+}
+
+impl AssetManager {
+    fn new() -> Self {
+        let shader_files = shader::source_files()
+            .into_iter()
+            .map(|path| {
+                let m = modified(&path);
+                (path, m)
+            })
+            .collect();
+
+        AssetManager {
+            textures: HashMap::new(),
+            models: HashMap::new(),
+            materials: HashMap::new(),
+            shader_files,
+            last_poll: Instant::now(),
+        }
+    }
+
+    pub fn load_texture(&mut self, path: &str) -> Result<Handle<Texture>> {
+        if let Some(asset) = self.textures.get(path).and_then(|e| e.asset.upgrade()) {
+            return Ok(Handle(asset));
+        }
+
+        let handle = Handle(Rc::new(RefCell::new(load_texture_from_path(path)?)));
+        self.textures.insert(String::from(path), Entry::new(&handle, path));
+        Ok(handle)
+    }
+
+    /// Imports a glTF file, its meshes are named "{path}/{mesh}/{primitive}" in the `MeshRepo`.
+    pub fn load_model(&mut self, path: &str) -> Result<Handle<GltfScene>> {
+        if let Some(asset) = self.models.get(path).and_then(|e| e.asset.upgrade()) {
+            return Ok(Handle(asset));
+        }
+
+        let scene = geometry::get_mesh_repo(|mr| gltf_import::import(mr, path, path))?;
+        let handle = Handle(Rc::new(RefCell::new(scene)));
+        self.models.insert(String::from(path), Entry::new(&handle, path));
+        Ok(handle)
+    }
+
+    /// Loads a material file and the textures it uses, its id is `MaterialId::from_path(path)`.
+    pub fn load_material(&mut self, path: &str) -> Result<Handle<Material>> {
+        if let Some(asset) = self.materials.get(path).and_then(|e| e.asset.upgrade()) {
+            return Ok(Handle(asset));
+        }
+
+        let material = self.material_from_file(path)?;
+        let handle = Handle(Rc::new(RefCell::new(material)));
+        self.materials.insert(String::from(path), Entry::new(&handle, path));
+        Ok(handle)
+    }
+
+    /// A material loaded earlier, e.g. for the id stored in an entity.
+    pub fn material(&self, id: MaterialId) -> Option<Handle<Material>> {
+        self.materials
+            .values()
+            .filter_map(|e| e.asset.upgrade())
+            .find(|m| m.borrow().id == id)
+            .map(Handle)
+    }
+
+    fn material_from_file(&mut self, path: &str) -> Result<Material> {
+        let def = MaterialDef::load(path)?;
+        Material::from_def(MaterialId::from_path(path), &def, |t| self.load_texture(t))
+    }
+
+    pub fn poll_changes(&mut self) {
+        if self.last_poll.elapsed() < POLL_INTERVAL {
+            return;
+        }
+        self.last_poll = Instant::now();
+
+        self.textures.retain(|_, e| e.asset.strong_count() > 0);
+        self.materials.retain(|_, e| e.asset.strong_count() > 0);
+        self.models.retain(|path, e| {
+            if e.asset.strong_count() > 0 {
+                true
+            } else {
+                geometry::get_mesh_repo(|mr| mr.remove_meshes_with_prefix(&format!("{}/", path)));
+                false
+            }
+        });
+
+        for (path, entry) in self.textures.iter_mut() {
+            if !changed(path, &mut entry.modified) {
+                continue;
+            }
+            if let Some(asset) = entry.asset.upgrade() {
+                match load_texture_from_path(path) {
+                    Ok(texture) => {
+                        *asset.borrow_mut() = texture;
+                        #[cfg(not(feature = "debug_off"))]
+                        println!("reloaded texture {}", path);
+                    }
+                    Err(e) => error::report(e),
+                }
+            }
+        }
+
+        for (path, entry) in self.models.iter_mut() {
+            if !changed(path, &mut entry.modified) {
+                continue;
+            }
+            if let Some(asset) = entry.asset.upgrade() {
+                let scene = geometry::get_mesh_repo(|mr| reimport_model(mr, path));
+                match scene {
+                    Ok(scene) => {
+                        *asset.borrow_mut() = scene;
+                        #[cfg(not(feature = "debug_off"))]
+                        println!("reloaded model {}", path);
+                    }
+                    Err(e) => error::report(e),
+                }
+            }
+        }
+
+        let mut changed_materials = Vec::new();
+        for (path, entry) in self.materials.iter_mut() {
+            if changed(path, &mut entry.modified) {
+                changed_materials.push(path.clone());
+            }
+        }
+        for path in changed_materials {
+            let asset = match self.materials.get(&path).and_then(|e| e.asset.upgrade()) {
+                Some(asset) => asset,
+                None => continue,
+            };
+            match self.material_from_file(&path) {
+                Ok(material) => {
+                    *asset.borrow_mut() = material;
+                    #[cfg(not(feature = "debug_off"))]
+                    println!("reloaded material {}", path);
+                }
+                Err(e) => error::report(e),
+            }
+        }
+
+        let mut changed_shaders = Vec::new();
+        for (path, m) in self.shader_files.iter_mut() {
+            if changed(path, m) {
+                changed_shaders.push(path.clone());
+            }
+        }
+        if !changed_shaders.is_empty() {
+            shader::reload_changed(&changed_shaders);
+        }
+    }
+}
+
+fn modified(path: &str) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+fn changed(path: &str, last: &mut Option<SystemTime>) -> bool {
+    let m = modified(path);
+    if m.is_some() && m != *last {
+        *last = m;
+        true
+    } else {
+        false
+    }
+}
+
+/// Imports the model next to the meshes of the live one, which are only replaced once the
+/// import succeeded. A file that is still being written keeps the old model drawable.
+fn reimport_model(mr: &mut geometry::MeshRepo, path: &str) -> Result<GltfScene> {
+    let staging = format!("{}~reload", path);
+    let mut scene = match gltf_import::import(mr, path, &staging) {
+        Ok(scene) => scene,
+        Err(e) => {
+            mr.remove_meshes_with_prefix(&format!("{}/", staging));
+            return Err(e);
+        }
+    };
+
+    mr.remove_meshes_with_prefix(&format!("{}/", path));
+    mr.rename_meshes_with_prefix(&format!("{}/", staging), &format!("{}/", path));
+    for primitive in scene.meshes.iter_mut().flat_map(|m| m.primitives.iter_mut()) {
+        primitive.name = format!("{}{}", path, &primitive.name[staging.len()..]);
+    }
+    Ok(scene)
+}
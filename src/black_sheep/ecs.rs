@@ -3,6 +3,8 @@ use chained_component_system::chained_component_system;
 
 use std::sync::*;
 
+use super::rendering::{geometry::mesh::MeshToken, lights::Light, material::MaterialId};
+
 chained_component_system!(
     components{
         pos: Vector3<f32>,
@@ -16,11 +18,18 @@ chained_component_system!(
         v: Vector2<f32>,
 
         mat: Matrix4<f32>,
+
+        mesh: MeshToken,
+        material: MaterialId,
+
+        light: Light,
     };
 
     entities{
         Ape(pos,ori,direction,target_ori,col,mat),
-        Ball(p,v)
+        Ball(p,v),
+        Prop(mat,mesh,material),
+        Lamp(light)
     };
 
     global_systems{
@@ -31,5 +40,9 @@ chained_component_system!(
         Draw(mat,col),
         Simulate(mut p,mut v),
         Poss(p),
+        ApeState(pos,ori,direction,target_ori,col),
+        BallState(p,v),
+        DrawMaterial(mat,mesh,material),
+        GatherLights(light,KEY),
     };
 );
@@ -0,0 +1,81 @@
+use std::{fmt, sync::Mutex};
+
+const MAX_REPORTED: usize = 32;
+
+lazy_static! {
+    static ref REPORTED: Mutex<Vec<String>> = Mutex::new(Vec::new());
+}
+
+#[derive(Debug)]
+pub enum EngineError {
+    AlreadyInitialized(&'static str),
+    NameTaken(String),
+    ShaderCompile(String),
+    ShaderLink(String),
+    UnknownShader(String),
+    UnknownBinding(String),
+    FramebufferIncomplete(u32),
+    Io(String, std::io::Error),
+    Image(String, image::ImageError),
+    /// path, actual and expected width and height
+    ImageSize(String, (u32, u32), (u32, u32)),
+    Gltf(String, gltf::Error),
+    /// path, mesh and primitive mode, only triangle lists are drawn
+    PrimitiveMode(String, String, gltf::mesh::Mode),
+    Parse(String, ron::Error),
+}
+
+pub type Result<T> = std::result::Result<T, EngineError>;
+
+impl fmt::Display for EngineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EngineError::AlreadyInitialized(what) => write!(f, "{} already initialized", what),
+            EngineError::NameTaken(name) => write!(f, "name already taken: {}", name),
+            EngineError::ShaderCompile(log) => write!(f, "shader compilation failed:\n{}", log),
+            EngineError::ShaderLink(log) => write!(f, "shader linking failed:\n{}", log),
+            EngineError::UnknownShader(name) => write!(f, "unknown shader: {}", name),
+            EngineError::UnknownBinding(binding) => write!(f, "unknown input binding: {}", binding),
+            EngineError::FramebufferIncomplete(status) => {
+                write!(f, "framebuffer not complete, status: {:#x}", status)
+            }
+            EngineError::Io(path, e) => write!(f, "{}: {}", path, e),
+            EngineError::Image(path, e) => write!(f, "{}: {}", path, e),
+            EngineError::ImageSize(path, (w, h), (ew, eh)) => {
+                write!(f, "{}: image is {}x{}, expected {}x{}", path, w, h, ew, eh)
+            }
+            EngineError::Gltf(path, e) => write!(f, "{}: {}", path, e),
+            EngineError::PrimitiveMode(path, mesh, mode) => {
+                write!(
+                    f,
+                    "{}: mesh {} has {:?} primitives, only triangles are supported",
+                    path, mesh, mode
+                )
+            }
+            EngineError::Parse(path, e) => write!(f, "{}: {}", path, e),
+        }
+    }
+}
+
+impl std::error::Error for EngineError {}
+
+/// Prints the error and keeps it around to be shown in the ImGui panel.
+pub fn report(e: EngineError) {
+    println!("error: {}", e);
+    if let Ok(mut reported) = REPORTED.lock() {
+        if reported.len() >= MAX_REPORTED {
+            reported.remove(0);
+        }
+        reported.push(e.to_string());
+    }
+}
+
+pub fn reported() -> Vec<String> {
+    REPORTED.lock().map(|r| r.clone()).unwrap_or_default()
+}
+
+pub fn clear_reported() {
+    if let Ok(mut reported) = REPORTED.lock() {
+        reported.clear();
+    }
+}
@@ -0,0 +1,94 @@
+use cgmath::{Vector3, Zero};
+
+use super::{look_at_orientation, Camera, Pose, Projection};
+use crate::black_sheep::{ecs::PositionsAccessor, gamestate::input::InputState};
+
+/// how fast the camera catches up with its target, higher is stiffer
+const STIFFNESS: f32 = 4.0;
+
+/// Position of one entity of the `Positions` system, looked up by its key.
+type EntityLookup = Box<dyn Fn(&PositionsAccessor) -> Option<Vector3<f32>>>;
+
+/// Trails behind an entity of the `Positions` system and keeps looking at it.
+pub struct FollowCamera {
+    positions: PositionsAccessor,
+    /// the followed entity, the first one until `follow` picks another
+    entity: Option<EntityLookup>,
+    /// eye position relative to the target
+    pub offset: Vector3<f32>,
+    pub projection: Projection,
+    target: Vector3<f32>,
+    last: Option<Pose>,
+    current: Option<Pose>,
+}
+
+impl FollowCamera {
+    pub fn new(positions: PositionsAccessor, projection: Projection) -> Self {
+        FollowCamera {
+            positions,
+            entity: None,
+            offset: Vector3::new(0.0, 6.0, 12.0),
+            projection,
+            target: Vector3::zero(),
+            last: None,
+            current: None,
+        }
+    }
+
+    /// Follows the `n`th entity in the current iteration order of the `Positions` system.
+    /// It is kept by key, so adding or removing other entities doesn't switch the target.
+    pub fn follow(&mut self, n: usize) {
+        let key = self.positions.lock().iter().nth(n).map(|(_, key)| key);
+        self.entity = key.map(|key| -> EntityLookup {
+            Box::new(move |positions: &PositionsAccessor| {
+                positions.lock().get(key.clone()).copied()
+            })
+        });
+    }
+
+    /// Falls back to the first entity if the followed one was removed.
+    fn target_position(&mut self) -> Option<Vector3<f32>> {
+        if let Some(p) = self.entity.as_ref().and_then(|e| e(&self.positions)) {
+            return Some(p);
+        }
+        self.follow(0);
+        self.entity.as_ref().and_then(|e| e(&self.positions))
+    }
+}
+
+impl Camera for FollowCamera {
+    fn update(&mut self, _input: &InputState, dt: f32) {
+        let target = match self.target_position() {
+            Some(t) => t,
+            None => return,
+        };
+
+        if self.current.is_none() {
+            self.target = target;
+        } else {
+            self.target += (target - self.target) * (1.0 - (-STIFFNESS * dt).exp());
+        }
+
+        let eye = self.target + self.offset;
+        let pose = Pose {
+            position: eye,
+            orientation: look_at_orientation(eye, self.target),
+        };
+        self.last = self.current.or(Some(pose));
+        self.current = Some(pose);
+    }
+
+    fn pose(&self, i: f32) -> Pose {
+        match (self.last, self.current) {
+            (Some(last), Some(current)) => last.lerp(&current, i),
+            _ => Pose {
+                position: self.offset,
+                orientation: look_at_orientation(self.offset, Vector3::zero()),
+            },
+        }
+    }
+
+    fn projection(&self) -> Projection {
+        self.projection
+    }
+}
@@ -1 +1,117 @@
+pub mod follow;
+pub mod orbit;
+pub mod rig;
 pub mod structs;
+pub mod top_down;
+
+use cgmath::{
+    Deg, InnerSpace, Matrix4, Quaternion, Rad, Rotation3, Vector3, VectorSpace, Zero,
+};
+
+use super::input::InputState;
+use crate::black_sheep::q_i_square_root::q_normalize;
+
+const CAM_SPEED: f32 = 10.0;
+/// degrees per second at full stick deflection
+const LOOK_SPEED: f32 = 120.0;
+
+/// A camera driven by the update loop. Everything that is rendered gets the camera as `&dyn Camera`,
+/// so drawing code doesn't depend on which kind of camera is active.
+pub trait Camera {
+    /// Called once per update tick.
+    fn update(&mut self, input: &InputState, dt: f32);
+
+    /// Relative mouse motion while the mouse is captured.
+    fn on_mouse_motion(&mut self, _xrel: f32, _yrel: f32) {}
+
+    /// Pose interpolated between the last update tick and the next one, `i` is in [0, 1].
+    fn pose(&self, i: f32) -> Pose;
+
+    fn projection(&self) -> Projection;
+
+    fn get_i_view(&self, i: f32) -> Matrix4<f32> {
+        self.pose(i).view()
+    }
+
+    fn projection_matrix(&self, aspect: f32, _i: f32) -> Matrix4<f32> {
+        self.projection().matrix(aspect)
+    }
+}
+
+/// Position of the eye in world space and the rotation from world into view space.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Pose {
+    pub position: Vector3<f32>,
+    pub orientation: Quaternion<f32>,
+}
+
+impl Pose {
+    pub fn view(&self) -> Matrix4<f32> {
+        Matrix4::from(self.orientation) * Matrix4::from_translation(-self.position)
+    }
+
+    pub fn lerp(&self, other: &Pose, t: f32) -> Pose {
+        Pose {
+            position: self.position.lerp(other.position, t),
+            orientation: self.orientation.slerp(other.orientation, t),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Projection {
+    Perspective { fov: Deg<f32>, near: f32, far: f32 },
+    /// `height` is the visible height in world units
+    Orthographic { height: f32, near: f32, far: f32 },
+}
+
+impl Projection {
+    pub fn matrix(&self, aspect: f32) -> Matrix4<f32> {
+        match *self {
+            Projection::Perspective { fov, near, far } => {
+                cgmath::perspective(fov, aspect, near, far)
+            }
+            Projection::Orthographic { height, near, far } => {
+                let h = height / 2.0;
+                let w = h * aspect;
+                cgmath::ortho(-w, w, -h, h, near, far)
+            }
+        }
+    }
+}
+
+/// Rotation into view space for a camera turned by `yaw` around the world up axis
+/// and then tilted down by `pitch`.
+pub fn yaw_pitch_orientation<A: Into<Rad<f32>>>(yaw: A, pitch: A) -> Quaternion<f32> {
+    Quaternion::from_angle_x(pitch) * Quaternion::from_angle_y(yaw)
+}
+
+/// Orientation of a camera at `eye` looking at `target`, without roll.
+pub fn look_at_orientation(eye: Vector3<f32>, target: Vector3<f32>) -> Quaternion<f32> {
+    let f = target - eye;
+    if f.magnitude2() == 0.0 {
+        return yaw_pitch_orientation(Rad(0.0), Rad(0.0));
+    }
+    let f = f.normalize();
+    let pitch = Rad((-f.y).max(-1.0).min(1.0).asin());
+    let yaw = Rad(f.x.atan2(-f.z));
+    yaw_pitch_orientation(yaw, pitch)
+}
+
+/// Reads the "move_x", "move_y" and "move_z" axes as a view space direction of at most length 1,
+/// analog input below full deflection gives a shorter vector.
+pub fn get_movement(input: &InputState) -> Option<Vector3<f32>> {
+    let v = Vector3::new(
+        input.axis("move_x"),
+        input.axis("move_y"),
+        input.axis("move_z"),
+    );
+
+    if v == Vector3::zero() {
+        None
+    } else if v.magnitude2() > 1.0 {
+        Some(q_normalize(v))
+    } else {
+        Some(v)
+    }
+}
@@ -0,0 +1,79 @@
+use cgmath::{Deg, Rad, Rotation, Vector3, Zero};
+
+use super::{yaw_pitch_orientation, Camera, Pose, Projection, LOOK_SPEED};
+use crate::black_sheep::gamestate::input::InputState;
+
+const MIN_DISTANCE: f32 = 1.0;
+/// distance change per second at full "move_z" deflection, relative to the current distance
+const ZOOM_SPEED: f32 = 1.5;
+/// distance change per wheel step, relative to the current distance
+const WHEEL_ZOOM: f32 = 0.1;
+
+/// Circles around `target`, turned with the look axes or the mouse and zoomed with "move_z" or the wheel.
+#[derive(Debug, Clone)]
+pub struct OrbitCamera {
+    pub target: Vector3<f32>,
+    pub distance: f32,
+    pub yaw: Deg<f32>,
+    pub pitch: Deg<f32>,
+    pub projection: Projection,
+    last: Pose,
+}
+
+impl OrbitCamera {
+    pub fn new(target: Vector3<f32>, distance: f32, projection: Projection) -> Self {
+        let mut orbit = OrbitCamera {
+            target,
+            distance,
+            yaw: Deg(0.0),
+            pitch: Deg(35.0),
+            projection,
+            last: Pose {
+                position: Vector3::zero(),
+                orientation: yaw_pitch_orientation(Rad(0.0), Rad(0.0)),
+            },
+        };
+        orbit.last = orbit.current();
+        orbit
+    }
+
+    fn current(&self) -> Pose {
+        let orientation = yaw_pitch_orientation(self.yaw, self.pitch);
+        let forward = orientation.invert().rotate_vector(-Vector3::unit_z());
+        Pose {
+            position: self.target - forward * self.distance,
+            orientation,
+        }
+    }
+
+    fn clamp(&mut self) {
+        self.pitch = Deg(self.pitch.0.max(-89.0).min(89.0));
+        self.distance = self.distance.max(MIN_DISTANCE);
+    }
+}
+
+impl Camera for OrbitCamera {
+    fn update(&mut self, input: &InputState, dt: f32) {
+        self.last = self.current();
+
+        self.yaw += Deg(input.axis("look_x") * LOOK_SPEED * dt);
+        self.pitch += Deg(input.axis("look_y") * LOOK_SPEED * dt);
+        self.distance *= 1.0 + input.axis("move_z") * ZOOM_SPEED * dt;
+        self.distance *= 1.0 - input.wheel() as f32 * WHEEL_ZOOM;
+        self.clamp();
+    }
+
+    fn on_mouse_motion(&mut self, xrel: f32, yrel: f32) {
+        self.yaw += Deg(xrel / 10.0);
+        self.pitch += Deg(yrel / 10.0);
+        self.clamp();
+    }
+
+    fn pose(&self, i: f32) -> Pose {
+        self.last.lerp(&self.current(), i)
+    }
+
+    fn projection(&self) -> Projection {
+        self.projection
+    }
+}
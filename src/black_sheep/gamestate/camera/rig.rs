@@ -0,0 +1,133 @@
+use cgmath::{Matrix4, VectorSpace};
+
+use super::{Camera, Pose, Projection};
+use crate::black_sheep::gamestate::input::InputState;
+
+/// seconds a switch between two cameras takes
+const TRANSITION_TIME: f32 = 0.75;
+
+struct Transition {
+    from: Pose,
+    from_projection: Projection,
+    /// seconds since the switch
+    elapsed: f32,
+}
+
+/// Named cameras of which one is active. Switching blends from the pose of the previous camera
+/// into the new one, the rig itself is a `Camera` that shows the blend.
+pub struct CameraRig {
+    cameras: Vec<(String, Box<dyn Camera>)>,
+    active: usize,
+    transition: Option<Transition>,
+    pub transition_time: f32,
+    dt: f32,
+}
+
+impl CameraRig {
+    pub fn new(name: &str, camera: Box<dyn Camera>) -> Self {
+        CameraRig {
+            cameras: vec![(String::from(name), camera)],
+            active: 0,
+            transition: None,
+            transition_time: TRANSITION_TIME,
+            dt: 1.0 / 25.0,
+        }
+    }
+
+    /// Adds a camera or replaces the one with the same name.
+    pub fn add(&mut self, name: &str, camera: Box<dyn Camera>) {
+        if let Some(i) = self.index_of(name) {
+            self.cameras[i].1 = camera;
+        } else {
+            self.cameras.push((String::from(name), camera));
+        }
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.cameras.iter().map(|(name, _)| name.as_str())
+    }
+
+    pub fn active(&self) -> usize {
+        self.active
+    }
+
+    pub fn active_name(&self) -> &str {
+        &self.cameras[self.active].0
+    }
+
+    pub fn active_camera(&mut self) -> &mut dyn Camera {
+        self.cameras[self.active].1.as_mut()
+    }
+
+    pub fn index_of(&self, name: &str) -> Option<usize> {
+        self.cameras.iter().position(|(n, _)| n == name)
+    }
+
+    pub fn switch_to(&mut self, index: usize) {
+        if index == self.active || index >= self.cameras.len() {
+            return;
+        }
+        self.transition = Some(Transition {
+            from: self.pose(0.0),
+            from_projection: self.cameras[self.active].1.projection(),
+            elapsed: 0.0,
+        });
+        self.active = index;
+    }
+
+    pub fn switch_to_name(&mut self, name: &str) {
+        if let Some(i) = self.index_of(name) {
+            self.switch_to(i);
+        }
+    }
+
+    pub fn switch_next(&mut self) {
+        self.switch_to((self.active + 1) % self.cameras.len());
+    }
+
+    /// progress of the running transition in [0, 1], eased at both ends
+    fn blend(&self, transition: &Transition, i: f32) -> f32 {
+        let t = ((transition.elapsed + self.dt * i) / self.transition_time).min(1.0);
+        t * t * (3.0 - 2.0 * t)
+    }
+}
+
+impl Camera for CameraRig {
+    fn update(&mut self, input: &InputState, dt: f32) {
+        self.dt = dt;
+        if let Some(transition) = &mut self.transition {
+            transition.elapsed += dt;
+            if transition.elapsed >= self.transition_time {
+                self.transition = None;
+            }
+        }
+        self.active_camera().update(input, dt);
+    }
+
+    fn on_mouse_motion(&mut self, xrel: f32, yrel: f32) {
+        self.active_camera().on_mouse_motion(xrel, yrel);
+    }
+
+    fn pose(&self, i: f32) -> Pose {
+        let pose = self.cameras[self.active].1.pose(i);
+        match &self.transition {
+            Some(transition) => transition.from.lerp(&pose, self.blend(transition, i)),
+            None => pose,
+        }
+    }
+
+    fn projection(&self) -> Projection {
+        self.cameras[self.active].1.projection()
+    }
+
+    fn projection_matrix(&self, aspect: f32, i: f32) -> Matrix4<f32> {
+        let projection = self.projection().matrix(aspect);
+        match &self.transition {
+            Some(transition) if transition.from_projection != self.projection() => transition
+                .from_projection
+                .matrix(aspect)
+                .lerp(projection, self.blend(transition, i)),
+            _ => projection,
+        }
+    }
+}
@@ -1,58 +1,76 @@
-use cgmath::{Matrix4, Quaternion, Rad, Rotation3, Vector3};
+use cgmath::{Deg, Quaternion, Rotation, Vector3, VectorSpace, Zero};
 
-use crate::black_sheep::settings::UPS_F32;
+use super::{get_movement, yaw_pitch_orientation, Camera, Pose, Projection};
+use crate::black_sheep::{gamestate::input::InputState, settings::CameraSettings};
 
+const MAX_PITCH: f32 = 89.0;
+
+/// First person camera. The orientation is kept as yaw and pitch, so it never rolls
+/// and can't tip over the poles.
 #[derive(Debug)]
 pub struct FlyingEye {
+    /// eye position in world space
     pub position: Vector3<f32>,
-    pub movement: Option<Vector3<f32>>,
-    pub orientation: Quaternion<f32>,
+    pub velocity: Vector3<f32>,
+    /// rotation around the world up axis, positive turns right
+    pub yaw: Deg<f32>,
+    /// positive looks down, clamped to +-89°
+    pub pitch: Deg<f32>,
+    pub settings: CameraSettings,
+    pub projection: Projection,
+    /// length of one update tick in seconds
+    pub dt: f32,
+    last_position: Vector3<f32>,
 }
 
 impl FlyingEye {
     pub fn new() -> Self {
         FlyingEye {
-            position: Vector3::new(0.0, 0.0, 0.0),
-            movement: None,
-            orientation: Quaternion::new(1.0, 0.0, 0.0, 0.0),
+            position: Vector3::zero(),
+            velocity: Vector3::zero(),
+            yaw: Deg(0.0),
+            pitch: Deg(0.0),
+            settings: CameraSettings::default(),
+            projection: Projection::Perspective {
+                fov: Deg(80.0),
+                near: 0.1,
+                far: 1000.0,
+            },
+            dt: 1.0 / 25.0,
+            last_position: Vector3::zero(),
         }
     }
+
+    pub fn orientation(&self) -> Quaternion<f32> {
+        yaw_pitch_orientation(self.yaw, self.pitch)
+    }
+
+    /// Moves by `v` given in view space, without interpolation.
     pub fn move_cam(&mut self, v: Vector3<f32>) {
-        self.position -= (Matrix4::from(self.orientation.conjugate()) * v.extend(1.0)).truncate();
-    }
-    pub fn set_movement(&mut self, v: Vector3<f32>) {
-        self.movement =
-            Some((Matrix4::from(self.orientation.conjugate()) * (-v).extend(1.0)).truncate());
-    }
-    pub fn reset_movement(&mut self) {
-        self.movement = None;
-    }
-    pub fn rotate_h<A>(&mut self, theta: A)
-    where
-        A: Into<Rad<f32>>,
-    {
-        self.orientation = Quaternion::from_angle_x(theta) * self.orientation;
-    }
-    pub fn rotate_v<A>(&mut self, theta: A)
-    where
-        A: Into<Rad<f32>>,
-    {
-        self.orientation = self.orientation * Quaternion::from_angle_y(theta);
-    }
-    pub fn update(&mut self) {
-        if let Some(v) = self.movement {
-            self.position += v / UPS_F32;
-        }
+        self.position += self.orientation().invert().rotate_vector(v);
+        self.last_position = self.position;
+    }
+
+    pub fn rotate_h<A: Into<Deg<f32>>>(&mut self, theta: A) {
+        self.pitch = Deg((self.pitch + theta.into()).0.max(-MAX_PITCH).min(MAX_PITCH));
     }
-    pub fn get_i_view(&self, i: f32) -> Matrix4<f32> {
-        let t = if let Some(v) = self.movement {
-            Matrix4::from_translation(self.position + ((v / UPS_F32) * i))
+
+    pub fn rotate_v<A: Into<Deg<f32>>>(&mut self, theta: A) {
+        self.yaw = Deg((self.yaw + theta.into()).0 % 360.0);
+    }
+
+    /// Steers the velocity towards `wish` (world space), speeding up with `acceleration`
+    /// and slowing down with `damping`, both as rates per second.
+    fn steer(&mut self, wish: Vector3<f32>, dt: f32) {
+        let rate = if wish == Vector3::zero() {
+            self.settings.damping
         } else {
-            Matrix4::from_translation(self.position)
+            self.settings.acceleration
         };
-        let r = Matrix4::from(self.orientation);
-
-        r * t
+        self.velocity += (wish - self.velocity) * (1.0 - (-rate * dt).exp());
+        if self.velocity.x.abs() + self.velocity.y.abs() + self.velocity.z.abs() < 1e-4 {
+            self.velocity = Vector3::zero();
+        }
     }
 }
 
@@ -61,3 +79,145 @@ impl Default for FlyingEye {
         Self::new()
     }
 }
+
+impl Camera for FlyingEye {
+    fn update(&mut self, input: &InputState, dt: f32) {
+        self.dt = dt;
+
+        let look = self.settings.look_speed * dt;
+        self.rotate_v(Deg(input.axis("look_x") * look));
+        self.rotate_h(Deg(input.axis("look_y") * look * self.settings.y_sign()));
+
+        let wish = get_movement(input)
+            .map(|v| self.orientation().invert().rotate_vector(v) * self.settings.speed)
+            .unwrap_or_else(Vector3::zero);
+        self.steer(wish, dt);
+
+        self.last_position = self.position;
+        self.position += self.velocity * dt;
+    }
+
+    fn on_mouse_motion(&mut self, xrel: f32, yrel: f32) {
+        let s = self.settings.mouse_sensitivity;
+        self.rotate_v(Deg(xrel * s));
+        self.rotate_h(Deg(yrel * s * self.settings.y_sign()));
+    }
+
+    fn pose(&self, i: f32) -> Pose {
+        Pose {
+            position: self.last_position.lerp(self.position, i),
+            orientation: self.orientation(),
+        }
+    }
+
+    fn projection(&self) -> Projection {
+        self.projection
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cgmath::{Matrix4, Vector4};
+
+    use super::*;
+
+    const EPSILON: f32 = 1e-5;
+
+    fn view(eye: &FlyingEye) -> Matrix4<f32> {
+        eye.pose(1.0).view()
+    }
+
+    /// `p` in view space, the camera looks down -z.
+    fn to_view(eye: &FlyingEye, p: Vector3<f32>) -> Vector3<f32> {
+        (view(eye) * p.extend(1.0)).truncate()
+    }
+
+    fn assert_close(a: Vector3<f32>, b: Vector3<f32>) {
+        assert!(
+            (a.x - b.x).abs() < EPSILON
+                && (a.y - b.y).abs() < EPSILON
+                && (a.z - b.z).abs() < EPSILON,
+            "{:?} != {:?}",
+            a,
+            b
+        );
+    }
+
+    #[test]
+    fn view_without_rotation_looks_down_negative_z() {
+        let mut eye = FlyingEye::new();
+        eye.move_cam(Vector3::new(1.0, 2.0, 3.0));
+
+        assert_close(to_view(&eye, Vector3::new(1.0, 2.0, 3.0)), Vector3::zero());
+        assert_close(
+            to_view(&eye, Vector3::new(1.0, 2.0, 2.0)),
+            Vector3::new(0.0, 0.0, -1.0),
+        );
+        assert_close(
+            to_view(&eye, Vector3::new(2.0, 2.0, 3.0)),
+            Vector3::new(1.0, 0.0, 0.0),
+        );
+    }
+
+    #[test]
+    fn view_yawed_90_degrees_looks_down_positive_x() {
+        let mut eye = FlyingEye::new();
+        eye.rotate_v(Deg(90.0));
+
+        assert_close(
+            to_view(&eye, Vector3::new(1.0, 0.0, 0.0)),
+            Vector3::new(0.0, 0.0, -1.0),
+        );
+        assert_close(
+            to_view(&eye, Vector3::new(0.0, 0.0, 1.0)),
+            Vector3::new(1.0, 0.0, 0.0),
+        );
+        assert_close(
+            to_view(&eye, Vector3::new(0.0, 1.0, 0.0)),
+            Vector3::new(0.0, 1.0, 0.0),
+        );
+    }
+
+    #[test]
+    fn pitch_is_clamped_when_input_pushes_past_it() {
+        let mut eye = FlyingEye::new();
+        // 120° per tick with the default look speed
+        let down = InputState::default().with_axis("look_y", 1.0);
+        for _ in 0..3 {
+            eye.update(&down, 1.0);
+        }
+        assert_eq!(eye.pitch, Deg(MAX_PITCH));
+        // looking almost straight down, not flipped over the pole
+        let below = view(&eye) * Vector4::new(0.0, -1.0, 0.0, 0.0);
+        assert!(below.z < -0.99 && below.y < 0.0, "{:?}", below);
+
+        let up = InputState::default().with_axis("look_y", -1.0);
+        for _ in 0..3 {
+            eye.update(&up, 1.0);
+        }
+        assert_eq!(eye.pitch, Deg(-MAX_PITCH));
+
+        eye.on_mouse_motion(0.0, 10_000.0);
+        assert_eq!(eye.pitch, Deg(MAX_PITCH));
+    }
+
+    #[test]
+    fn velocity_is_damped_to_zero_without_input() {
+        let mut eye = FlyingEye::new();
+        eye.velocity = Vector3::new(5.0, 0.0, -3.0);
+        let idle = InputState::default();
+
+        let mut last_speed = f32::INFINITY;
+        for _ in 0..200 {
+            eye.update(&idle, 1.0 / 25.0);
+            let speed = eye.velocity.x.abs() + eye.velocity.z.abs();
+            assert!(speed <= last_speed);
+            last_speed = speed;
+        }
+        assert_eq!(eye.velocity, Vector3::zero());
+
+        let resting = eye.position;
+        eye.update(&idle, 1.0 / 25.0);
+        assert_eq!(eye.position, resting);
+    }
+}
@@ -0,0 +1,67 @@
+use cgmath::{Deg, Vector3};
+
+use super::{yaw_pitch_orientation, Camera, Pose, Projection, CAM_SPEED};
+use crate::black_sheep::gamestate::input::InputState;
+
+const EYE_HEIGHT: f32 = 100.0;
+const MIN_HEIGHT: f32 = 2.0;
+/// visible height change per wheel step, relative to the current height
+const WHEEL_ZOOM: f32 = 0.1;
+
+/// Orthographic camera looking straight down, panned with "move_x"/"move_z" and zoomed with
+/// "move_y" or the wheel.
+#[derive(Debug, Clone)]
+pub struct TopDownCamera {
+    /// point on the ground in the middle of the view
+    pub center: Vector3<f32>,
+    /// visible height in world units
+    pub height: f32,
+    pub near: f32,
+    pub far: f32,
+    velocity: Vector3<f32>,
+    dt: f32,
+}
+
+impl TopDownCamera {
+    pub fn new(center: Vector3<f32>, height: f32) -> Self {
+        TopDownCamera {
+            center,
+            height,
+            near: 0.1,
+            far: EYE_HEIGHT * 2.0,
+            velocity: Vector3::new(0.0, 0.0, 0.0),
+            dt: 1.0 / 25.0,
+        }
+    }
+}
+
+impl Camera for TopDownCamera {
+    fn update(&mut self, input: &InputState, dt: f32) {
+        self.dt = dt;
+        self.center += self.velocity * dt;
+
+        // pan faster when zoomed out
+        let speed = CAM_SPEED * self.height / 20.0;
+        self.velocity = Vector3::new(input.axis("move_x"), 0.0, input.axis("move_z")) * speed;
+
+        self.height *= 1.0 - input.axis("move_y") * dt;
+        self.height *= 1.0 - input.wheel() as f32 * WHEEL_ZOOM;
+        self.height = self.height.max(MIN_HEIGHT);
+    }
+
+    fn pose(&self, i: f32) -> Pose {
+        let center = self.center + self.velocity * (self.dt * i);
+        Pose {
+            position: Vector3::new(center.x, center.y + EYE_HEIGHT, center.z),
+            orientation: yaw_pitch_orientation(Deg(0.0), Deg(90.0)),
+        }
+    }
+
+    fn projection(&self) -> Projection {
+        Projection::Orthographic {
+            height: self.height,
+            near: self.near,
+            far: self.far,
+        }
+    }
+}
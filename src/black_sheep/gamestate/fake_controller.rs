@@ -0,0 +1,204 @@
+use sdl2::{
+    controller::{Axis, Button},
+    event::Event,
+};
+use serde::{Deserialize, Serialize};
+
+use super::input::RawInput;
+use crate::black_sheep::error::{self, EngineError, Result};
+
+/// joystick instance id used for the generated events
+const FAKE_INSTANCE_ID: u32 = u32::MAX;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum FakeInput {
+    /// SDL game controller button name like "a" or "dpup"
+    Button { button: String, down: bool },
+    /// SDL game controller axis name like "leftx", value in [-1, 1]
+    Axis { axis: String, value: f32 },
+}
+
+/// Scripted controller that sends the same SDL events as a real one, keyed by update tick.
+/// Rewinding it with `reset` replays the exact same input, e.g. for headless test runs.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FakeController {
+    pub inputs: Vec<(u64, FakeInput)>,
+    #[serde(skip)]
+    tick: u64,
+}
+
+impl FakeController {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn press(mut self, tick: u64, button: &str) -> Self {
+        self.push(tick, FakeInput::Button {
+            button: String::from(button),
+            down: true,
+        });
+        self
+    }
+
+    pub fn release(mut self, tick: u64, button: &str) -> Self {
+        self.push(tick, FakeInput::Button {
+            button: String::from(button),
+            down: false,
+        });
+        self
+    }
+
+    pub fn axis(mut self, tick: u64, axis: &str, value: f32) -> Self {
+        self.push(tick, FakeInput::Axis {
+            axis: String::from(axis),
+            value,
+        });
+        self
+    }
+
+    fn push(&mut self, tick: u64, input: FakeInput) {
+        let i = self.inputs.partition_point(|(t, _)| *t <= tick);
+        self.inputs.insert(i, (tick, input));
+    }
+
+    /// Fails with `UnknownBinding` if the script names a button or axis SDL doesn't know.
+    pub fn load(path: &str) -> Result<Self> {
+        let s = std::fs::read_to_string(path).map_err(|e| EngineError::Io(String::from(path), e))?;
+        let mut fake: FakeController =
+            ron::from_str(&s).map_err(|e| EngineError::Parse(String::from(path), e))?;
+        for (_, input) in fake.inputs.iter() {
+            to_event(input)?;
+        }
+        fake.inputs.sort_by_key(|(t, _)| *t);
+        Ok(fake)
+    }
+
+    pub fn save(&self, path: &str) -> std::io::Result<()> {
+        let s = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default()).unwrap();
+        std::fs::write(path, s)
+    }
+
+    pub fn reset(&mut self) {
+        self.tick = 0;
+    }
+
+    /// Sends the inputs of the current tick to `raw` and advances to the next tick.
+    pub fn feed(&mut self, raw: &mut RawInput) {
+        let tick = self.tick;
+        for (_, input) in self.inputs.iter().filter(|(t, _)| *t == tick) {
+            match to_event(input) {
+                Ok(event) => raw.handle_event(&event),
+                Err(e) => error::report(e),
+            }
+        }
+        self.tick += 1;
+    }
+}
+
+fn to_event(input: &FakeInput) -> Result<Event> {
+    let unknown = || EngineError::UnknownBinding(format!("{:?}", input));
+    Ok(match input {
+        FakeInput::Button { button, down } => {
+            let button = Button::from_string(button).ok_or_else(unknown)?;
+            if *down {
+                Event::ControllerButtonDown {
+                    timestamp: 0,
+                    which: FAKE_INSTANCE_ID,
+                    button,
+                }
+            } else {
+                Event::ControllerButtonUp {
+                    timestamp: 0,
+                    which: FAKE_INSTANCE_ID,
+                    button,
+                }
+            }
+        }
+        FakeInput::Axis { axis, value } => Event::ControllerAxisMotion {
+            timestamp: 0,
+            which: FAKE_INSTANCE_ID,
+            axis: Axis::from_string(axis).ok_or_else(unknown)?,
+            value: (value.max(-1.0).min(1.0) * i16::MAX as f32) as i16,
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use cgmath::Deg;
+
+    use super::*;
+    use crate::black_sheep::gamestate::{
+        camera::{structs::FlyingEye, Camera},
+        input::{InputMap, InputState},
+    };
+
+    const DT: f32 = 1.0 / 25.0;
+    const EPSILON: f32 = 1e-3;
+
+    /// Feeds the script through the default bindings into a camera, one tick at a time.
+    fn fly(fake: &mut FakeController, ticks: u64) -> FlyingEye {
+        let map = InputMap::default();
+        let mut raw = RawInput::default();
+        let mut input = InputState::default();
+        let mut eye = FlyingEye::new();
+        for _ in 0..ticks {
+            fake.feed(&mut raw);
+            input = map.update(&mut raw, &input);
+            eye.update(&input, DT);
+        }
+        eye
+    }
+
+    #[test]
+    fn scripted_stick_moves_and_pitches_the_camera() {
+        // full forward for 10 ticks, then looking down for 10 ticks
+        let mut fake = FakeController::new()
+            .axis(0, "lefty", -1.0)
+            .axis(10, "lefty", 0.0)
+            .axis(10, "righty", 1.0)
+            .axis(20, "righty", 0.0);
+        let eye = fly(&mut fake, 30);
+
+        // 120° per second at full deflection
+        assert!((eye.pitch.0 - 48.0).abs() < EPSILON, "{:?}", eye.pitch);
+        assert_eq!(eye.yaw, Deg(0.0));
+        assert!(eye.position.z < -1.0, "{:?}", eye.position);
+        assert!(eye.position.x.abs() < EPSILON && eye.position.y.abs() < EPSILON);
+    }
+
+    #[test]
+    fn reset_replays_the_same_input() {
+        let mut fake = FakeController::new()
+            .axis(0, "leftx", 0.8)
+            .press(3, "y")
+            .axis(5, "rightx", -1.0)
+            .release(6, "y");
+        let first = fly(&mut fake, 20);
+        fake.reset();
+        let second = fly(&mut fake, 20);
+        assert_eq!(first.position, second.position);
+        assert_eq!(first.yaw, second.yaw);
+    }
+
+    #[test]
+    fn fly_script_turns_and_climbs() {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/res/input/fake_fly.ron");
+        let mut fake = FakeController::load(path).unwrap();
+        let eye = fly(&mut fake, 100);
+
+        // half deflection to the right for a second, the stick value is quantized to an i16
+        assert!((eye.yaw.0 - 60.0).abs() < 0.01, "{:?}", eye.yaw);
+        assert_eq!(eye.pitch, Deg(0.0));
+        assert!(eye.position.y > 1.0, "{:?}", eye.position);
+        assert!(eye.position.z < -1.0, "{:?}", eye.position);
+    }
+
+    #[test]
+    fn load_rejects_unknown_names() {
+        let path = std::env::temp_dir().join("last_engine_fake_unknown.ron");
+        std::fs::write(&path, "(inputs: [(0, Button(button: \"nope\", down: true))])").unwrap();
+        let result = FakeController::load(path.to_str().unwrap());
+        assert!(matches!(result, Err(EngineError::UnknownBinding(_))), "{:?}", result);
+    }
+}
@@ -0,0 +1,555 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+use cgmath::{Vector2, Zero};
+use sdl2::{
+    controller::{Axis, Button},
+    event::Event,
+    keyboard::Scancode,
+    mouse::MouseButton,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::black_sheep::error::{EngineError, Result};
+
+/// Analog values below this are treated as 0.
+const DEFAULT_DEADZONE: f32 = 0.15;
+/// An analog axis bound to an action counts as held above this value.
+const AXIS_PRESS_THRESHOLD: f32 = 0.5;
+
+/// One physical input as written in the bindings file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Binding {
+    /// SDL scancode name like "W" or "Left Shift", keys keep their position on every layout
+    Key(String),
+    /// "Left", "Middle", "Right", "X1" or "X2"
+    Mouse(String),
+    WheelUp,
+    WheelDown,
+    /// SDL game controller button name like "a", "start" or "dpup"
+    Button(String),
+    /// SDL game controller axis name like "leftx" or "righttrigger"
+    Axis(String),
+}
+
+/// An axis is the sum of its positive bindings minus its negative bindings, clamped to [-1, 1].
+/// Keys and buttons count as 1, controller axes add their analog value.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AxisBinding {
+    #[serde(default)]
+    pub positive: Vec<Binding>,
+    #[serde(default)]
+    pub negative: Vec<Binding>,
+}
+
+/// Content of the bindings file. An action is held if all bindings of one of its chords are held.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Bindings {
+    pub actions: BTreeMap<String, Vec<Vec<Binding>>>,
+    pub axes: BTreeMap<String, AxisBinding>,
+    pub deadzone: f32,
+}
+
+impl Default for Bindings {
+    fn default() -> Self {
+        let key = |k: &str| Binding::Key(String::from(k));
+        let stick = |a: &str| Binding::Axis(String::from(a));
+        let axis = |positive, negative| AxisBinding { positive, negative };
+
+        let mut actions = BTreeMap::new();
+        actions.insert(String::from("quit"), vec![vec![key("Escape")]]);
+        actions.insert(
+            String::from("next_camera"),
+            vec![vec![key("C")], vec![Binding::Button(String::from("y"))]],
+        );
+        actions.insert(
+            String::from("capture_mouse"),
+            vec![vec![Binding::Mouse(String::from("Right"))]],
+        );
+
+        // Z is the key below A, where QWERTZ layouts have their Y
+        let mut axes = BTreeMap::new();
+        axes.insert(
+            String::from("move_x"),
+            axis(vec![key("D"), stick("leftx")], vec![key("A")]),
+        );
+        axes.insert(
+            String::from("move_y"),
+            axis(
+                vec![key("X"), stick("righttrigger")],
+                vec![key("Z"), stick("lefttrigger")],
+            ),
+        );
+        axes.insert(
+            String::from("move_z"),
+            axis(vec![key("S"), stick("lefty")], vec![key("W")]),
+        );
+        axes.insert(
+            String::from("look_x"),
+            axis(vec![key("Right"), stick("rightx")], vec![key("Left")]),
+        );
+        axes.insert(
+            String::from("look_y"),
+            axis(vec![key("Down"), stick("righty")], vec![key("Up")]),
+        );
+
+        Bindings {
+            actions,
+            axes,
+            deadzone: DEFAULT_DEADZONE,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Input {
+    Key(Scancode),
+    Mouse(MouseButton),
+    WheelUp,
+    WheelDown,
+    Button(Button),
+    Axis(Axis),
+}
+
+impl Input {
+    fn resolve(binding: &Binding) -> Result<Self> {
+        let unknown = || EngineError::UnknownBinding(format!("{:?}", binding));
+        Ok(match binding {
+            Binding::Key(name) => Input::Key(Scancode::from_name(name).ok_or_else(unknown)?),
+            Binding::Mouse(name) => Input::Mouse(match name.as_str() {
+                "Left" => MouseButton::Left,
+                "Middle" => MouseButton::Middle,
+                "Right" => MouseButton::Right,
+                "X1" => MouseButton::X1,
+                "X2" => MouseButton::X2,
+                _ => return Err(unknown()),
+            }),
+            Binding::WheelUp => Input::WheelUp,
+            Binding::WheelDown => Input::WheelDown,
+            Binding::Button(name) => Input::Button(Button::from_string(name).ok_or_else(unknown)?),
+            Binding::Axis(name) => Input::Axis(Axis::from_string(name).ok_or_else(unknown)?),
+        })
+    }
+
+    fn value(&self, raw: &RawInput) -> f32 {
+        let digital = |held: bool| if held { 1.0 } else { 0.0 };
+        match self {
+            Input::Key(k) => digital(raw.keys.contains(k) || raw.tapped_keys.contains(k)),
+            Input::Mouse(b) => digital(raw.mouse.contains(b) || raw.tapped_mouse.contains(b)),
+            Input::WheelUp => digital(raw.wheel > 0),
+            Input::WheelDown => digital(raw.wheel < 0),
+            Input::Button(b) => digital(
+                raw.buttons.iter().any(|(_, held)| held == b) || raw.tapped_buttons.contains(b),
+            ),
+            Input::Axis(a) => raw.axis(*a),
+        }
+    }
+
+    fn held(&self, raw: &RawInput) -> bool {
+        self.value(raw) > AXIS_PRESS_THRESHOLD
+    }
+}
+
+/// Device state collected from SDL events between two update ticks.
+#[derive(Debug, Clone, Default)]
+pub struct RawInput {
+    keys: HashSet<Scancode>,
+    mouse: HashSet<MouseButton>,
+    /// held buttons and axis values by controller instance id
+    buttons: HashSet<(u32, Button)>,
+    axes: HashMap<(u32, Axis), f32>,
+    /// went down since the last tick, so a tap between two ticks still counts as held once
+    tapped_keys: HashSet<Scancode>,
+    tapped_mouse: HashSet<MouseButton>,
+    tapped_buttons: HashSet<Button>,
+    deadzone: f32,
+    wheel: i32,
+    mouse_delta: Vector2<f32>,
+}
+
+impl RawInput {
+    pub fn handle_event(&mut self, event: &Event) {
+        match event {
+            Event::KeyDown {
+                scancode: Some(s),
+                repeat: false,
+                ..
+            } => {
+                self.keys.insert(*s);
+                self.tapped_keys.insert(*s);
+            }
+            Event::KeyUp {
+                scancode: Some(s), ..
+            } => {
+                self.keys.remove(s);
+            }
+            Event::MouseButtonDown { mouse_btn, .. } => {
+                self.mouse.insert(*mouse_btn);
+                self.tapped_mouse.insert(*mouse_btn);
+            }
+            Event::MouseButtonUp { mouse_btn, .. } => {
+                self.mouse.remove(mouse_btn);
+            }
+            Event::MouseWheel { y, .. } => self.wheel += y,
+            Event::MouseMotion { xrel, yrel, .. } => {
+                self.mouse_delta += Vector2::new(*xrel as f32, *yrel as f32);
+            }
+            Event::ControllerButtonDown { which, button, .. } => {
+                self.buttons.insert((*which, *button));
+                self.tapped_buttons.insert(*button);
+            }
+            Event::ControllerButtonUp { which, button, .. } => {
+                self.buttons.remove(&(*which, *button));
+            }
+            Event::ControllerAxisMotion {
+                which, axis, value, ..
+            } => {
+                self.axes.insert((*which, *axis), *value as f32 / i16::MAX as f32);
+            }
+            // releases what the removed controller held, the others keep their state
+            Event::ControllerDeviceRemoved { which, .. } => {
+                self.buttons.retain(|(instance, _)| instance != which);
+                self.axes.retain(|(instance, _), _| instance != which);
+            }
+            _ => (),
+        }
+    }
+
+    /// The value of the controller that deflects `axis` the most.
+    fn axis(&self, axis: Axis) -> f32 {
+        let v = self
+            .axes
+            .iter()
+            .filter(|((_, a), _)| *a == axis)
+            .map(|(_, v)| v.max(-1.0))
+            .fold(0.0, |max: f32, v| if v.abs() > max.abs() { v } else { max });
+        if v.abs() < self.deadzone {
+            0.0
+        } else {
+            v
+        }
+    }
+
+    /// Wheel, mouse motion and taps only count for one tick.
+    fn end_tick(&mut self) {
+        self.wheel = 0;
+        self.mouse_delta = Vector2::zero();
+        self.tapped_keys.clear();
+        self.tapped_mouse.clear();
+        self.tapped_buttons.clear();
+    }
+}
+
+/// Resolved `Bindings`, turns the `RawInput` into an `InputState` once per tick.
+pub struct InputMap {
+    bindings: Bindings,
+    actions: Vec<(String, Vec<Vec<Input>>)>,
+    axes: Vec<(String, Vec<Input>, Vec<Input>)>,
+}
+
+impl Default for InputMap {
+    fn default() -> Self {
+        InputMap::new(Bindings::default()).expect("built in bindings are valid")
+    }
+}
+
+impl InputMap {
+    pub fn new(bindings: Bindings) -> Result<Self> {
+        let resolve_all =
+            |b: &Vec<Binding>| b.iter().map(Input::resolve).collect::<Result<Vec<_>>>();
+
+        let mut actions = Vec::new();
+        for (name, chords) in bindings.actions.iter() {
+            let chords = chords.iter().map(resolve_all).collect::<Result<Vec<_>>>()?;
+            actions.push((name.clone(), chords));
+        }
+
+        let mut axes = Vec::new();
+        for (name, axis) in bindings.axes.iter() {
+            axes.push((
+                name.clone(),
+                resolve_all(&axis.positive)?,
+                resolve_all(&axis.negative)?,
+            ));
+        }
+
+        Ok(InputMap {
+            bindings,
+            actions,
+            axes,
+        })
+    }
+
+    pub fn load(path: &str) -> Result<Self> {
+        let s = std::fs::read_to_string(path).map_err(|e| EngineError::Io(String::from(path), e))?;
+        let bindings = ron::from_str(&s).map_err(|e| EngineError::Parse(String::from(path), e))?;
+        InputMap::new(bindings)
+    }
+
+    pub fn save(&self, path: &str) -> std::io::Result<()> {
+        let s = ron::ser::to_string_pretty(&self.bindings, ron::ser::PrettyConfig::default())
+            .unwrap();
+        std::fs::write(path, s)
+    }
+
+    pub fn bindings(&self) -> &Bindings {
+        &self.bindings
+    }
+
+    /// Replaces the chords of an action, the old bindings stay if one of the new ones is unknown.
+    pub fn rebind(&mut self, action: &str, chords: Vec<Vec<Binding>>) -> Result<()> {
+        let mut bindings = self.bindings.clone();
+        bindings.actions.insert(String::from(action), chords);
+        *self = InputMap::new(bindings)?;
+        Ok(())
+    }
+
+    pub fn rebind_axis(&mut self, axis: &str, binding: AxisBinding) -> Result<()> {
+        let mut bindings = self.bindings.clone();
+        bindings.axes.insert(String::from(axis), binding);
+        *self = InputMap::new(bindings)?;
+        Ok(())
+    }
+
+    /// Checks an action directly against the device state, for things that can't wait for the next tick.
+    pub fn held(&self, action: &str, raw: &RawInput) -> bool {
+        self.actions
+            .iter()
+            .find(|(name, _)| name == action)
+            .map_or(false, |(_, chords)| chord_held(chords, raw))
+    }
+
+    /// Evaluates all actions and axes and resets the per tick deltas of `raw`.
+    pub fn update(&self, raw: &mut RawInput, previous: &InputState) -> InputState {
+        raw.deadzone = self.bindings.deadzone;
+
+        let mut state = InputState {
+            mouse_delta: raw.mouse_delta.into(),
+            wheel: raw.wheel,
+            ..Default::default()
+        };
+
+        for (name, chords) in self.actions.iter() {
+            if chord_held(chords, raw) {
+                state.held.insert(name.clone());
+                if !previous.held(name) {
+                    state.pressed.insert(name.clone());
+                }
+            } else if previous.held(name) {
+                state.released.insert(name.clone());
+            }
+        }
+
+        for (name, positive, negative) in self.axes.iter() {
+            let p: f32 = positive.iter().map(|i| i.value(raw)).sum();
+            let n: f32 = negative.iter().map(|i| i.value(raw)).sum();
+            let v = (p - n).max(-1.0).min(1.0);
+            if v != 0.0 {
+                state.axes.insert(name.clone(), v);
+            }
+        }
+
+        raw.end_tick();
+        state
+    }
+}
+
+fn chord_held(chords: &[Vec<Input>], raw: &RawInput) -> bool {
+    chords
+        .iter()
+        .any(|chord| !chord.is_empty() && chord.iter().all(|i| i.held(raw)))
+}
+
+/// Input of one update tick, handed to `Logic::update`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct InputState {
+    held: BTreeSet<String>,
+    pressed: BTreeSet<String>,
+    released: BTreeSet<String>,
+    axes: BTreeMap<String, f32>,
+    mouse_delta: [f32; 2],
+    wheel: i32,
+}
+
+impl InputState {
+    pub fn held(&self, action: &str) -> bool {
+        self.held.contains(action)
+    }
+
+    /// true only in the tick the action started being held
+    pub fn pressed(&self, action: &str) -> bool {
+        self.pressed.contains(action)
+    }
+
+    pub fn released(&self, action: &str) -> bool {
+        self.released.contains(action)
+    }
+
+    pub fn axis(&self, axis: &str) -> f32 {
+        self.axes.get(axis).copied().unwrap_or(0.0)
+    }
+
+    pub fn mouse_delta(&self) -> Vector2<f32> {
+        Vector2::from(self.mouse_delta)
+    }
+
+    pub fn wheel(&self) -> i32 {
+        self.wheel
+    }
+
+    #[cfg(test)]
+    pub fn with_axis(mut self, axis: &str, value: f32) -> Self {
+        self.axes.insert(axis.to_string(), value);
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sdl2::keyboard::Mod;
+
+    use super::*;
+
+    fn key(scancode: Scancode, down: bool) -> Event {
+        if down {
+            Event::KeyDown {
+                timestamp: 0,
+                window_id: 0,
+                keycode: None,
+                scancode: Some(scancode),
+                keymod: Mod::empty(),
+                repeat: false,
+            }
+        } else {
+            Event::KeyUp {
+                timestamp: 0,
+                window_id: 0,
+                keycode: None,
+                scancode: Some(scancode),
+                keymod: Mod::empty(),
+                repeat: false,
+            }
+        }
+    }
+
+    fn stick(axis: Axis, value: f32) -> Event {
+        Event::ControllerAxisMotion {
+            timestamp: 0,
+            which: 0,
+            axis,
+            value: (value * i16::MAX as f32) as i16,
+        }
+    }
+
+    fn button(button: Button) -> Event {
+        Event::ControllerButtonDown {
+            timestamp: 0,
+            which: 0,
+            button,
+        }
+    }
+
+    /// Handles `events` and evaluates one tick.
+    fn tick(
+        map: &InputMap,
+        raw: &mut RawInput,
+        previous: &InputState,
+        events: &[Event],
+    ) -> InputState {
+        for event in events.iter() {
+            raw.handle_event(event);
+        }
+        map.update(raw, previous)
+    }
+
+    #[test]
+    fn chord_needs_all_of_its_bindings() {
+        let mut map = InputMap::default();
+        let boost = vec![
+            vec![
+                Binding::Key(String::from("Left Shift")),
+                Binding::Key(String::from("W")),
+            ],
+            vec![Binding::Button(String::from("a"))],
+        ];
+        map.rebind("boost", boost).unwrap();
+
+        let mut raw = RawInput::default();
+        let state = tick(&map, &mut raw, &InputState::default(), &[key(Scancode::W, true)]);
+        assert!(!state.held("boost"));
+        let state = tick(&map, &mut raw, &state, &[key(Scancode::LShift, true)]);
+        assert!(state.held("boost"));
+
+        let mut raw = RawInput::default();
+        let state = tick(&map, &mut raw, &InputState::default(), &[button(Button::A)]);
+        assert!(state.held("boost"));
+    }
+
+    #[test]
+    fn pressed_and_released_are_edges() {
+        let map = InputMap::default();
+        let mut raw = RawInput::default();
+
+        let down = tick(&map, &mut raw, &InputState::default(), &[key(Scancode::C, true)]);
+        assert!(down.pressed("next_camera") && down.held("next_camera"));
+        let still = tick(&map, &mut raw, &down, &[]);
+        assert!(!still.pressed("next_camera") && still.held("next_camera"));
+        let up = tick(&map, &mut raw, &still, &[key(Scancode::C, false)]);
+        assert!(up.released("next_camera") && !up.held("next_camera"));
+        let idle = tick(&map, &mut raw, &up, &[]);
+        assert!(!idle.released("next_camera"));
+    }
+
+    #[test]
+    fn tap_between_two_ticks_is_pressed_once() {
+        let map = InputMap::default();
+        let mut raw = RawInput::default();
+
+        let tap = [key(Scancode::C, true), key(Scancode::C, false)];
+        let state = tick(&map, &mut raw, &InputState::default(), &tap);
+        assert!(state.pressed("next_camera"));
+        let state = tick(&map, &mut raw, &state, &[]);
+        assert!(!state.pressed("next_camera") && state.released("next_camera"));
+        assert!(!state.held("next_camera"));
+    }
+
+    #[test]
+    fn axis_is_clamped() {
+        let map = InputMap::default();
+        let mut raw = RawInput::default();
+        let events = [key(Scancode::D, true), stick(Axis::LeftX, 1.0)];
+        let state = tick(&map, &mut raw, &InputState::default(), &events);
+        assert_eq!(state.axis("move_x"), 1.0);
+
+        let state = tick(&map, &mut raw, &state, &[key(Scancode::A, true)]);
+        assert_eq!(state.axis("move_x"), 1.0);
+        let events = [key(Scancode::D, false), stick(Axis::LeftX, 0.0)];
+        let state = tick(&map, &mut raw, &state, &events);
+        assert_eq!(state.axis("move_x"), -1.0);
+    }
+
+    #[test]
+    fn small_stick_values_are_in_the_deadzone() {
+        let map = InputMap::default();
+        let mut raw = RawInput::default();
+        let state = tick(&map, &mut raw, &InputState::default(), &[stick(Axis::LeftX, 0.1)]);
+        assert_eq!(state.axis("move_x"), 0.0);
+
+        let state = tick(&map, &mut raw, &state, &[stick(Axis::LeftX, -0.5)]);
+        assert!((state.axis("move_x") + 0.5).abs() < 1e-3);
+    }
+
+    #[test]
+    fn rebind_to_unknown_binding_keeps_the_old_map() {
+        let mut map = InputMap::default();
+        let before = map.bindings().clone();
+
+        let result = map.rebind("quit", vec![vec![Binding::Key(String::from("NoSuchKey"))]]);
+        assert!(matches!(result, Err(EngineError::UnknownBinding(_))));
+        assert_eq!(map.bindings(), &before);
+
+        let mut raw = RawInput::default();
+        let state = tick(&map, &mut raw, &InputState::default(), &[key(Scancode::Escape, true)]);
+        assert!(state.pressed("quit"));
+    }
+}
@@ -1,106 +1,213 @@
+pub mod fake_controller;
+pub mod input;
 pub mod input_flags;
 
 pub mod camera;
 
 mod job;
 
-use cgmath::{Deg, Matrix4, Vector2, Vector3, Zero};
-
-use self::{camera::structs::FlyingEye, input_flags::InputFlags};
-use crate::black_sheep::q_i_square_root::q_normalize;
-
-use super::settings::*;
+use cgmath::{Deg, Matrix4, SquareMatrix, Vector2, Vector3, Zero};
+
+use self::{
+    camera::{
+        follow::FollowCamera, orbit::OrbitCamera, rig::CameraRig, structs::FlyingEye,
+        top_down::TopDownCamera, Camera,
+    },
+    fake_controller::FakeController,
+    input::{InputMap, InputState, RawInput},
+    input_flags::InputFlags,
+};
+use super::ecs::CHAINED_ECS;
+use super::error::{self, EngineError};
+use super::recording::{Recording, Replay, TickInput};
+use super::rng;
+use super::settings::EngineConfig;
 
 //pub auto trait UpdateFunction : FnMut(InputFlags){}
-// pub auto trait DrawFunction : FnMut(f32, &FlyingEye, &Matrix4<f32>);
+// pub auto trait DrawFunction : FnMut(f32, &dyn Camera, &Matrix4<f32>);
 
 pub struct GameState {
     pub input_flags: InputFlags,
+    pub input_map: InputMap,
+    pub raw_input: RawInput,
+    /// input of the last update tick
+    pub input: InputState,
+    /// scripted controller input, fed into `raw_input` before every tick
+    pub fake_controller: Option<FakeController>,
+    /// input of every tick, written to `config.record` when the engine shuts down
+    pub recording: Option<Recording>,
+    /// replaces the live input while it lasts, the engine closes when it is finished
+    pub replay: Option<Replay>,
     pub window_size_f32: [f32; 2],
     pub window_size_i32: [i32; 2],
     pub ui_projection: Matrix4<f32>,
+    /// projection of the active camera, updated every frame
     pub world_projection: Matrix4<f32>,
-    pub cam: FlyingEye,
+    pub cameras: CameraRig,
+    pub config: EngineConfig,
 }
 
 impl GameState {
-    pub fn new() -> Self {
-        let ui_projection = cgmath::ortho(
-            0.0,
-            INIT_WINDOW_SIZE_F32[0],
-            INIT_WINDOW_SIZE_F32[1],
-            0.0,
-            -1.0,
-            1.0,
-        );
-        let aspect = (INIT_WINDOW_SIZE_F32[0] - 300.0) / INIT_WINDOW_SIZE_F32[1];
-        let world_projection = cgmath::perspective(Deg(80.0), aspect, 0.1, 1000.0);
+    pub fn new(mut config: EngineConfig) -> Self {
+        let replay = config
+            .replay
+            .as_ref()
+            .and_then(|path| Recording::load(path).map_err(error::report).ok())
+            .map(Replay::new);
+        if let Some(replay) = &replay {
+            config.seed = Some(replay.seed());
+            config.ups = replay.ups().max(1);
+        }
+        let seed = *config.seed.get_or_insert_with(rng::time_seed);
+        rng::init(seed);
+        let recording = config
+            .record
+            .as_ref()
+            .map(|_| Recording::new(seed, config.ups));
+
+        let perspective = config.perspective();
+
         let mut cam = FlyingEye::new();
+        cam.dt = config.dt();
+        cam.settings = config.camera;
+        cam.projection = perspective;
         cam.move_cam(Vector3::new(0.0, 20.0, 20.0));
         cam.rotate_h(Deg(65.0));
 
-        GameState {
+        let mut cameras = CameraRig::new("fly", Box::new(cam));
+        cameras.add(
+            "orbit",
+            Box::new(OrbitCamera::new(Vector3::zero(), 30.0, perspective)),
+        );
+        cameras.add(
+            "top_down",
+            Box::new(TopDownCamera::new(Vector3::zero(), 50.0)),
+        );
+
+        let input_map = match InputMap::load(&config.input_bindings) {
+            Ok(input_map) => input_map,
+            Err(EngineError::Io(_, e)) if e.kind() == std::io::ErrorKind::NotFound => {
+                InputMap::default()
+            }
+            Err(e) => {
+                error::report(e);
+                InputMap::default()
+            }
+        };
+
+        let fake_controller = config
+            .fake_controller
+            .as_ref()
+            .and_then(|path| FakeController::load(path).map_err(error::report).ok());
+
+        let mut game_state = GameState {
             input_flags: InputFlags::NONE,
-            window_size_f32: INIT_WINDOW_SIZE_F32,
-            window_size_i32: INIT_WINDOW_SIZE_I32,
-            ui_projection,
-            world_projection,
-            cam,
-        }
+            input_map,
+            raw_input: RawInput::default(),
+            input: InputState::default(),
+            fake_controller,
+            recording,
+            replay,
+            window_size_f32: config.window_size_f32(),
+            window_size_i32: config.window_size_i32(),
+            ui_projection: Matrix4::identity(),
+            world_projection: Matrix4::identity(),
+            cameras,
+            config,
+        };
+        let [w, h] = game_state.window_size_i32;
+        game_state.resize(w, h);
+        game_state
     }
 
-    pub fn update(&mut self) {
-        self.cam.update();
+    pub fn resize(&mut self, w: i32, h: i32) {
+        self.window_size_i32 = [w, h];
+        let wh = [w as f32, h as f32];
+        self.window_size_f32 = wh;
 
-        if let Some(v) = get_movement(&mut self.input_flags) {
-            self.cam.set_movement(v);
-        } else {
-            self.cam.reset_movement();
-        }
+        self.ui_projection = cgmath::ortho(0.0, wh[0], wh[1], 0.0, -1.0, 1.0);
+        self.update_projection(0.0);
     }
 
-    pub fn on_mouse_motion(&mut self, xrel: i32, yrel: i32, x: i32, y: i32) {
-        let _v = Vector2::new(x as f32, y as f32);
-        //self.structogram.update(v);
+    /// aspect ratio of the 3D viewport
+    pub fn aspect(&self) -> f32 {
+        let [w, h] = self.viewport_size();
+        w as f32 / h.max(1) as f32
+    }
 
-        if self.input_flags.contains(InputFlags::CAPTURED_MOUSE) {
-            if xrel != 0 {
-                self.cam.rotate_v(Deg(xrel as f32 / 10.0));
-            }
-            if yrel != 0 {
-                self.cam.rotate_h(Deg(yrel as f32 / 10.0));
-            }
-        }
+    pub fn update_projection(&mut self, i: f32) {
+        self.world_projection = self.cameras.projection_matrix(self.aspect(), i);
     }
-}
 
-const CAM_SPEED: f32 = 10.0;
+    /// Adds the cameras that need access to the entities, called once the `Logic` has filled the ecs.
+    pub fn add_ecs_cameras(&mut self, ecs: &mut CHAINED_ECS) {
+        self.cameras.add(
+            "follow",
+            Box::new(FollowCamera::new(
+                ecs.get_positions_accessor(),
+                self.config.perspective(),
+            )),
+        );
+    }
 
-pub fn get_movement(input: &mut InputFlags) -> Option<Vector3<f32>> {
-    use InputFlags as kf;
+    pub fn viewport_size(&self) -> [i32; 2] {
+        [
+            self.window_size_i32[0] - self.config.side_panel_width as i32,
+            self.window_size_i32[1],
+        ]
+    }
 
-    if *input == kf::NONE {
-        None
-    } else {
-        let mut v = Vector3::zero();
-        if input.contains(kf::W) {
-            v += Vector3::new(0.0, 0.0, -1.0)
-        }
-        if input.contains(kf::S) {
-            v += Vector3::new(0.0, 0.0, 1.0)
+    pub fn update(&mut self) {
+        if let Some(replay) = &mut self.replay {
+            if let Some(tick) = replay.next_tick() {
+                self.input = tick.input.clone();
+                if tick.captured_mouse {
+                    let d = self.input.mouse_delta();
+                    self.cameras.on_mouse_motion(d.x, d.y);
+                }
+            } else {
+                self.input = InputState::default();
+                self.input_flags.insert(InputFlags::CLOSE);
+            }
+        } else {
+            if let Some(fake) = &mut self.fake_controller {
+                fake.feed(&mut self.raw_input);
+            }
+            self.input = self.input_map.update(&mut self.raw_input, &self.input);
         }
-        if input.contains(kf::D) {
-            v += Vector3::new(1.0, 0.0, 0.0)
+
+        if let Some(recording) = &mut self.recording {
+            recording.push(TickInput {
+                captured_mouse: self.input_flags.contains(InputFlags::CAPTURED_MOUSE),
+                input: self.input.clone(),
+            });
         }
-        if input.contains(kf::A) {
-            v += Vector3::new(-1.0, 0.0, 0.0)
+
+        if self.input.pressed("next_camera") {
+            self.cameras.switch_next();
         }
-        if input.contains(kf::X) {
-            v += Vector3::new(0.0, 1.0, 0.0)
+        self.cameras.update(&self.input, self.config.dt());
+    }
+
+    pub fn on_mouse_motion(&mut self, xrel: i32, yrel: i32, x: i32, y: i32) {
+        let _v = Vector2::new(x as f32, y as f32);
+        //self.structogram.update(v);
+
+        if self.replay.is_none() && self.input_flags.contains(InputFlags::CAPTURED_MOUSE) {
+            self.cameras.on_mouse_motion(xrel as f32, yrel as f32);
         }
-        if input.contains(kf::Y) {
-            v += Vector3::new(0.0, -1.0, 0.0)
+    }
+
+    /// Writes the input recorded so far to `config.record`.
+    pub fn save_recording(&self) {
+        if let (Some(recording), Some(path)) = (&self.recording, &self.config.record) {
+            match recording.save(path) {
+                Ok(()) => {
+                    #[cfg(not(feature = "debug_off"))]
+                    println!("{} ticks recorded to {}", recording.ticks.len(), path);
+                }
+                Err(e) => error::report(EngineError::Io(path.clone(), e)),
+            }
         }
-        Some(q_normalize(v) * CAM_SPEED)
     }
 }
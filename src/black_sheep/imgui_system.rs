@@ -1,11 +1,9 @@
 use imgui::{Context, FontConfig, FontGlyphRanges, FontSource, Key, TextureId, Ui};
 use sdl2::event::Event;
 
-use crate::black_sheep::settings::INIT_WINDOW_SIZE_F32;
-
 use super::rendering::{
     geometry::imgui_mesh::{imguimesh_from_drawdata, ImguiMesh},
-    loader, Texture,
+    loader, shader, Texture,
 };
 
 pub struct ImguiSystem {
@@ -144,14 +142,15 @@ impl ImguiSystem {
     }
 
     pub fn draw(&self) {
+        let imgui_shader = shader::get_shader_repo().imgui;
         self.mesh_vec.iter().for_each(|mesh| {
             mesh.bind_vertex_array();
-            mesh.draw(self.imgui.io().display_size);
+            mesh.draw(&imgui_shader, self.imgui.io().display_size);
         });
     }
 }
 
-pub fn init() -> ImguiSystem {
+pub fn init(display_size: [f32; 2]) -> ImguiSystem {
     let mut imgui = Context::create();
 
     imgui.io_mut()[Key::Backspace] = sdl2::keyboard::Keycode::Backspace as u32;
@@ -180,7 +179,7 @@ pub fn init() -> ImguiSystem {
 
     imgui.io_mut().font_global_scale = 1.0 / hidpi_factor;
 
-    imgui.io_mut().display_size = INIT_WINDOW_SIZE_F32;
+    imgui.io_mut().display_size = display_size;
     ImguiSystem {
         imgui,
         frame_update_counter: 2,
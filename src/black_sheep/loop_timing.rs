@@ -1,36 +1,55 @@
 use std::time::{Duration, Instant};
 
-use super::settings::DT_D;
-
 pub struct CatchupTimer {
     start_time: Instant,
+    dt: Duration,
     previous_update_time: Duration,
     lag: Duration,
+    /// one tick per frame without reading the clock
+    fixed: bool,
+    ticked: bool,
 
     previous_fps_time: Duration,
     fps: u32,
 }
 
 impl CatchupTimer {
-    pub fn new() -> Self {
+    pub fn new(dt: Duration) -> Self {
         CatchupTimer {
             start_time: Instant::now(),
+            dt,
             previous_update_time: Duration::ZERO,
             lag: Duration::ZERO,
+            fixed: false,
+            ticked: false,
 
             previous_fps_time: Duration::ZERO,
             fps: 0,
         }
     }
 
+    /// Runs exactly one update per frame and always interpolates with 0, so every frame is
+    /// the same no matter how long it took.
+    pub fn fixed(dt: Duration) -> Self {
+        CatchupTimer {
+            fixed: true,
+            ..Self::new(dt)
+        }
+    }
+
     pub fn should_update(&mut self) -> bool {
+        if self.fixed {
+            // true for the first call of a frame, false for the one that ends its update loop
+            self.ticked = !self.ticked;
+            return self.ticked;
+        }
         let current = self.start_time.elapsed();
         let elapsed = current - self.previous_update_time;
         self.previous_update_time = current;
         self.lag += elapsed;
 
-        if self.lag >= DT_D {
-            self.lag -= DT_D;
+        if self.lag >= self.dt {
+            self.lag -= self.dt;
             true
         } else {
             self.fps += 1;
@@ -43,7 +62,28 @@ impl CatchupTimer {
         }
     }
 
+    /// Pretends a full tick of time has passed, so headless runs don't wait for the wall clock.
+    pub fn fast_forward(&mut self) {
+        self.lag += self.dt;
+    }
+
     pub fn get_iv(&self) -> f32 {
-        self.lag.as_secs_f32() / DT_D.as_secs_f32()
+        self.lag.as_secs_f32() / self.dt.as_secs_f32()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fixed_timer_ticks_once_per_frame() {
+        let mut timer = CatchupTimer::fixed(Duration::from_millis(40));
+        for _ in 0..3 {
+            std::thread::sleep(Duration::from_millis(50));
+            assert!(timer.should_update());
+            assert!(!timer.should_update());
+            assert_eq!(timer.get_iv(), 0.0);
+        }
     }
 }
@@ -2,14 +2,19 @@ pub mod rendering;
 pub mod window;
 
 mod algorithms;
+pub mod assets;
 #[allow(dead_code)]
 mod constants;
 pub mod ecs;
+pub mod error;
 pub mod gamestate;
 mod generators;
 mod imgui_system;
 mod loop_timing;
 mod q_i_square_root;
+pub mod recording;
+pub mod rng;
+pub mod scene;
 mod script;
 pub mod settings;
 pub mod setup;
@@ -19,32 +24,32 @@ mod transform;
 mod gl_debug;
 
 use cgmath::Matrix4;
-use cgmath::{Deg, Vector2};
+use cgmath::Vector2;
 use gamestate::*;
 
-use imgui::{ColorPicker, Condition, Image, TextureId, Window};
-use sdl2::mouse::MouseButton;
+use imgui::{ColorPicker, Condition, Image, Slider, TextureId, Window};
 
 use window::window_util::*;
 use window::SDLWindow;
 
 use sdl2::event::{Event, WindowEvent};
 
-use crate::black_sheep::rendering::loader::load_texture_from_path;
 use crate::black_sheep::rendering::rendertarget;
 
 use crate::black_sheep::window::window_util::{clear_drawbuffer, set_viewport};
 
+use gamestate::input::InputState;
 use gamestate::input_flags::InputFlags;
 use imgui_system::ImguiSystem;
 use rendering::geometry;
 use rendering::geometry::mesh::MeshToken;
 use rendering::shader;
 
-use camera::structs::FlyingEye;
+use camera::Camera;
+use settings::{EngineConfig, RunMode};
 
-pub trait UpdateFunction = FnMut(InputFlags);
-pub trait DrawFunction = FnMut(f32, &FlyingEye, &Matrix4<f32>);
+pub trait UpdateFunction = FnMut(&InputState);
+pub trait DrawFunction = FnMut(f32, &dyn Camera, &Matrix4<f32>);
 
 pub struct Logic<U: UpdateFunction, D: DrawFunction> {
     pub update: U,
@@ -65,28 +70,60 @@ where
 
 impl<U: UpdateFunction, D: DrawFunction> Drop for BlackSheep<U, D> {
     fn drop(&mut self) {
+        assets::cleanup();
         shader::cleanup();
         geometry::cleanup();
     }
 }
 
-pub fn run<U, D, FL>(mut f_logic: FL)
+/// Hands back the ecs after a headless run, windowed runs keep it until the window closes.
+pub fn run<U, D, FL>(config: EngineConfig, f_logic: FL) -> Option<ecs::CHAINED_ECS>
+where
+    U: UpdateFunction,
+    D: DrawFunction,
+    FL: FnMut(&mut ecs::CHAINED_ECS) -> Logic<U, D>,
+{
+    match config.run_mode {
+        RunMode::Headless { ticks } => {
+            let save_scene = config.save_scene.clone();
+            let mut ecs = run_headless(config, ticks, f_logic);
+            if let Some(path) = save_scene {
+                scene::save_ecs(&mut ecs, &path);
+            }
+            Some(ecs)
+        }
+        _ => {
+            // a failed setup or an unsaved offscreen frame must not look like a clean exit
+            if let Err(e) = run_windowed(config, f_logic) {
+                error::report(e);
+                std::process::exit(1);
+            }
+            None
+        }
+    }
+}
+
+fn run_windowed<U, D, FL>(config: EngineConfig, mut f_logic: FL) -> error::Result<()>
 where
     U: UpdateFunction,
     D: DrawFunction,
     FL: FnMut(&mut ecs::CHAINED_ECS) -> Logic<U, D>,
 {
     // KEEP THIS ORDER
-    let window = SDLWindow::new();
-    shader::init();
-    geometry::init();
+    let offscreen = matches!(config.run_mode, RunMode::Offscreen { .. });
+    let window = SDLWindow::new(&config, offscreen);
+    let mut game_state = GameState::new(config);
+    shader::init()
+        .and_then(|_| geometry::init())
+        .and_then(|_| setup::init_mesh())?;
+    assets::init();
 
     #[cfg(feature = "gl_debug")]
     gl_debug::setup_debug();
 
-    let game_state = GameState::new();
     let mut ecs = ecs::CHAINED_ECS::new();
     let logic = (f_logic)(&mut ecs);
+    game_state.add_ecs_cameras(&mut ecs);
 
     let bs = BlackSheep {
         window,
@@ -96,7 +133,52 @@ where
         ecs,
     };
 
-    bs.run();
+    bs.run()
+}
+
+/// Runs `ticks` updates without creating a window or a GL context and hands back the ecs,
+/// so the update logic can be checked on machines without a display.
+/// The draw function is never called, so it must not touch GL before its first call.
+pub fn run_headless<U, D, FL>(
+    config: EngineConfig,
+    ticks: u64,
+    mut f_logic: FL,
+) -> ecs::CHAINED_ECS
+where
+    U: UpdateFunction,
+    D: DrawFunction,
+    FL: FnMut(&mut ecs::CHAINED_ECS) -> Logic<U, D>,
+{
+    let mut game_state = GameState::new(config);
+    let mut ecs = ecs::CHAINED_ECS::new();
+    let mut logic = (f_logic)(&mut ecs);
+    game_state.add_ecs_cameras(&mut ecs);
+
+    // a replay runs for exactly as long as it was recorded
+    let ticks = game_state
+        .replay
+        .as_ref()
+        .map_or(ticks, |r| r.tick_count() as u64);
+
+    let mut loop_timer = loop_timing::CatchupTimer::new(game_state.config.dt_d());
+
+    let mut tick = 0;
+
+    while tick < ticks {
+        loop_timer.fast_forward();
+        while tick < ticks && loop_timer.should_update() {
+            game_state.update();
+            (logic.update)(&game_state.input);
+            tick += 1;
+        }
+    }
+
+    game_state.save_recording();
+
+    #[cfg(not(feature = "debug_off"))]
+    println!("headless run finished after {} ticks", tick);
+
+    ecs
 }
 
 impl<U, D> BlackSheep<U, D>
@@ -108,42 +190,21 @@ where
         while let Some(event) = self.window.poll_event() {
             imgui_system.handle_event(&event);
             let game_state = &mut self.game_state;
+            game_state.raw_input.handle_event(&event);
             match event {
                 Event::Quit { .. } => {
                     game_state.input_flags.insert(InputFlags::CLOSE);
                 }
-                Event::KeyDown { keycode, .. } => {
-                    if let Some(key) = keycode {
-                        use sdl2::keyboard::Keycode::*;
-                        if let Escape = key {
-                            game_state.input_flags.insert(InputFlags::CLOSE);
-                        } else {
-                            game_state.input_flags.key_down(key);
-                        }
-                    } else {
-                        #[cfg(not(feature = "debug_off"))]
-                        println!("No Valid KeyCode");
-                    }
-                }
-                Event::KeyUp { keycode, .. } => {
-                    if let Some(key) = keycode {
-                        game_state.input_flags.key_up(key);
-                    } else {
-                        #[cfg(not(feature = "debug_off"))]
-                        println!("No Valid KeyCode");
+                Event::KeyDown { .. } | Event::KeyUp { .. } => {
+                    if game_state.input_map.held("quit", &game_state.raw_input) {
+                        game_state.input_flags.insert(InputFlags::CLOSE);
                     }
                 }
-                Event::MouseButtonDown { mouse_btn, .. } => {
-                    if MouseButton::Right == mouse_btn {
-                        game_state.input_flags.insert(InputFlags::CAPTURED_MOUSE);
-                        self.window.capture_mouse();
-                    }
+                Event::ControllerDeviceAdded { which, .. } => {
+                    self.window.controller_added(which);
                 }
-                Event::MouseButtonUp { mouse_btn, .. } => {
-                    if MouseButton::Right == mouse_btn {
-                        game_state.input_flags.remove(InputFlags::CAPTURED_MOUSE);
-                        self.window.release_mouse();
-                    }
+                Event::ControllerDeviceRemoved { which, .. } => {
+                    self.window.controller_removed(which);
                 }
                 Event::MouseMotion {
                     xrel, yrel, x, y, ..
@@ -154,42 +215,77 @@ where
                 Event::Window { win_event, .. } => match win_event {
                     WindowEvent::Resized(w, h) => {
                         set_viewport(w, h);
-                        game_state.window_size_i32 = [w, h];
-                        let wh = [w as f32, h as f32];
-                        game_state.window_size_f32 = wh;
-
-                        game_state.ui_projection = cgmath::ortho(0.0, wh[0], wh[1], 0.0, -1.0, 1.0);
-                        let aspect = (wh[0] - 300.0) / wh[1];
-                        game_state.world_projection =
-                            cgmath::perspective(Deg(80.0), aspect, 0.1, 1000.0);
+                        game_state.resize(w, h);
+                        let [vw, vh] = game_state.viewport_size();
+                        rendertarget::set_viewport_size(vw, vh);
                     }
                     _ => (),
                 },
                 _ => (),
             }
+
+            let game_state = &mut self.game_state;
+            let capture = game_state.input_map.held("capture_mouse", &game_state.raw_input);
+            if capture != game_state.input_flags.contains(InputFlags::CAPTURED_MOUSE) {
+                game_state.input_flags.set(InputFlags::CAPTURED_MOUSE, capture);
+                if capture {
+                    self.window.capture_mouse();
+                } else {
+                    self.window.release_mouse();
+                }
+            }
         }
     }
 
-    pub fn run(mut self) {
-        let (imgui_shader_program, gizmo_shader, _three_d) = {
-            let shader_repo = rendering::shader::get_shader_repo();
-            (shader_repo.imgui, shader_repo.gizmo, shader_repo.color_3d)
-        };
-
+    /// Fails if the setup fails or an offscreen frame can't be saved.
+    pub fn run(mut self) -> error::Result<()> {
         init_rendersetup();
 
-        let mut imgui_system = imgui_system::init();
+        let mut imgui_system = imgui_system::init(self.game_state.window_size_f32);
 
-        let rt_gizmo = rendering::rendertarget::RenderTarget::new(300, 300);
+        let panel_width = self.game_state.config.side_panel_width;
+        let gizmo_size = panel_width as i32;
+        let mut rt_gizmo = rendering::rendertarget::RenderTarget::new(gizmo_size, gizmo_size)?;
         rendertarget::unbind_framebuffer();
 
+        let [vw, vh] = self.game_state.viewport_size();
+        rendertarget::set_viewport_size(vw, vh);
+        let samples = self.game_state.config.msaa_samples as i32;
+        let mut post = rendering::postprocess::PostProcess::new(samples)?;
+        if let Some(path) = &self.game_state.config.color_lut {
+            if let Err(e) = post.load_lut(path) {
+                error::report(e);
+            }
+        }
+
+        let (mut frame_target, frame_count, out_dir) = match &self.game_state.config.run_mode {
+            RunMode::Offscreen { frames, out_dir } => {
+                std::fs::create_dir_all(out_dir)
+                    .map_err(|e| error::EngineError::Io(out_dir.clone(), e))?;
+                let [w, h] = self.game_state.window_size_i32;
+                let rt = rendering::rendertarget::RenderTarget::new(w, h)?;
+                rendertarget::unbind_framebuffer();
+                (Some(rt), *frames, out_dir.clone())
+            }
+            _ => (None, 0, String::new()),
+        };
+        let mut frame = 0;
+
         let font_texture = imgui_system.load_font_atlas_texture();
-        let nice_image = load_texture_from_path("./res/1322615842122.jpg").unwrap();
+        let nice_image = assets::get_asset_manager(|am| am.load_texture("./res/1322615842122.jpg"))
+            .map_err(error::report)
+            .ok();
 
         let gizmo =
             geometry::get_mesh_repo(|mr| MeshToken::from(mr.get_mesh_by_name("gizmo").unwrap()));
 
-        let mut loop_timer = loop_timing::CatchupTimer::new();
+        let dt = self.game_state.config.dt_d();
+        // offscreen frames are one tick apart no matter how long they take to render
+        let mut loop_timer = if frame_target.is_some() {
+            loop_timing::CatchupTimer::fixed(dt)
+        } else {
+            loop_timing::CatchupTimer::new(dt)
+        };
 
         let _fps = 0;
 
@@ -197,13 +293,19 @@ where
 
         let mut wiregrid = false;
 
+        let mut cull_stats = rendering::frustum::CullStats::default();
+        let mut queue_stats = rendering::render_queue::QueueStats::default();
+        let mut result = Ok(());
+
         'mainloop: loop {
             //PROCESS INPUT
             self.handle_events(&mut imgui_system);
+            assets::poll_changes();
             if self.game_state.input_flags.contains(InputFlags::CLOSE) {
                 break 'mainloop;
             }
 
+            let controllers = self.window.controller_names();
             let game_state = &mut self.game_state;
 
             while loop_timer.should_update() {
@@ -213,9 +315,9 @@ where
                     use imgui::WindowFlags;
 
                     Window::new("Image")
-                        .size([300.0, game_state.window_size_f32[1]], Condition::Always)
+                        .size([panel_width, game_state.window_size_f32[1]], Condition::Always)
                         .position(
-                            [game_state.window_size_f32[0] - 300.0, 0.0],
+                            [game_state.window_size_f32[0] - panel_width, 0.0],
                             Condition::Always,
                         )
                         .flags(
@@ -234,68 +336,198 @@ where
                                 gl_wiregrid(wiregrid);
                             }
 
-                            ui.text(format!("{:?}", -game_state.cam.position));
-                            ui.text(format!("{:#?}", game_state.cam.orientation));
+                            ui.text(format!(
+                                "culled {} of {}",
+                                cull_stats.culled, cull_stats.tested
+                            ));
+                            ui.text(format!(
+                                "queue: {} items, {} programs, {} textures, {} states",
+                                queue_stats.items,
+                                queue_stats.program_changes,
+                                queue_stats.texture_binds,
+                                queue_stats.state_changes
+                            ));
+
+                            let pose = game_state.cameras.pose(0.0);
+                            ui.text(format!("{:?}", pose.position));
+                            ui.text(format!("{:#?}", pose.orientation));
+
+                            let active = game_state.cameras.active();
+                            let mut switch = None;
+                            for (i, name) in game_state.cameras.names().enumerate() {
+                                if ui.radio_button_bool(name, i == active) {
+                                    switch = Some(i);
+                                }
+                            }
+                            if let Some(i) = switch {
+                                game_state.cameras.switch_to(i);
+                            }
+                            for name in controllers.iter() {
+                                ui.text(format!("controller: {}", name));
+                            }
+
+                            ui.separator();
+                            let mut shadow = rendering::shadow::settings();
+                            ui.checkbox("shadows", &mut shadow.enabled);
+                            Slider::new("shadow bias", 0.0, 1.0).build(ui, &mut shadow.bias);
+                            Slider::new("pcf radius", 0.0, 0.5).build(ui, &mut shadow.pcf_radius);
+                            for res in rendering::shadow::SHADOW_RESOLUTIONS.iter() {
+                                if ui.radio_button_bool(format!("{}", res), shadow.resolution == *res) {
+                                    shadow.resolution = *res;
+                                }
+                            }
+                            rendering::shadow::set_settings(shadow);
+                            ui.separator();
+
+                            let mut pp = rendering::postprocess::settings();
+                            ui.checkbox("post processing", &mut pp.enabled);
+                            Slider::new("exposure", 0.1, 8.0).build(ui, &mut pp.exposure);
+                            ui.checkbox("tonemap", &mut pp.tonemap);
+                            ui.checkbox("gamma", &mut pp.gamma_correct);
+                            Slider::new("gamma value", 1.0, 3.0).build(ui, &mut pp.gamma);
+                            ui.checkbox("bloom", &mut pp.bloom);
+                            Slider::new("bloom threshold", 0.0, 4.0)
+                                .build(ui, &mut pp.bloom_threshold);
+                            Slider::new("bloom intensity", 0.0, 2.0)
+                                .build(ui, &mut pp.bloom_intensity);
+                            Slider::new("bloom passes", 1, 10).build(ui, &mut pp.bloom_passes);
+                            ui.checkbox("fxaa", &mut pp.fxaa);
+                            ui.checkbox("vignette", &mut pp.vignette);
+                            Slider::new("vignette strength", 0.0, 1.0)
+                                .build(ui, &mut pp.vignette_strength);
+                            Slider::new("vignette radius", 0.0, 1.0)
+                                .build(ui, &mut pp.vignette_radius);
+                            ui.checkbox("color grading", &mut pp.lut);
+                            Slider::new("grading strength", 0.0, 1.0)
+                                .build(ui, &mut pp.lut_strength);
+                            rendering::postprocess::set_settings(pp);
+                            ui.separator();
+
+                            let mut deferred = rendering::deferred::settings();
+                            ui.checkbox("deferred apes", &mut deferred.enabled);
+                            for channel in rendering::deferred::GBufferChannel::ALL.iter() {
+                                let selected = deferred.channel == *channel;
+                                if ui.radio_button_bool(channel.name(), selected) {
+                                    deferred.channel = *channel;
+                                }
+                            }
+                            Slider::new("depth range", 10.0, 1000.0)
+                                .build(ui, &mut deferred.depth_range);
+                            rendering::deferred::set_settings(deferred);
+                            ui.separator();
+
                             ColorPicker::new("color_picker", &mut t_color).build(ui);
-                            Image::new(TextureId::new(2 as usize), [300.0, 300.0])
+                            let gizmo_id = TextureId::new(rendering::GIZMO_TEXTURE_UNIT as usize);
+                            Image::new(gizmo_id, [panel_width, panel_width])
                                 .uv0([0.0, 1.0])
                                 .uv1([1.0, 0.0])
                                 .build(ui);
-                            Image::new(TextureId::new(1 as usize), [300.0, 300.0]).build(ui);
+                            let image_id = TextureId::new(rendering::IMAGE_TEXTURE_UNIT as usize);
+                            Image::new(image_id, [panel_width, panel_width])
+                                .build(ui);
+
+                            let errors = error::reported();
+                            if !errors.is_empty() {
+                                ui.separator();
+                                for e in errors.iter() {
+                                    ui.text_colored([1.0, 0.3, 0.3, 1.0], e);
+                                }
+                                if ui.button("clear errors") {
+                                    error::clear_reported();
+                                }
+                            }
                         });
+                    rendering::debug_draw::draw_text(ui);
                 });
                 //HANDLE INPUT
 
                 game_state.update();
 
-                (self.logic.update)(game_state.input_flags);
+                (self.logic.update)(&game_state.input);
+                rendering::debug_draw::tick(game_state.config.dt());
             }
 
             //RENDER
             unsafe {
-                gl::ActiveTexture(gl::TEXTURE0 + 0);
+                gl::ActiveTexture(gl::TEXTURE0 + rendering::FONT_TEXTURE_UNIT);
                 font_texture.bind();
-                gl::ActiveTexture(gl::TEXTURE0 + 1);
-                nice_image.bind();
-                gl::ActiveTexture(gl::TEXTURE0 + 2);
+                if let Some(nice_image) = &nice_image {
+                    gl::ActiveTexture(gl::TEXTURE0 + rendering::IMAGE_TEXTURE_UNIT);
+                    nice_image.get().bind();
+                }
+                gl::ActiveTexture(gl::TEXTURE0 + rendering::GIZMO_TEXTURE_UNIT);
                 rt_gizmo.bind_texture();
             }
 
             let i = loop_timer.get_iv();
 
-            let view = game_state.cam.get_i_view(i);
+            let view = game_state.cameras.get_i_view(i);
+            game_state.update_projection(i);
 
             rt_gizmo.bind_framebuffer();
             three_d_rendering_setup();
             clear_color(0.1, 0.1, 0.1, 1.0);
             clear_drawbuffer();
-            set_viewport(300, 300);
+            set_viewport(gizmo_size, gizmo_size);
+            // fetched every frame, a hot reload only updates the uniform locations in the repo
+            let shader_repo = rendering::shader::get_shader_repo();
+            let gizmo_shader = shader_repo.gizmo;
             gizmo_shader.use_program();
             gizmo_shader.set_view(view);
             gizmo.bind_vertex_array();
             gizmo.draw_point_elements();
-            rendertarget::unbind_framebuffer();
 
-            set_viewport(
-                game_state.window_size_i32[0] - 300,
-                game_state.window_size_i32[1],
-            );
+            if let Some(ft) = &mut frame_target {
+                ft.bind_framebuffer();
+            } else {
+                rendertarget::unbind_framebuffer();
+            }
+
+            let [vw, vh] = game_state.viewport_size();
+            post.begin();
+            set_viewport(vw, vh);
 
             three_d_rendering_setup();
 
             clear_color(0.0, 0.3, 0.3, 1.0);
             clear_drawbuffer();
 
-            (self.logic.draw)(i, &game_state.cam, &game_state.world_projection);
+            (self.logic.draw)(i, &game_state.cameras, &game_state.world_projection);
+            queue_stats = rendering::render_queue::execute(game_state.cameras.pose(i).position);
+            rendering::debug_draw::flush(game_state.world_projection * view);
+            cull_stats = rendering::frustum::take_cull_stats();
+            post.end();
 
             set_viewport(game_state.window_size_i32[0], game_state.window_size_i32[1]);
             imgui_rendering_setup();
 
+            let imgui_shader_program = shader_repo.imgui;
             imgui_shader_program.use_program();
             imgui_shader_program.set_matrix(game_state.ui_projection);
             imgui_system.draw();
 
+            if let Some(ft) = &frame_target {
+                let path = format!("{}/frame_{:04}.png", out_dir, frame);
+                if let Err(e) = ft.save_png(&path) {
+                    result = Err(error::EngineError::Image(path, e));
+                    break 'mainloop;
+                }
+                frame += 1;
+                if frame >= frame_count {
+                    break 'mainloop;
+                }
+                rendertarget::unbind_framebuffer();
+            }
+
             self.window.swap();
         }
+
+        self.game_state.save_recording();
+
+        if let Some(path) = &self.game_state.config.save_scene {
+            scene::save_ecs(&mut self.ecs, path);
+        }
+
+        result
     }
 }
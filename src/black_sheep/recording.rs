@@ -0,0 +1,77 @@
+use serde::{Deserialize, Serialize};
+
+use super::error::{EngineError, Result};
+use super::gamestate::input::InputState;
+
+/// Input of one update tick as the engine saw it.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TickInput {
+    pub captured_mouse: bool,
+    pub input: InputState,
+}
+
+/// Everything needed to run the same simulation again: the seed, the tick rate and the input of every tick.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Recording {
+    pub seed: u64,
+    pub ups: u64,
+    pub ticks: Vec<TickInput>,
+}
+
+impl Recording {
+    pub fn new(seed: u64, ups: u64) -> Self {
+        Recording {
+            seed,
+            ups,
+            ticks: Vec::new(),
+        }
+    }
+
+    pub fn load(path: &str) -> Result<Self> {
+        let s = std::fs::read_to_string(path).map_err(|e| EngineError::Io(String::from(path), e))?;
+        ron::from_str(&s).map_err(|e| EngineError::Parse(String::from(path), e))
+    }
+
+    pub fn save(&self, path: &str) -> std::io::Result<()> {
+        let s = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default()).unwrap();
+        std::fs::write(path, s)
+    }
+
+    pub fn push(&mut self, tick: TickInput) {
+        self.ticks.push(tick);
+    }
+}
+
+/// Hands out the ticks of a `Recording` one after another.
+pub struct Replay {
+    recording: Recording,
+    tick: usize,
+}
+
+impl Replay {
+    pub fn new(recording: Recording) -> Self {
+        Replay { recording, tick: 0 }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.recording.seed
+    }
+
+    pub fn ups(&self) -> u64 {
+        self.recording.ups
+    }
+
+    pub fn tick_count(&self) -> usize {
+        self.recording.ticks.len()
+    }
+
+    pub fn finished(&self) -> bool {
+        self.tick >= self.recording.ticks.len()
+    }
+
+    pub fn next_tick(&mut self) -> Option<&TickInput> {
+        let tick = self.recording.ticks.get(self.tick);
+        self.tick += 1;
+        tick
+    }
+}
@@ -0,0 +1,362 @@
+//! Immediate mode debug shapes, usable from update and draw code.
+//! Shapes are kept for `lifetime` seconds, a lifetime of 0 draws them once.
+//! Everything here compiles to nothing without `debug_assertions`.
+#![cfg_attr(not(debug_assertions), allow(unused_imports, unused_variables, dead_code))]
+
+use std::{collections::HashMap, sync::Mutex};
+
+use cgmath::{InnerSpace, Matrix4, SquareMatrix, Vector3, Vector4};
+use imgui::Ui;
+
+use super::{
+    frustum::Aabb,
+    geometry::mesh::Mesh,
+    rendertarget::viewport_size,
+    shader::get_shader_repo,
+};
+
+const CIRCLE_SEGMENTS: usize = 32;
+
+#[cfg(debug_assertions)]
+lazy_static! {
+    static ref DEBUG_DRAW: Mutex<DebugDraw> = Mutex::new(DebugDraw::default());
+}
+
+struct Lines {
+    color: Vector3<f32>,
+    lifetime: f32,
+    /// pairs of segment end points
+    points: Vec<Vector3<f32>>,
+}
+
+struct Text {
+    position: Vector3<f32>,
+    text: String,
+    color: Vector3<f32>,
+    lifetime: f32,
+}
+
+/// A `Text` placed on the screen by `flush`.
+struct Label {
+    position: [f32; 2],
+    color: [f32; 4],
+    text: String,
+}
+
+#[derive(Default)]
+struct DebugDraw {
+    lines: Vec<Lines>,
+    texts: Vec<Text>,
+    /// the texts visible in the last flush, in window coordinates
+    labels: Vec<Label>,
+    /// dynamic vertex buffer all lines are uploaded to, created on the first flush
+    batch: Option<Mesh>,
+}
+
+impl DebugDraw {
+    fn flush(&mut self, view_projection: Matrix4<f32>) {
+        self.place_labels(view_projection);
+
+        // one draw call per color, the Color3D shader takes the color as uniform
+        let mut groups: HashMap<[u32; 3], (Vector3<f32>, Vec<Vector3<f32>>)> = HashMap::new();
+        for lines in self.lines.iter() {
+            let key = [
+                lines.color.x.to_bits(),
+                lines.color.y.to_bits(),
+                lines.color.z.to_bits(),
+            ];
+            groups
+                .entry(key)
+                .or_insert_with(|| (lines.color, Vec::new()))
+                .1
+                .extend_from_slice(&lines.points);
+        }
+        self.lines.retain(|l| l.lifetime > 0.0);
+
+        let mut vertices = Vec::new();
+        let mut ranges = Vec::with_capacity(groups.len());
+        for (color, points) in groups.values() {
+            ranges.push((*color, vertices.len() as i32, points.len() as i32));
+            vertices.extend_from_slice(points);
+        }
+        if vertices.is_empty() {
+            return;
+        }
+
+        match &self.batch {
+            Some(batch) => {
+                batch.bind_vertex_array();
+                batch.update_buffer(&vertices, 0);
+            }
+            None => {
+                let mut batch = Mesh::new();
+                batch.add_dynamic_floatbuffer(&vertices, 0, 3);
+                self.batch = Some(batch);
+            }
+        }
+        let program = get_shader_repo().color_3d;
+        program.use_program();
+        program.set_MVP(view_projection);
+        for (color, first, count) in ranges {
+            program.set_col(color);
+            unsafe {
+                gl::DrawArrays(gl::LINES, first, count);
+            }
+        }
+        unsafe {
+            gl::BindVertexArray(0);
+        }
+    }
+
+    /// Projects the texts once per rendered frame and drops the expired ones, the ImGui frame
+    /// is built per update tick and only shows the result.
+    fn place_labels(&mut self, view_projection: Matrix4<f32>) {
+        let [width, height] = viewport_size();
+        self.labels.clear();
+        for text in self.texts.iter() {
+            let clip = view_projection * text.position.extend(1.0);
+            if clip.w <= 0.0 {
+                continue;
+            }
+            let ndc = clip.truncate() / clip.w;
+            if ndc.x.abs() > 1.0 || ndc.y.abs() > 1.0 {
+                continue;
+            }
+            let position = [
+                (ndc.x * 0.5 + 0.5) * width as f32,
+                (0.5 - ndc.y * 0.5) * height as f32,
+            ];
+            let c = text.color;
+            self.labels.push(Label {
+                position,
+                color: [c.x, c.y, c.z, 1.0],
+                text: text.text.clone(),
+            });
+        }
+        self.texts.retain(|t| t.lifetime > 0.0);
+    }
+
+    fn draw_text(&self, ui: &Ui) {
+        let draw_list = ui.get_foreground_draw_list();
+        for label in self.labels.iter() {
+            draw_list.add_text(label.position, label.color, &label.text);
+        }
+    }
+}
+
+/// Ages all shapes by `dt` seconds, called once per update tick.
+pub fn tick(dt: f32) {
+    #[cfg(debug_assertions)]
+    if let Ok(mut d) = DEBUG_DRAW.lock() {
+        d.lines.iter_mut().for_each(|l| l.lifetime -= dt);
+        d.texts.iter_mut().for_each(|t| t.lifetime -= dt);
+    }
+}
+
+/// Draws all lines into the bound framebuffer, places the labels and drops the expired shapes.
+/// Called once per rendered frame.
+pub fn flush(view_projection: Matrix4<f32>) {
+    #[cfg(debug_assertions)]
+    if let Ok(mut d) = DEBUG_DRAW.lock() {
+        d.flush(view_projection);
+    }
+}
+
+/// Adds the `text_3d` labels placed by the last flush to the foreground draw list.
+pub fn draw_text(ui: &Ui) {
+    #[cfg(debug_assertions)]
+    if let Ok(d) = DEBUG_DRAW.lock() {
+        d.draw_text(ui);
+    }
+}
+
+/// `build` only runs in debug builds.
+fn push<F: FnOnce(&mut Vec<Vector3<f32>>)>(color: Vector3<f32>, lifetime: f32, build: F) {
+    #[cfg(debug_assertions)]
+    {
+        let mut points = Vec::new();
+        build(&mut points);
+        if let Ok(mut d) = DEBUG_DRAW.lock() {
+            d.lines.push(Lines {
+                color,
+                lifetime,
+                points,
+            });
+        }
+    }
+}
+
+pub fn line(from: Vector3<f32>, to: Vector3<f32>, color: Vector3<f32>, lifetime: f32) {
+    push(color, lifetime, |points| points.extend_from_slice(&[from, to]));
+}
+
+/// A line with a head at `to`, a fifth of its length.
+pub fn arrow(from: Vector3<f32>, to: Vector3<f32>, color: Vector3<f32>, lifetime: f32) {
+    push(color, lifetime, |points| {
+        points.extend_from_slice(&[from, to]);
+        let direction = to - from;
+        let length = direction.magnitude();
+        if length <= f32::EPSILON {
+            return;
+        }
+        let direction = direction / length;
+        let (side, up) = basis(direction);
+        let head = length * 0.2;
+        let base = to - direction * head;
+        for offset in [side, -side, up, -up].iter() {
+            points.extend_from_slice(&[to, base + *offset * head * 0.5]);
+        }
+    });
+}
+
+pub fn aabb(bounds: &Aabb, color: Vector3<f32>, lifetime: f32) {
+    let (min, max) = (bounds.min, bounds.max);
+    push(color, lifetime, |points| {
+        let corner = |i: usize| {
+            Vector3::new(
+                if i & 1 == 0 { min.x } else { max.x },
+                if i & 2 == 0 { min.y } else { max.y },
+                if i & 4 == 0 { min.z } else { max.z },
+            )
+        };
+        box_edges(points, corner);
+    });
+}
+
+/// Three circles around the axes.
+pub fn sphere(center: Vector3<f32>, radius: f32, color: Vector3<f32>, lifetime: f32) {
+    push(color, lifetime, |points| {
+        let (x, y, z) = (Vector3::unit_x(), Vector3::unit_y(), Vector3::unit_z());
+        circle_points(points, center, x, y, radius);
+        circle_points(points, center, y, z, radius);
+        circle_points(points, center, z, x, radius);
+    });
+}
+
+/// A circle in the plane through `center` perpendicular to `normal`.
+pub fn circle(
+    center: Vector3<f32>,
+    normal: Vector3<f32>,
+    radius: f32,
+    color: Vector3<f32>,
+    lifetime: f32,
+) {
+    push(color, lifetime, |points| {
+        let (u, v) = basis(normal.normalize());
+        circle_points(points, center, u, v, radius);
+    });
+}
+
+/// The frustum of a projection * view matrix (OpenGL clip space).
+pub fn frustum(view_projection: Matrix4<f32>, color: Vector3<f32>, lifetime: f32) {
+    push(color, lifetime, |points| {
+        if let Some(inverse) = view_projection.invert() {
+            let corner = |i: usize| {
+                let sign = |bit: usize| if i & bit == 0 { -1.0 } else { 1.0 };
+                let p = inverse * Vector4::new(sign(1), sign(2), sign(4), 1.0);
+                p.truncate() / p.w
+            };
+            box_edges(points, corner);
+        }
+    });
+}
+
+/// The x, y and z axes of `transform` in red, green and blue, `size` long.
+pub fn axes(transform: Matrix4<f32>, size: f32, lifetime: f32) {
+    let origin = transform.w.truncate();
+    let axis = |v: Vector4<f32>| origin + v.truncate() * size;
+    line(origin, axis(transform.x), Vector3::new(1.0, 0.0, 0.0), lifetime);
+    line(origin, axis(transform.y), Vector3::new(0.0, 1.0, 0.0), lifetime);
+    line(origin, axis(transform.z), Vector3::new(0.0, 0.0, 1.0), lifetime);
+}
+
+/// A label at `position`, drawn by ImGui on top of the scene.
+pub fn text_3d<S: Into<String>>(
+    position: Vector3<f32>,
+    text: S,
+    color: Vector3<f32>,
+    lifetime: f32,
+) {
+    #[cfg(debug_assertions)]
+    if let Ok(mut d) = DEBUG_DRAW.lock() {
+        d.texts.push(Text {
+            position,
+            text: text.into(),
+            color,
+            lifetime,
+        });
+    }
+}
+
+/// Two unit vectors perpendicular to `n` and each other.
+fn basis(n: Vector3<f32>) -> (Vector3<f32>, Vector3<f32>) {
+    let helper = if n.y.abs() < 0.99 {
+        Vector3::unit_y()
+    } else {
+        Vector3::unit_x()
+    };
+    let u = n.cross(helper).normalize();
+    (u, u.cross(n))
+}
+
+fn circle_points(
+    points: &mut Vec<Vector3<f32>>,
+    center: Vector3<f32>,
+    u: Vector3<f32>,
+    v: Vector3<f32>,
+    radius: f32,
+) {
+    let at = |i: usize| {
+        let a = i as f32 / CIRCLE_SEGMENTS as f32 * std::f32::consts::PI * 2.0;
+        center + (u * a.cos() + v * a.sin()) * radius
+    };
+    for i in 0..CIRCLE_SEGMENTS {
+        points.extend_from_slice(&[at(i), at(i + 1)]);
+    }
+}
+
+/// The 12 edges of a box, corner `i` has bit 0, 1 and 2 set for the max x, y and z side.
+fn box_edges<F: Fn(usize) -> Vector3<f32>>(points: &mut Vec<Vector3<f32>>, corner: F) {
+    for i in 0..8 {
+        for bit in [1, 2, 4].iter() {
+            if i & bit == 0 {
+                points.extend_from_slice(&[corner(i), corner(i | bit)]);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(position: Vector3<f32>, lifetime: f32) -> Text {
+        Text {
+            position,
+            text: String::from("ape"),
+            color: Vector3::new(1.0, 1.0, 1.0),
+            lifetime,
+        }
+    }
+
+    #[test]
+    fn labels_are_placed_once_per_flush_and_expire_there() {
+        let mut d = DebugDraw::default();
+        d.texts.push(text(Vector3::new(0.0, 0.0, 0.0), 0.0));
+        d.texts.push(text(Vector3::new(0.5, 0.5, 0.0), 1.0));
+        // outside of clip space, kept but not shown
+        d.texts.push(text(Vector3::new(2.0, 0.0, 0.0), 1.0));
+
+        d.place_labels(Matrix4::identity());
+        assert_eq!(d.labels.len(), 2);
+        assert_eq!(d.texts.len(), 2);
+
+        d.texts.iter_mut().for_each(|t| t.lifetime -= 1.0);
+        d.place_labels(Matrix4::identity());
+        assert_eq!(d.labels.len(), 1);
+        assert!(d.texts.is_empty());
+
+        d.place_labels(Matrix4::identity());
+        assert!(d.labels.is_empty());
+    }
+}
@@ -0,0 +1,215 @@
+use std::sync::Mutex;
+
+use cgmath::{Matrix4, Vector2, Vector3};
+
+use super::{
+    geometry::{instance::InstanceBuffer, mesh::MeshToken},
+    render_queue::uniform_location,
+    rendertarget::{bound_framebuffer, RenderTarget, RenderTargetBuilder},
+    shader::{get_shader_repo, shader_structs::GBufferGeometry},
+    shadow::PointShadow,
+    FIRST_MATERIAL_TEXTURE_UNIT,
+};
+use crate::black_sheep::error::Result;
+
+// the light pass runs before the render queue, which binds the material units again
+const FIRST_GBUFFER_UNIT: u32 = FIRST_MATERIAL_TEXTURE_UNIT;
+
+lazy_static! {
+    static ref DEFERRED_SETTINGS: Mutex<DeferredSettings> =
+        Mutex::new(DeferredSettings::default());
+}
+
+/// What the light pass writes, everything but `Lit` shows one G-buffer channel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GBufferChannel {
+    Lit = 0,
+    Albedo = 1,
+    Normal = 2,
+    Position = 3,
+    Depth = 4,
+    Material = 5,
+}
+
+impl GBufferChannel {
+    pub const ALL: [GBufferChannel; 6] = [
+        GBufferChannel::Lit,
+        GBufferChannel::Albedo,
+        GBufferChannel::Normal,
+        GBufferChannel::Position,
+        GBufferChannel::Depth,
+        GBufferChannel::Material,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            GBufferChannel::Lit => "lit",
+            GBufferChannel::Albedo => "albedo",
+            GBufferChannel::Normal => "normal",
+            GBufferChannel::Position => "position",
+            GBufferChannel::Depth => "depth",
+            GBufferChannel::Material => "material",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DeferredSettings {
+    pub enabled: bool,
+    pub channel: GBufferChannel,
+    /// view depth shown as white in the depth channel
+    pub depth_range: f32,
+}
+
+impl Default for DeferredSettings {
+    fn default() -> Self {
+        DeferredSettings {
+            enabled: false,
+            channel: GBufferChannel::Lit,
+            depth_range: 200.0,
+        }
+    }
+}
+
+pub fn settings() -> DeferredSettings {
+    DEFERRED_SETTINGS.lock().map(|s| *s).unwrap_or_default()
+}
+
+pub fn set_settings(settings: DeferredSettings) {
+    if let Ok(mut s) = DEFERRED_SETTINGS.lock() {
+        *s = settings;
+    }
+}
+
+/// Deferred shading: opaque geometry is rendered into the G-buffer (albedo, normal,
+/// world position with view depth, material) and lit in one fullscreen pass over all lights.
+pub struct GBuffer {
+    target: RenderTarget,
+    empty_vao: u32,
+}
+
+impl GBuffer {
+    /// Follows the scene viewport. Leaves the previously bound framebuffer bound.
+    pub fn new() -> Result<Self> {
+        let previous = bound_framebuffer();
+        let target = RenderTargetBuilder::viewport_sized(1.0)
+            .color(gl::RGBA8)
+            .color(gl::RGBA16F)
+            .color(gl::RGBA32F)
+            .color(gl::RGBA8)
+            .depth_texture()
+            .build()?;
+        let mut empty_vao = 0;
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, previous);
+            gl::GenVertexArrays(1, &mut empty_vao);
+        }
+        Ok(GBuffer { target, empty_vao })
+    }
+
+    /// Clears the G-buffer and fills it through `draw`, `view_projection` is the camera's.
+    /// Restores the framebuffer binding.
+    pub fn geometry_pass<F: FnMut(&GeometryPass)>(
+        &mut self,
+        view_projection: Matrix4<f32>,
+        mut draw: F,
+    ) {
+        let previous = bound_framebuffer();
+        self.target.bind_framebuffer();
+        unsafe {
+            gl::Disable(gl::BLEND);
+            gl::Enable(gl::DEPTH_TEST);
+            gl::DepthMask(gl::TRUE);
+            gl::ClearColor(0.0, 0.0, 0.0, 0.0);
+            gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+        }
+        // fetched every frame, a hot reload only updates the uniform locations in the repo
+        let geometry = get_shader_repo().gbuffer;
+        geometry.use_program();
+        geometry.set_VP(view_projection);
+        draw(&GeometryPass { program: &geometry });
+        unsafe {
+            gl::Enable(gl::BLEND);
+            gl::BindFramebuffer(gl::FRAMEBUFFER, previous);
+        }
+    }
+
+    /// Lights the G-buffer into the bound framebuffer and writes its depth, so forward
+    /// rendered items drawn afterwards are hidden behind it.
+    pub fn light_pass(&self, camera_position: Vector3<f32>, shadow: &PointShadow) {
+        let settings = settings();
+        for i in 0..4 {
+            bind_texture(FIRST_GBUFFER_UNIT + i, self.target.texture(i as usize));
+        }
+        if let Some(depth) = self.target.depth_texture() {
+            bind_texture(FIRST_GBUFFER_UNIT + 4, depth);
+        }
+
+        let unit = |i: u32| (FIRST_GBUFFER_UNIT + i) as i32;
+        let repo = get_shader_repo();
+        let light = repo.deferred_light;
+        let light_program = repo.program("deferred_light").unwrap();
+        light.use_program();
+        light.set_g_albedo(unit(0));
+        light.set_g_normal(unit(1));
+        light.set_g_position(unit(2));
+        light.set_g_material(unit(3));
+        light.set_g_depth(unit(4));
+        light.set_camera_position(camera_position);
+        light.set_channel(settings.channel as i32);
+        light.set_depth_range(settings.depth_range);
+        for (name, value) in shadow.uniforms().iter() {
+            value.apply(uniform_location(light_program, name));
+        }
+
+        unsafe {
+            gl::Disable(gl::BLEND);
+            gl::DepthFunc(gl::ALWAYS);
+            gl::BindVertexArray(self.empty_vao);
+            gl::DrawArrays(gl::TRIANGLES, 0, 3);
+            gl::BindVertexArray(0);
+            gl::DepthFunc(gl::LESS);
+            gl::Enable(gl::BLEND);
+        }
+    }
+}
+
+impl Drop for GBuffer {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteVertexArrays(1, &self.empty_vao);
+        }
+    }
+}
+
+/// Handed to the callback of `GBuffer::geometry_pass`, with the G-buffer program in use.
+pub struct GeometryPass<'a> {
+    program: &'a GBufferGeometry,
+}
+
+impl<'a> GeometryPass<'a> {
+    /// `specular` scales the highlight, `roughness` widens it.
+    pub fn material(&self, specular: f32, roughness: f32) {
+        self.program.set_material(Vector2::new(specular, roughness));
+    }
+
+    pub fn draw(&self, mesh: &MeshToken, model: Matrix4<f32>, color: Vector3<f32>) {
+        self.program.set_instanced(0);
+        self.program.set_M(model);
+        self.program.set_color(color);
+        mesh.bind_vertex_array();
+        mesh.draw_triangle_elements();
+    }
+
+    pub fn draw_instanced(&self, instances: &InstanceBuffer) {
+        self.program.set_instanced(1);
+        instances.draw_triangle_elements();
+    }
+}
+
+fn bind_texture(unit: u32, texture: u32) {
+    unsafe {
+        gl::ActiveTexture(gl::TEXTURE0 + unit);
+        gl::BindTexture(gl::TEXTURE_2D, texture);
+    }
+}
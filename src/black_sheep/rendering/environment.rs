@@ -0,0 +1,331 @@
+use std::{fs::File, io::BufReader};
+
+use image::codecs::hdr::HdrDecoder;
+
+use super::{
+    render_queue::{DrawItem, UniformValue},
+    rendertarget::{bound_framebuffer, gen_framebuffer},
+    shader::get_shader_repo,
+    BRDF_LUT_TEXTURE_UNIT, IRRADIANCE_TEXTURE_UNIT, PREFILTER_TEXTURE_UNIT,
+};
+use crate::black_sheep::error::{EngineError, Result};
+
+const ENVIRONMENT_SIZE: i32 = 512;
+const IRRADIANCE_SIZE: i32 = 32;
+const PREFILTER_SIZE: i32 = 128;
+const PREFILTER_MIPS: i32 = 5;
+const BRDF_LUT_SIZE: i32 = 512;
+const BRDF_SAMPLES: i32 = 1024;
+
+/// Image based lighting baked from an equirectangular environment: the environment cube map,
+/// its diffuse irradiance, the specular prefiltered mips and the split sum BRDF lookup table.
+pub struct Environment {
+    pub environment: u32,
+    pub irradiance: u32,
+    pub prefilter: u32,
+    pub brdf_lut: u32,
+    pub intensity: f32,
+}
+
+impl Environment {
+    /// Loads a Radiance .hdr panorama.
+    pub fn from_hdr(path: &str) -> Result<Self> {
+        let (width, height, data) = load_hdr(path)?;
+        Self::from_equirect(width, height, &data)
+    }
+
+    /// A sky to ground gradient, used when no environment file is configured.
+    pub fn gradient() -> Result<Self> {
+        let (width, height) = (64, 32);
+        let mut data = Vec::with_capacity((width * height * 3) as usize);
+        for y in 0..height {
+            let t = y as f32 / (height - 1) as f32;
+            let color = if t < 0.5 {
+                let s = t * 2.0;
+                [0.3 + 0.5 * s, 0.5 + 0.35 * s, 0.9 - 0.1 * s]
+            } else {
+                let s = (t - 0.5) * 2.0;
+                [0.8 - 0.5 * s, 0.85 - 0.6 * s, 0.8 - 0.65 * s]
+            };
+            for _ in 0..width {
+                data.extend_from_slice(&color);
+            }
+        }
+        Self::from_equirect(width, height, &data)
+    }
+
+    /// Bakes all maps from RGB float texels, row 0 is the top of the sky.
+    /// Restores framebuffer, viewport and depth test.
+    pub fn from_equirect(width: i32, height: i32, data: &[f32]) -> Result<Self> {
+        // mipmapped so the irradiance pass can sample a blurred level
+        let environment = equirect_to_cube(width, height, data, ENVIRONMENT_SIZE);
+
+        let previous = bound_framebuffer();
+        let mut viewport = [0; 4];
+        let mut empty_vao = 0;
+        unsafe {
+            gl::GetIntegerv(gl::VIEWPORT, viewport.as_mut_ptr());
+            gl::Disable(gl::DEPTH_TEST);
+            gl::GenVertexArrays(1, &mut empty_vao);
+            gl::BindVertexArray(empty_vao);
+        }
+        let frame_buffer = gen_framebuffer();
+        let repo = get_shader_repo();
+
+        let irradiance = gen_cube_map(IRRADIANCE_SIZE, 1);
+        bind_cube(IRRADIANCE_TEXTURE_UNIT, environment);
+        repo.irradiance.use_program();
+        repo.irradiance
+            .set_environment(IRRADIANCE_TEXTURE_UNIT as i32);
+        render_faces(irradiance, IRRADIANCE_SIZE, 0, |face| {
+            repo.irradiance.set_face(face)
+        });
+
+        let prefilter = gen_cube_map(PREFILTER_SIZE, PREFILTER_MIPS);
+        repo.prefilter.use_program();
+        repo.prefilter
+            .set_environment(IRRADIANCE_TEXTURE_UNIT as i32);
+        repo.prefilter.set_resolution(ENVIRONMENT_SIZE as f32);
+        for mip in 0..PREFILTER_MIPS {
+            repo.prefilter
+                .set_roughness(mip as f32 / (PREFILTER_MIPS - 1) as f32);
+            render_faces(prefilter, PREFILTER_SIZE >> mip, mip, |face| {
+                repo.prefilter.set_face(face)
+            });
+        }
+
+        let mut brdf_lut = 0;
+        unsafe {
+            gl::GenTextures(1, &mut brdf_lut);
+            gl::BindTexture(gl::TEXTURE_2D, brdf_lut);
+            gl::TexStorage2D(gl::TEXTURE_2D, 1, gl::RG16F, BRDF_LUT_SIZE, BRDF_LUT_SIZE);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as i32);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as i32);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as i32);
+            gl::FramebufferTexture2D(
+                gl::FRAMEBUFFER,
+                gl::COLOR_ATTACHMENT0,
+                gl::TEXTURE_2D,
+                brdf_lut,
+                0,
+            );
+            gl::Viewport(0, 0, BRDF_LUT_SIZE, BRDF_LUT_SIZE);
+        }
+        repo.brdf_lut.use_program();
+        repo.brdf_lut.set_sample_count(BRDF_SAMPLES);
+        unsafe {
+            gl::DrawArrays(gl::TRIANGLES, 0, 3);
+        }
+
+        unsafe {
+            gl::BindVertexArray(0);
+            gl::DeleteVertexArrays(1, &empty_vao);
+            gl::BindFramebuffer(gl::FRAMEBUFFER, previous);
+            gl::DeleteFramebuffers(1, &frame_buffer);
+            gl::Viewport(viewport[0], viewport[1], viewport[2], viewport[3]);
+            gl::Enable(gl::DEPTH_TEST);
+        }
+
+        #[cfg(not(feature = "debug_off"))]
+        println!(
+            "environment baked from {}x{}: env {}, irradiance {}, prefilter {}, brdf {}",
+            width, height, environment, irradiance, prefilter, brdf_lut
+        );
+
+        let env = Environment {
+            environment,
+            irradiance,
+            prefilter,
+            brdf_lut,
+            intensity: 1.0,
+        };
+        env.bind();
+        Ok(env)
+    }
+
+    /// Binds the maps to their reserved texture units.
+    pub fn bind(&self) {
+        bind_cube(IRRADIANCE_TEXTURE_UNIT, self.irradiance);
+        bind_cube(PREFILTER_TEXTURE_UNIT, self.prefilter);
+        unsafe {
+            gl::ActiveTexture(gl::TEXTURE0 + BRDF_LUT_TEXTURE_UNIT);
+            gl::BindTexture(gl::TEXTURE_2D, self.brdf_lut);
+        }
+    }
+
+    /// Adds the uniforms the pbr shader needs to sample these maps.
+    pub fn receive(&self, item: DrawItem) -> DrawItem {
+        item.uniform(
+            "irradiance_map",
+            UniformValue::Int(IRRADIANCE_TEXTURE_UNIT as i32),
+        )
+        .uniform(
+            "prefilter_map",
+            UniformValue::Int(PREFILTER_TEXTURE_UNIT as i32),
+        )
+        .uniform("brdf_lut", UniformValue::Int(BRDF_LUT_TEXTURE_UNIT as i32))
+        .uniform(
+            "prefilter_mips",
+            UniformValue::Float((PREFILTER_MIPS - 1) as f32),
+        )
+        .uniform("ibl_intensity", UniformValue::Float(self.intensity))
+    }
+}
+
+impl Drop for Environment {
+    fn drop(&mut self) {
+        #[cfg(not(feature = "debug_off"))]
+        println!("environment cleanup");
+        let textures = [
+            self.environment,
+            self.irradiance,
+            self.prefilter,
+            self.brdf_lut,
+        ];
+        unsafe {
+            gl::DeleteTextures(textures.len() as i32, textures.as_ptr());
+        }
+    }
+}
+
+/// Reads a Radiance .hdr file into its size and RGB float texels, row 0 at the top.
+pub fn load_hdr(path: &str) -> Result<(i32, i32, Vec<f32>)> {
+    let file = File::open(path).map_err(|e| EngineError::Io(String::from(path), e))?;
+    let decoder = HdrDecoder::new(BufReader::new(file))
+        .map_err(|e| EngineError::Image(String::from(path), e))?;
+    let meta = decoder.metadata();
+    let pixels = decoder
+        .read_image_hdr()
+        .map_err(|e| EngineError::Image(String::from(path), e))?;
+    let data: Vec<f32> = pixels.iter().flat_map(|p| p.0.iter().copied()).collect();
+    Ok((meta.width as i32, meta.height as i32, data))
+}
+
+/// Projects an equirectangular panorama onto a new mipmapped cube map of `size`.
+/// Restores framebuffer, viewport and depth test.
+pub fn equirect_to_cube(width: i32, height: i32, data: &[f32], size: i32) -> u32 {
+    let previous = bound_framebuffer();
+    let mut viewport = [0; 4];
+    let mut empty_vao = 0;
+    let mut equirect = 0;
+    unsafe {
+        gl::GetIntegerv(gl::VIEWPORT, viewport.as_mut_ptr());
+        gl::Enable(gl::TEXTURE_CUBE_MAP_SEAMLESS);
+        gl::Disable(gl::DEPTH_TEST);
+        gl::GenVertexArrays(1, &mut empty_vao);
+        gl::BindVertexArray(empty_vao);
+
+        gl::GenTextures(1, &mut equirect);
+        gl::ActiveTexture(gl::TEXTURE0 + IRRADIANCE_TEXTURE_UNIT);
+        gl::BindTexture(gl::TEXTURE_2D, equirect);
+        gl::TexImage2D(
+            gl::TEXTURE_2D,
+            0,
+            gl::RGB16F as i32,
+            width,
+            height,
+            0,
+            gl::RGB,
+            gl::FLOAT,
+            data.as_ptr() as *const std::ffi::c_void,
+        );
+        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as i32);
+        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
+        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::REPEAT as i32);
+        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as i32);
+    }
+    let frame_buffer = gen_framebuffer();
+    let repo = get_shader_repo();
+
+    let cube = gen_cube_map(size, mip_count(size));
+    repo.equirect_to_cube.use_program();
+    repo.equirect_to_cube
+        .set_equirect(IRRADIANCE_TEXTURE_UNIT as i32);
+    render_faces(cube, size, 0, |face| repo.equirect_to_cube.set_face(face));
+    unsafe {
+        gl::DeleteTextures(1, &equirect);
+        gl::BindTexture(gl::TEXTURE_CUBE_MAP, cube);
+        gl::GenerateMipmap(gl::TEXTURE_CUBE_MAP);
+
+        gl::BindVertexArray(0);
+        gl::DeleteVertexArrays(1, &empty_vao);
+        gl::BindFramebuffer(gl::FRAMEBUFFER, previous);
+        gl::DeleteFramebuffers(1, &frame_buffer);
+        gl::Viewport(viewport[0], viewport[1], viewport[2], viewport[3]);
+        gl::Enable(gl::DEPTH_TEST);
+    }
+    cube
+}
+
+fn mip_count(size: i32) -> i32 {
+    32 - (size as u32).leading_zeros() as i32
+}
+
+pub(crate) fn gen_cube_map(size: i32, mips: i32) -> u32 {
+    let mut cube = 0;
+    unsafe {
+        gl::GenTextures(1, &mut cube);
+        gl::BindTexture(gl::TEXTURE_CUBE_MAP, cube);
+        gl::TexStorage2D(gl::TEXTURE_CUBE_MAP, mips, gl::RGB16F, size, size);
+        let min_filter = if mips > 1 {
+            gl::LINEAR_MIPMAP_LINEAR
+        } else {
+            gl::LINEAR
+        };
+        gl::TexParameteri(
+            gl::TEXTURE_CUBE_MAP,
+            gl::TEXTURE_MIN_FILTER,
+            min_filter as i32,
+        );
+        gl::TexParameteri(
+            gl::TEXTURE_CUBE_MAP,
+            gl::TEXTURE_MAG_FILTER,
+            gl::LINEAR as i32,
+        );
+        gl::TexParameteri(
+            gl::TEXTURE_CUBE_MAP,
+            gl::TEXTURE_WRAP_S,
+            gl::CLAMP_TO_EDGE as i32,
+        );
+        gl::TexParameteri(
+            gl::TEXTURE_CUBE_MAP,
+            gl::TEXTURE_WRAP_T,
+            gl::CLAMP_TO_EDGE as i32,
+        );
+        gl::TexParameteri(
+            gl::TEXTURE_CUBE_MAP,
+            gl::TEXTURE_WRAP_R,
+            gl::CLAMP_TO_EDGE as i32,
+        );
+    }
+    cube
+}
+
+fn bind_cube(unit: u32, cube: u32) {
+    unsafe {
+        gl::ActiveTexture(gl::TEXTURE0 + unit);
+        gl::BindTexture(gl::TEXTURE_CUBE_MAP, cube);
+    }
+}
+
+/// Draws a fullscreen triangle into each face of `mip` of `cube`, with the program in use.
+/// Expects the baking framebuffer and an empty vertex array to be bound.
+fn render_faces<F: FnMut(i32)>(cube: u32, size: i32, mip: i32, mut set_face: F) {
+    unsafe {
+        gl::Viewport(0, 0, size, size);
+    }
+    for face in 0..6 {
+        set_face(face);
+        unsafe {
+            gl::FramebufferTexture2D(
+                gl::FRAMEBUFFER,
+                gl::COLOR_ATTACHMENT0,
+                gl::TEXTURE_CUBE_MAP_POSITIVE_X + face as u32,
+                cube,
+                mip,
+            );
+            gl::DrawArrays(gl::TRIANGLES, 0, 3);
+        }
+    }
+}
@@ -0,0 +1,222 @@
+use std::sync::Mutex;
+
+use cgmath::{InnerSpace, Matrix4, Vector3, Vector4};
+
+lazy_static! {
+    static ref CULL_STATS: Mutex<CullStats> = Mutex::new(CullStats::default());
+}
+
+/// Axis aligned bounding box.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Aabb {
+    pub min: Vector3<f32>,
+    pub max: Vector3<f32>,
+}
+
+impl Aabb {
+    pub fn new(min: Vector3<f32>, max: Vector3<f32>) -> Self {
+        Aabb { min, max }
+    }
+
+    pub fn center(&self) -> Vector3<f32> {
+        (self.min + self.max) * 0.5
+    }
+
+    pub fn half_extents(&self) -> Vector3<f32> {
+        (self.max - self.min) * 0.5
+    }
+
+    /// Smallest box around the transformed box.
+    pub fn transform(&self, m: &Matrix4<f32>) -> Aabb {
+        let c = (*m * self.center().extend(1.0)).truncate();
+        let e = self.half_extents();
+        let extent = Vector3::new(
+            m.x.x.abs() * e.x + m.y.x.abs() * e.y + m.z.x.abs() * e.z,
+            m.x.y.abs() * e.x + m.y.y.abs() * e.y + m.z.y.abs() * e.z,
+            m.x.z.abs() * e.x + m.y.z.abs() * e.y + m.z.z.abs() * e.z,
+        );
+        Aabb::new(c - extent, c + extent)
+    }
+}
+
+/// The six planes of a view frustum, normals pointing inwards.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Frustum {
+    planes: [Vector4<f32>; 6],
+}
+
+impl Frustum {
+    /// Extracts the planes from a projection * view matrix (OpenGL clip space).
+    pub fn from_matrix(m: &Matrix4<f32>) -> Self {
+        let row = |i: usize| Vector4::new(m.x[i], m.y[i], m.z[i], m.w[i]);
+        let (r0, r1, r2, r3) = (row(0), row(1), row(2), row(3));
+
+        let normalize = |p: Vector4<f32>| p / p.truncate().magnitude();
+        Frustum {
+            planes: [
+                normalize(r3 + r0),
+                normalize(r3 - r0),
+                normalize(r3 + r1),
+                normalize(r3 - r1),
+                normalize(r3 + r2),
+                normalize(r3 - r2),
+            ],
+        }
+    }
+
+    /// Conservative, a box near a frustum corner can pass without being visible.
+    pub fn intersects_aabb(&self, b: &Aabb) -> bool {
+        self.planes.iter().all(|plane| {
+            // the corner furthest along the plane normal
+            let p = Vector3::new(
+                if plane.x >= 0.0 { b.max.x } else { b.min.x },
+                if plane.y >= 0.0 { b.max.y } else { b.min.y },
+                if plane.z >= 0.0 { b.max.z } else { b.min.z },
+            );
+            distance(plane, p) >= 0.0
+        })
+    }
+}
+
+fn distance(plane: &Vector4<f32>, p: Vector3<f32>) -> f32 {
+    plane.truncate().dot(p) + plane.w
+}
+
+/// Number of objects tested against the frustum in one frame.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct CullStats {
+    pub tested: u32,
+    pub culled: u32,
+}
+
+/// Counts one culling test for the debug overlay.
+pub fn record_cull(visible: bool) {
+    if let Ok(mut stats) = CULL_STATS.lock() {
+        stats.tested += 1;
+        if !visible {
+            stats.culled += 1;
+        }
+    }
+}
+
+/// Returns the counts since the last call and resets them, called once per frame by the engine.
+pub fn take_cull_stats() -> CullStats {
+    CULL_STATS
+        .lock()
+        .map(|mut stats| std::mem::take(&mut *stats))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use cgmath::{perspective, Deg, Point3};
+
+    use super::*;
+
+    const EPSILON: f32 = 1e-4;
+
+    fn close(a: Vector4<f32>, b: Vector4<f32>) -> bool {
+        (a - b).magnitude() < EPSILON
+    }
+
+    fn assert_aabb(actual: Aabb, min: Vector3<f32>, max: Vector3<f32>) {
+        assert!(
+            close(actual.min.extend(0.0), min.extend(0.0))
+                && close(actual.max.extend(0.0), max.extend(0.0)),
+            "{:?} != {:?} {:?}",
+            actual,
+            min,
+            max
+        );
+    }
+
+    /// 90° fov, near 1 and far 100, the eye at z = 5 looking at the origin.
+    fn camera_frustum() -> Frustum {
+        let projection = perspective(Deg(90.0), 1.0, 1.0, 100.0);
+        let view = Matrix4::look_at_rh(
+            Point3::new(0.0, 0.0, 5.0),
+            Point3::new(0.0, 0.0, 0.0),
+            Vector3::unit_y(),
+        );
+        Frustum::from_matrix(&(projection * view))
+    }
+
+    fn cube(center: Vector3<f32>, half: f32) -> Aabb {
+        let e = Vector3::new(half, half, half);
+        Aabb::new(center - e, center + e)
+    }
+
+    #[test]
+    fn planes_of_perspective_view() {
+        let s = std::f32::consts::FRAC_1_SQRT_2;
+        let expected = [
+            // left, right, bottom and top meet at the eye in 45° angles
+            Vector4::new(s, 0.0, -s, 5.0 * s),
+            Vector4::new(-s, 0.0, -s, 5.0 * s),
+            Vector4::new(0.0, s, -s, 5.0 * s),
+            Vector4::new(0.0, -s, -s, 5.0 * s),
+            // near at z = 4, far at z = -95
+            Vector4::new(0.0, 0.0, -1.0, 4.0),
+            Vector4::new(0.0, 0.0, 1.0, 95.0),
+        ];
+        let frustum = camera_frustum();
+        for (plane, expected) in frustum.planes.iter().zip(expected.iter()) {
+            assert!(close(*plane, *expected), "{:?} != {:?}", plane, expected);
+        }
+    }
+
+    #[test]
+    fn transform_scales_and_translates() {
+        let b = Aabb::new(Vector3::new(0.0, 0.0, 0.0), Vector3::new(1.0, 1.0, 1.0));
+        let m = Matrix4::from_translation(Vector3::new(1.0, -2.0, 3.0))
+            * Matrix4::from_nonuniform_scale(2.0, 3.0, 4.0);
+        assert_aabb(
+            b.transform(&m),
+            Vector3::new(1.0, -2.0, 3.0),
+            Vector3::new(3.0, 1.0, 7.0),
+        );
+    }
+
+    #[test]
+    fn transform_grows_around_rotated_box() {
+        let b = cube(Vector3::new(0.0, 0.0, 0.0), 1.0);
+        let m = Matrix4::from_translation(Vector3::new(10.0, 0.0, 0.0))
+            * Matrix4::from_angle_z(Deg(45.0));
+        let r = std::f32::consts::SQRT_2;
+        assert_aabb(
+            b.transform(&m),
+            Vector3::new(10.0 - r, -r, -1.0),
+            Vector3::new(10.0 + r, r, 1.0),
+        );
+    }
+
+    #[test]
+    fn aabb_inside_is_visible() {
+        assert!(camera_frustum().intersects_aabb(&cube(Vector3::new(0.0, 0.0, 0.0), 0.5)));
+    }
+
+    #[test]
+    fn aabb_outside_a_side_plane_is_culled() {
+        let frustum = camera_frustum();
+        // the frustum is 10 wide at the origin
+        assert!(!frustum.intersects_aabb(&cube(Vector3::new(100.0, 0.0, 0.0), 0.5)));
+        assert!(!frustum.intersects_aabb(&cube(Vector3::new(0.0, -7.0, 0.0), 0.5)));
+        assert!(!frustum.intersects_aabb(&cube(Vector3::new(0.0, 0.0, -200.0), 0.5)));
+    }
+
+    #[test]
+    fn aabb_straddling_a_plane_is_visible() {
+        let frustum = camera_frustum();
+        assert!(frustum.intersects_aabb(&cube(Vector3::new(5.0, 0.0, 0.0), 1.0)));
+        assert!(frustum.intersects_aabb(&cube(Vector3::new(0.0, 0.0, 4.0), 0.5)));
+        assert!(frustum.intersects_aabb(&cube(Vector3::new(0.0, 0.0, -95.0), 0.5)));
+    }
+
+    #[test]
+    fn aabb_behind_the_camera_is_culled() {
+        let frustum = camera_frustum();
+        assert!(!frustum.intersects_aabb(&cube(Vector3::new(0.0, 0.0, 10.0), 0.5)));
+        // passes the side planes, only the near plane rejects it
+        assert!(!frustum.intersects_aabb(&cube(Vector3::new(0.0, 0.0, 20.0), 8.0)));
+    }
+}
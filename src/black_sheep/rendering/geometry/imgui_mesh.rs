@@ -1,7 +1,7 @@
 use gl::types::GLushort;
 use imgui::{DrawCmdParams, DrawData, DrawList};
 
-use crate::black_sheep::rendering::shader;
+use crate::black_sheep::rendering::shader::shader_structs::ImguiShaderProgram;
 
 use super::mesh_util::*;
 
@@ -11,7 +11,6 @@ pub struct ImguiMesh {
     vertex_buffer_id: u32,
     element_buffer_id: u32,
     draw_params: Vec<(i32, DrawCmdParams)>,
-    imgui_shader: shader::shader_structs::ImguiShaderProgram,
 }
 
 impl ImguiMesh {
@@ -26,7 +25,6 @@ impl ImguiMesh {
             vertex_buffer_id,
             element_buffer_id,
             draw_params,
-            imgui_shader: shader::get_shader_repo().imgui,
         }
     }
     pub fn bind_vertex_array(&self) {
@@ -34,7 +32,7 @@ impl ImguiMesh {
             gl::BindVertexArray(self.vertex_array_id);
         }
     }
-    pub fn draw(&self, window_size: [f32; 2]) {
+    pub fn draw(&self, imgui_shader: &ImguiShaderProgram, window_size: [f32; 2]) {
         for dp in self.draw_params.iter() {
             let count = dp.0;
             let offset = dp.1.idx_offset * std::mem::size_of::<GLushort>();
@@ -45,7 +43,7 @@ impl ImguiMesh {
                 window_size[1] - dp.1.clip_rect[3],
             );
 
-            self.imgui_shader.set_tex(dp.1.texture_id.id() as i32);
+            imgui_shader.set_tex(dp.1.texture_id.id() as i32);
 
             unsafe {
                 gl::Scissor(
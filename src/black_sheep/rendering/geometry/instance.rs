@@ -0,0 +1,102 @@
+use cgmath::{Matrix4, Vector3};
+
+use super::{mesh::MeshToken, mesh_util::*};
+
+/// first of the four vec4 locations of the per instance model matrix
+pub const INSTANCE_MODEL_LOCATION: u32 = 5;
+pub const INSTANCE_COLOR_LOCATION: u32 = 9;
+
+const MAT4_SIZE: i32 = std::mem::size_of::<Matrix4<f32>>() as i32;
+const VEC4_SIZE: i32 = MAT4_SIZE / 4;
+
+/// Per instance model matrices and colors attached to the vertex array of a mesh,
+/// drawn with one `glDrawElementsInstanced` call.
+pub struct InstanceBuffer {
+    mesh: MeshToken,
+    model_buffer: u32,
+    color_buffer: u32,
+    models: Vec<Matrix4<f32>>,
+    colors: Vec<Vector3<f32>>,
+}
+
+impl InstanceBuffer {
+    pub fn new(mesh: MeshToken) -> Self {
+        let mut buffers = [0; 2];
+        unsafe {
+            gl::GenBuffers(2, buffers.as_mut_ptr());
+        }
+        let [model_buffer, color_buffer] = buffers;
+
+        mesh.bind_vertex_array();
+        unsafe {
+            gl::BindBuffer(gl::ARRAY_BUFFER, model_buffer);
+        }
+        for column in 0..4 {
+            set_instanced_attribute_pointer(
+                INSTANCE_MODEL_LOCATION + column,
+                4,
+                MAT4_SIZE,
+                VEC4_SIZE * column as i32,
+            );
+        }
+        unsafe {
+            gl::BindBuffer(gl::ARRAY_BUFFER, color_buffer);
+        }
+        set_instanced_attribute_pointer(INSTANCE_COLOR_LOCATION, 3, 0, 0);
+        bind_vertex_array(0);
+
+        InstanceBuffer {
+            mesh,
+            model_buffer,
+            color_buffer,
+            models: Vec::new(),
+            colors: Vec::new(),
+        }
+    }
+
+    /// Replaces all instances and uploads them.
+    pub fn fill<'a, I>(&mut self, instances: I)
+    where
+        I: IntoIterator<Item = (&'a Matrix4<f32>, &'a Vector3<f32>)>,
+    {
+        self.models.clear();
+        self.colors.clear();
+        for (m, c) in instances {
+            self.models.push(*m);
+            self.colors.push(*c);
+        }
+        if !self.models.is_empty() {
+            update_buffer_data(&self.models, self.model_buffer, gl::ARRAY_BUFFER);
+            update_buffer_data(&self.colors, self.color_buffer, gl::ARRAY_BUFFER);
+        }
+    }
+
+    pub fn mesh_uid(&self) -> usize {
+        self.mesh.uid
+    }
+
+    pub fn len(&self) -> usize {
+        self.models.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.models.is_empty()
+    }
+
+    pub fn draw_triangle_elements(&self) {
+        if self.models.is_empty() {
+            return;
+        }
+        self.mesh.bind_vertex_array();
+        self.mesh.draw_triangle_elements_instanced(self.models.len() as i32);
+    }
+}
+
+impl Drop for InstanceBuffer {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteBuffers(1, &self.model_buffer);
+            gl::DeleteBuffers(1, &self.color_buffer);
+        }
+    }
+}
@@ -1,10 +1,13 @@
 use super::mesh_util::*;
+use crate::black_sheep::rendering::frustum::Aabb;
 
 pub struct Mesh {
     pub uid: usize,
     buffer_ids: Vec<u32>,
     array_id: u32,
     pub vertex_count: i32,
+    /// bounds in model space, used for culling
+    pub bounds: Option<Aabb>,
 }
 
 impl Mesh {
@@ -14,9 +17,14 @@ impl Mesh {
             buffer_ids: Vec::new(),
             array_id: gen_vertexarray(),
             vertex_count: -1,
+            bounds: None,
         }
     }
 
+    pub fn set_bounds(&mut self, bounds: Aabb) {
+        self.bounds = Some(bounds);
+    }
+
     pub fn add_floatbuffer<T>(&mut self, data: &[T], attribute_index: u32, attribute_size: i32) {
         if !(attribute_size > 0) {
             panic!("Attribute size needs to be > 0")
@@ -123,6 +131,7 @@ impl From<&Mesh> for MeshToken {
             uid: mesh.uid,
             array_id: mesh.array_id,
             vertex_count: mesh.vertex_count,
+            bounds: mesh.bounds,
         }
     }
 }
@@ -131,6 +140,7 @@ pub struct MeshToken {
     pub uid: usize,
     array_id: u32,
     vertex_count: i32,
+    pub bounds: Option<Aabb>,
 }
 
 impl MeshToken {
@@ -149,4 +159,7 @@ impl MeshToken {
     pub fn draw_line_elements(&self) {
         draw_line_elements(self.vertex_count);
     }
+    pub fn draw_triangle_elements_instanced(&self, instance_count: i32) {
+        draw_triangle_elements_instanced(self.vertex_count, instance_count);
+    }
 }
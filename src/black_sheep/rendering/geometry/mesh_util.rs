@@ -25,6 +25,14 @@ pub fn set_attribute_pointer_for_nerds(
     }
 }
 
+/// Like `set_attribute_pointer_for_nerds`, but the attribute advances once per instance.
+pub fn set_instanced_attribute_pointer(index: u32, size: i32, stride: i32, offset: i32) {
+    set_attribute_pointer_for_nerds(index, gl::FLOAT, size, stride, offset);
+    unsafe {
+        gl::VertexAttribDivisor(index, 1);
+    }
+}
+
 pub fn buffer_data_static<T>(buffer_data: &[T], buffer_type: GLenum) -> u32 {
     let mut vertex_buffer_id = 0;
 
@@ -131,3 +139,14 @@ pub fn draw_line_elements(vertex_count: i32) {
         );
     }
 }
+pub fn draw_triangle_elements_instanced(vertex_count: i32, instance_count: i32) {
+    unsafe {
+        gl::DrawElementsInstanced(
+            TRIANGLES,
+            vertex_count,
+            gl::UNSIGNED_INT,
+            0 as *const std::ffi::c_void,
+            instance_count,
+        );
+    }
+}
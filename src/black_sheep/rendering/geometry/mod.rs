@@ -1,4 +1,5 @@
 pub mod imgui_mesh;
+pub mod instance;
 pub mod mesh;
 mod mesh_util;
 mod unique_index;
@@ -11,11 +12,13 @@ use mesh::Mesh;
 
 use self::mesh::MeshToken;
 
+use crate::black_sheep::error::{EngineError, Result};
+
 lazy_static! {
     static ref MESH_REPO: Mutex<Option<MeshRepo>> = Mutex::new(None);
 }
 
-pub fn init() {
+pub fn init() -> Result<()> {
     let sr = MESH_REPO.lock();
     if sr.is_err() {
         panic!("shader_repo locked failed");
@@ -24,10 +27,11 @@ pub fn init() {
     let mut sr = sr.unwrap();
 
     if sr.is_some() {
-        panic!("shader_repo already initialized")
+        return Err(EngineError::AlreadyInitialized("mesh_repo"));
     }
 
     *sr = Some(MeshRepo::new());
+    Ok(())
 }
 pub fn cleanup() {
     let sr = MESH_REPO.lock();
@@ -87,12 +91,48 @@ impl MeshRepo {
                 .ok()
                 .map(|i| i)
             {
-                self.mesh_i_data.remove(i);
+                self.mesh_i_data.remove(i).cleanup();
             }
         }
     }
 
-    pub fn add_mesh<T: FnMut(&mut Mesh)>(&mut self, name: &str, mut init_mesh: T) -> MeshToken {
+    /// Removes every mesh whose name starts with `prefix`, like the meshes of an imported model.
+    pub fn remove_meshes_with_prefix(&mut self, prefix: &str) {
+        let names: Vec<String> = self
+            .mesh_map
+            .keys()
+            .filter(|name| name.starts_with(prefix))
+            .cloned()
+            .collect();
+        for name in names {
+            self.remove_mesh(&name);
+        }
+    }
+
+    /// Gives every mesh whose name starts with `from` the prefix `to` instead.
+    pub fn rename_meshes_with_prefix(&mut self, from: &str, to: &str) {
+        let names: Vec<String> = self
+            .mesh_map
+            .keys()
+            .filter(|name| name.starts_with(from))
+            .cloned()
+            .collect();
+        for name in names {
+            if let Some(uid) = self.mesh_map.remove(&name) {
+                self.mesh_map.insert(format!("{}{}", to, &name[from.len()..]), uid);
+            }
+        }
+    }
+
+    pub fn add_mesh<T: FnMut(&mut Mesh)>(
+        &mut self,
+        name: &str,
+        mut init_mesh: T,
+    ) -> Result<MeshToken> {
+        if self.mesh_map.contains_key(name) {
+            return Err(EngineError::NameTaken(String::from(name)));
+        }
+
         let unique_index = self.unique_indexer.get_next();
 
         let mut mesh = Mesh::new();
@@ -103,16 +143,9 @@ impl MeshRepo {
         let mesh_token = MeshToken::from(mesh.borrow());
 
         self.mesh_i_data.push(mesh);
+        self.mesh_map.insert(String::from(name), unique_index);
 
-        if self
-            .mesh_map
-            .insert(String::from(name), unique_index)
-            .is_some()
-        {
-            panic!("name already taken!");
-        }
-
-        mesh_token
+        Ok(mesh_token)
     }
 
     pub fn get_mesh_by_uid(&self, uid: &usize) -> Option<&Mesh> {
@@ -0,0 +1,291 @@
+use cgmath::{Matrix4, SquareMatrix, Vector3, Vector4};
+use gltf::image::Format;
+use itertools::Itertools;
+
+use crate::black_sheep::error::{EngineError, Result};
+
+use super::{
+    geometry::{
+        mesh::{Mesh, MeshToken},
+        MeshRepo,
+    },
+    frustum::Aabb,
+    loader::gen_texture,
+    Texture,
+};
+
+pub const POSITION_LOCATION: u32 = 0;
+pub const NORMAL_LOCATION: u32 = 1;
+pub const UV_LOCATION: u32 = 2;
+pub const COLOR_LOCATION: u32 = 3;
+pub const TANGENT_LOCATION: u32 = 4;
+
+pub struct GltfPrimitive {
+    /// name in the `MeshRepo`, "{prefix}/{mesh}/{primitive index}"
+    pub name: String,
+    pub mesh: MeshToken,
+    pub material: Option<usize>,
+}
+
+pub struct GltfMesh {
+    pub name: String,
+    pub primitives: Vec<GltfPrimitive>,
+}
+
+pub struct GltfNode {
+    pub name: Option<String>,
+    pub local: Matrix4<f32>,
+    /// `local` multiplied with the transforms of all parents
+    pub world: Matrix4<f32>,
+    pub parent: Option<usize>,
+    pub children: Vec<usize>,
+    pub mesh: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AlphaMode {
+    Opaque,
+    Mask(f32),
+    Blend,
+}
+
+/// Metallic-roughness parameters, texture indices point into `GltfScene::textures`.
+#[derive(Debug, Clone)]
+pub struct PbrMaterial {
+    pub name: Option<String>,
+    pub base_color_factor: Vector4<f32>,
+    pub base_color_texture: Option<usize>,
+    pub metallic_factor: f32,
+    pub roughness_factor: f32,
+    pub metallic_roughness_texture: Option<usize>,
+    pub normal_texture: Option<usize>,
+    pub normal_scale: f32,
+    pub occlusion_texture: Option<usize>,
+    pub occlusion_strength: f32,
+    pub emissive_factor: Vector3<f32>,
+    pub emissive_texture: Option<usize>,
+    pub alpha_mode: AlphaMode,
+    pub double_sided: bool,
+}
+
+pub struct GltfScene {
+    pub meshes: Vec<GltfMesh>,
+    pub nodes: Vec<GltfNode>,
+    /// root nodes of the default scene
+    pub roots: Vec<usize>,
+    pub materials: Vec<PbrMaterial>,
+    pub textures: Vec<Texture>,
+}
+
+impl GltfScene {
+    /// Visits every node that has a mesh with its world transform.
+    pub fn for_each_mesh_node<F: FnMut(&Matrix4<f32>, &GltfMesh)>(&self, mut f: F) {
+        for node in self.nodes.iter() {
+            if let Some(mesh) = node.mesh {
+                f(&node.world, &self.meshes[mesh]);
+            }
+        }
+    }
+
+    pub fn find_node(&self, name: &str) -> Option<&GltfNode> {
+        self.nodes.iter().find(|n| n.name.as_deref() == Some(name))
+    }
+}
+
+/// Loads every mesh, node, material and embedded texture of a .gltf/.glb file.
+/// Meshes are added to `mesh_repo`, named "{prefix}/{mesh name or index}/{primitive index}".
+pub fn import(mesh_repo: &mut MeshRepo, path: &str, prefix: &str) -> Result<GltfScene> {
+    let (document, buffers, images) =
+        gltf::import(path).map_err(|e| EngineError::Gltf(String::from(path), e))?;
+
+    // checked up front, so a rejected file leaves nothing behind in the mesh repo
+    for mesh in document.meshes() {
+        for primitive in mesh.primitives() {
+            if primitive.mode() != gltf::mesh::Mode::Triangles {
+                let mesh_name = mesh.name().map(String::from);
+                return Err(EngineError::PrimitiveMode(
+                    String::from(path),
+                    mesh_name.unwrap_or_else(|| mesh.index().to_string()),
+                    primitive.mode(),
+                ));
+            }
+        }
+    }
+
+    let textures = document
+        .textures()
+        .map(|t| {
+            let image = &images[t.source().index()];
+            let mut pixels = to_rgba8(image);
+            gen_texture(
+                pixels.as_mut_ptr() as *mut std::ffi::c_void,
+                (image.width as i32, image.height as i32),
+                true,
+            )
+        })
+        .collect_vec();
+
+    let materials = document.materials().map(|m| read_material(&m)).collect_vec();
+
+    let mut meshes = Vec::new();
+    for mesh in document.meshes() {
+        let mesh_name = mesh
+            .name()
+            .map(String::from)
+            .unwrap_or_else(|| mesh.index().to_string());
+
+        let mut primitives = Vec::new();
+        for (i, primitive) in mesh.primitives().enumerate() {
+            let name = format!("{}/{}/{}", prefix, mesh_name, i);
+            let token = mesh_repo.add_mesh(&name, |m| load_primitive(m, &primitive, &buffers))?;
+            primitives.push(GltfPrimitive {
+                name,
+                mesh: token,
+                material: primitive.material().index(),
+            });
+        }
+
+        meshes.push(GltfMesh {
+            name: mesh_name,
+            primitives,
+        });
+    }
+
+    let mut nodes = document
+        .nodes()
+        .map(|n| GltfNode {
+            name: n.name().map(String::from),
+            local: Matrix4::from(n.transform().matrix()),
+            world: Matrix4::identity(),
+            parent: None,
+            children: n.children().map(|c| c.index()).collect(),
+            mesh: n.mesh().map(|m| m.index()),
+        })
+        .collect_vec();
+
+    for i in 0..nodes.len() {
+        for c in nodes[i].children.clone() {
+            nodes[c].parent = Some(i);
+        }
+    }
+
+    let roots = document
+        .default_scene()
+        .or_else(|| document.scenes().next())
+        .map(|s| s.nodes().map(|n| n.index()).collect_vec())
+        .unwrap_or_else(|| (0..nodes.len()).filter(|i| nodes[*i].parent.is_none()).collect());
+
+    let mut stack = roots
+        .iter()
+        .map(|r| (*r, Matrix4::identity()))
+        .collect_vec();
+    while let Some((i, parent_world)) = stack.pop() {
+        let world = parent_world * nodes[i].local;
+        nodes[i].world = world;
+        for c in nodes[i].children.iter() {
+            stack.push((*c, world));
+        }
+    }
+
+    Ok(GltfScene {
+        meshes,
+        nodes,
+        roots,
+        materials,
+        textures,
+    })
+}
+
+/// Uploads all vertex attributes of a primitive that are present,
+/// see the `*_LOCATION` constants for the attribute indices.
+/// A primitive without indices gets one index per vertex, so it can be drawn the same way.
+pub fn load_primitive(m: &mut Mesh, primitive: &gltf::Primitive, buffers: &[gltf::buffer::Data]) {
+    let reader = primitive.reader(|buffer| Some(&buffers[buffer.index()]));
+
+    let bb = primitive.bounding_box();
+    m.set_bounds(Aabb::new(bb.min.into(), bb.max.into()));
+
+    let mut position_count = 0;
+    if let Some(iter) = reader.read_positions() {
+        let p = iter.collect_vec();
+        position_count = p.len() as u32;
+        m.add_floatbuffer(p.as_slice(), POSITION_LOCATION, 3);
+    }
+    if let Some(iter) = reader.read_normals() {
+        let n = iter.collect_vec();
+        m.add_floatbuffer(n.as_slice(), NORMAL_LOCATION, 3);
+    }
+    if let Some(iter) = reader.read_tex_coords(0) {
+        let uv = iter.into_f32().collect_vec();
+        m.add_floatbuffer(uv.as_slice(), UV_LOCATION, 2);
+    }
+    if let Some(iter) = reader.read_colors(0) {
+        let c = iter.into_rgba_f32().collect_vec();
+        m.add_floatbuffer(c.as_slice(), COLOR_LOCATION, 4);
+    }
+    if let Some(iter) = reader.read_tangents() {
+        let t = iter.collect_vec();
+        m.add_floatbuffer(t.as_slice(), TANGENT_LOCATION, 4);
+    }
+    let e = match reader.read_indices() {
+        Some(iter) => iter.into_u32().collect_vec(),
+        None => (0..position_count).collect_vec(),
+    };
+    m.add_elementarraybuffer(e.as_slice());
+}
+
+fn read_material(m: &gltf::Material) -> PbrMaterial {
+    let pbr = m.pbr_metallic_roughness();
+    PbrMaterial {
+        name: m.name().map(String::from),
+        base_color_factor: pbr.base_color_factor().into(),
+        base_color_texture: pbr.base_color_texture().map(|t| t.texture().index()),
+        metallic_factor: pbr.metallic_factor(),
+        roughness_factor: pbr.roughness_factor(),
+        metallic_roughness_texture: pbr.metallic_roughness_texture().map(|t| t.texture().index()),
+        normal_texture: m.normal_texture().map(|t| t.texture().index()),
+        normal_scale: m.normal_texture().map(|t| t.scale()).unwrap_or(1.0),
+        occlusion_texture: m.occlusion_texture().map(|t| t.texture().index()),
+        occlusion_strength: m.occlusion_texture().map(|t| t.strength()).unwrap_or(1.0),
+        emissive_factor: m.emissive_factor().into(),
+        emissive_texture: m.emissive_texture().map(|t| t.texture().index()),
+        alpha_mode: match m.alpha_mode() {
+            gltf::material::AlphaMode::Opaque => AlphaMode::Opaque,
+            gltf::material::AlphaMode::Mask => AlphaMode::Mask(m.alpha_cutoff().unwrap_or(0.5)),
+            gltf::material::AlphaMode::Blend => AlphaMode::Blend,
+        },
+        double_sided: m.double_sided(),
+    }
+}
+
+fn to_rgba8(image: &gltf::image::Data) -> Vec<u8> {
+    let (channels, bytes) = match image.format {
+        Format::R8 => (1, 1),
+        Format::R8G8 => (2, 1),
+        Format::R8G8B8 | Format::B8G8R8 => (3, 1),
+        Format::R8G8B8A8 | Format::B8G8R8A8 => (4, 1),
+        Format::R16 => (1, 2),
+        Format::R16G16 => (2, 2),
+        Format::R16G16B16 => (3, 2),
+        Format::R16G16B16A16 => (4, 2),
+    };
+    let bgr = matches!(image.format, Format::B8G8R8 | Format::B8G8R8A8);
+
+    let mut rgba = Vec::with_capacity((image.width * image.height * 4) as usize);
+    for pixel in image.pixels.chunks_exact(channels * bytes) {
+        // 16 bit channels are little endian, keep the high byte
+        let c = |i: usize| pixel[i * bytes + bytes - 1];
+        let (r, g, b, a) = match channels {
+            1 => (c(0), c(0), c(0), 255),
+            2 => (c(0), c(0), c(0), c(1)),
+            3 => (c(0), c(1), c(2), 255),
+            _ => (c(0), c(1), c(2), c(3)),
+        };
+        if bgr {
+            rgba.extend_from_slice(&[b, g, r, a]);
+        } else {
+            rgba.extend_from_slice(&[r, g, b, a]);
+        }
+    }
+    rgba
+}
@@ -0,0 +1,227 @@
+use cgmath::Vector3;
+use serde::{Deserialize, Serialize};
+
+use crate::black_sheep::ecs::GatherLightsAccessor;
+
+/// Has to match `MAX_LIGHTS` in the lit shaders.
+pub const MAX_LIGHTS: usize = 16;
+/// Uniform buffer binding point of the `Lights` block.
+pub const LIGHTS_BINDING: u32 = 0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum LightKind {
+    Directional,
+    Point,
+    /// cone angles in degrees, measured from the direction
+    Spot { inner: f32, outer: f32 },
+}
+
+/// A light as it is stored in the ECS and in scene files.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Light {
+    pub kind: LightKind,
+    #[serde(default)]
+    pub position: [f32; 3],
+    /// where directional and spot lights point to
+    #[serde(default = "down")]
+    pub direction: [f32; 3],
+    #[serde(default = "white")]
+    pub color: [f32; 3],
+    #[serde(default = "one")]
+    pub intensity: f32,
+    /// distance at which point and spot lights have faded out completely
+    #[serde(default = "default_range")]
+    pub range: f32,
+    /// the first point light with shadows gets the shadow map
+    #[serde(default)]
+    pub shadows: bool,
+}
+
+fn down() -> [f32; 3] {
+    [0.0, -1.0, 0.0]
+}
+
+fn white() -> [f32; 3] {
+    [1.0, 1.0, 1.0]
+}
+
+fn one() -> f32 {
+    1.0
+}
+
+fn default_range() -> f32 {
+    100.0
+}
+
+impl Light {
+    pub fn directional(direction: Vector3<f32>, intensity: f32) -> Self {
+        Light {
+            kind: LightKind::Directional,
+            position: [0.0; 3],
+            direction: direction.into(),
+            color: white(),
+            intensity,
+            range: default_range(),
+            shadows: false,
+        }
+    }
+
+    pub fn point(position: Vector3<f32>, intensity: f32, range: f32) -> Self {
+        Light {
+            kind: LightKind::Point,
+            position: position.into(),
+            direction: down(),
+            color: white(),
+            intensity,
+            range,
+            shadows: false,
+        }
+    }
+
+    pub fn spot(
+        position: Vector3<f32>,
+        direction: Vector3<f32>,
+        inner: f32,
+        outer: f32,
+        intensity: f32,
+        range: f32,
+    ) -> Self {
+        Light {
+            kind: LightKind::Spot { inner, outer },
+            position: position.into(),
+            direction: direction.into(),
+            color: white(),
+            intensity,
+            range,
+            shadows: false,
+        }
+    }
+
+    pub fn color(mut self, color: Vector3<f32>) -> Self {
+        self.color = color.into();
+        self
+    }
+
+    pub fn with_shadows(mut self) -> Self {
+        self.shadows = true;
+        self
+    }
+
+    fn data(&self) -> LightData {
+        let (kind, cone) = match self.kind {
+            LightKind::Directional => (0.0, [0.0; 4]),
+            LightKind::Point => (1.0, [0.0; 4]),
+            LightKind::Spot { inner, outer } => (
+                2.0,
+                [inner.to_radians().cos(), outer.to_radians().cos(), 0.0, 0.0],
+            ),
+        };
+        let [px, py, pz] = self.position;
+        let [dx, dy, dz] = self.direction;
+        let [r, g, b] = self.color;
+        LightData {
+            position_kind: [px, py, pz, kind],
+            direction_range: [dx, dy, dz, self.range],
+            color_intensity: [r, g, b, self.intensity],
+            cone,
+        }
+    }
+}
+
+/// One light in the std140 `Lights` block.
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+struct LightData {
+    position_kind: [f32; 4],
+    direction_range: [f32; 4],
+    color_intensity: [f32; 4],
+    /// cosines of the inner and outer spot angle
+    cone: [f32; 4],
+}
+
+#[repr(C)]
+struct LightBlock {
+    lights: [LightData; MAX_LIGHTS],
+    count: i32,
+    _padding: [i32; 3],
+}
+
+/// Uniform buffer with the lights of the ECS, bound to `LIGHTS_BINDING`.
+pub struct LightBuffer {
+    ubo: u32,
+    shadow_light: Option<(i32, Vector3<f32>)>,
+}
+
+impl LightBuffer {
+    pub fn new() -> Self {
+        let mut ubo = 0;
+        unsafe {
+            gl::GenBuffers(1, &mut ubo);
+            gl::BindBuffer(gl::UNIFORM_BUFFER, ubo);
+            gl::BufferData(
+                gl::UNIFORM_BUFFER,
+                std::mem::size_of::<LightBlock>() as isize,
+                std::ptr::null(),
+                gl::DYNAMIC_DRAW,
+            );
+            gl::BindBufferBase(gl::UNIFORM_BUFFER, LIGHTS_BINDING, ubo);
+            gl::BindBuffer(gl::UNIFORM_BUFFER, 0);
+        }
+        LightBuffer {
+            ubo,
+            shadow_light: None,
+        }
+    }
+
+    /// Uploads the first `MAX_LIGHTS` lights.
+    pub fn upload<'a, I: IntoIterator<Item = &'a Light>>(&mut self, lights: I) {
+        let mut block = LightBlock {
+            lights: [LightData::default(); MAX_LIGHTS],
+            count: 0,
+            _padding: [0; 3],
+        };
+        self.shadow_light = None;
+        for (i, light) in lights.into_iter().take(MAX_LIGHTS).enumerate() {
+            block.lights[i] = light.data();
+            block.count += 1;
+            if self.shadow_light.is_none() && light.shadows && light.kind == LightKind::Point {
+                self.shadow_light = Some((i as i32, Vector3::from(light.position)));
+            }
+        }
+        unsafe {
+            gl::BindBuffer(gl::UNIFORM_BUFFER, self.ubo);
+            gl::BufferSubData(
+                gl::UNIFORM_BUFFER,
+                0,
+                std::mem::size_of::<LightBlock>() as isize,
+                &block as *const LightBlock as *const std::ffi::c_void,
+            );
+            gl::BindBuffer(gl::UNIFORM_BUFFER, 0);
+        }
+    }
+
+    /// Gathers the lights of all `Lamp` entities and uploads them.
+    pub fn gather(&mut self, lights: &GatherLightsAccessor) {
+        let lock = lights.lock();
+        self.upload(lock.iter().map(|(light, _)| light));
+    }
+
+    /// Index and position of the light that gets the shadow map.
+    pub fn shadow_light(&self) -> Option<(i32, Vector3<f32>)> {
+        self.shadow_light
+    }
+}
+
+impl Default for LightBuffer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for LightBuffer {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteBuffers(1, &self.ubo);
+        }
+    }
+}
@@ -1,14 +1,16 @@
 use imgui::FontAtlasTexture;
 
 use super::Texture;
+use crate::black_sheep::error::{EngineError, Result};
 
-pub fn load_texture_from_path(path: &str) -> Option<Texture> {
+pub fn load_texture_from_path(path: &str) -> Result<Texture> {
     use image::io::Reader as ImageReader;
 
-    let im = match ImageReader::open(path) {
-        Ok(im) => im.decode().unwrap().flipv(),
-        Err(_) => return None,
-    };
+    let im = ImageReader::open(path)
+        .map_err(|e| EngineError::Io(String::from(path), e))?
+        .decode()
+        .map_err(|e| EngineError::Image(String::from(path), e))?
+        .flipv();
 
     let im = match im.flipv() {
         image::DynamicImage::ImageRgba8(img) => img,
@@ -17,7 +19,7 @@ pub fn load_texture_from_path(path: &str) -> Option<Texture> {
 
     let dim = im.dimensions();
 
-    Some(gen_texture(
+    Ok(gen_texture(
         im.as_ptr() as *mut std::ffi::c_void,
         (dim.0 as i32, dim.1 as i32),
         true,
@@ -0,0 +1,176 @@
+use std::{
+    collections::{hash_map::DefaultHasher, BTreeMap},
+    hash::{Hash, Hasher},
+};
+
+use cgmath::{Vector2, Vector3, Vector4};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    geometry::mesh::MeshToken,
+    render_queue::{self, DrawItem, Pass, UniformValue},
+    shader, Texture, FIRST_MATERIAL_TEXTURE_UNIT,
+};
+use crate::black_sheep::{
+    assets::Handle,
+    error::{EngineError, Result},
+};
+
+/// Uniform value as written in a material file.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum MaterialValue {
+    Int(i32),
+    Float(f32),
+    Vec2([f32; 2]),
+    Vec3([f32; 3]),
+    Vec4([f32; 4]),
+}
+
+impl From<MaterialValue> for UniformValue {
+    fn from(v: MaterialValue) -> Self {
+        match v {
+            MaterialValue::Int(v) => UniformValue::Int(v),
+            MaterialValue::Float(v) => UniformValue::Float(v),
+            MaterialValue::Vec2(v) => UniformValue::Vec2(Vector2::from(v)),
+            MaterialValue::Vec3(v) => UniformValue::Vec3(Vector3::from(v)),
+            MaterialValue::Vec4(v) => UniformValue::Vec4(Vector4::from(v)),
+        }
+    }
+}
+
+/// A material file, e.g. res/materials/orange.ron.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MaterialDef {
+    /// field name in the `ShaderRepo`
+    pub shader: String,
+    pub pass: Pass,
+    pub uniforms: BTreeMap<String, MaterialValue>,
+    /// sampler uniform -> texture path
+    pub textures: BTreeMap<String, String>,
+}
+
+impl MaterialDef {
+    pub fn load(path: &str) -> Result<Self> {
+        let s = std::fs::read_to_string(path).map_err(|e| EngineError::Io(String::from(path), e))?;
+        ron::from_str(&s).map_err(|e| EngineError::Parse(String::from(path), e))
+    }
+}
+
+/// Names a material by its file, cheap to copy so it can be an ECS component.
+/// The material itself is looked up with `AssetManager::material`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct MaterialId(pub u32);
+
+impl MaterialId {
+    pub fn from_path(path: &str) -> Self {
+        let mut hasher = DefaultHasher::new();
+        path.hash(&mut hasher);
+        MaterialId(hasher.finish() as u32)
+    }
+}
+
+struct TextureSlot {
+    sampler: String,
+    unit: u32,
+    texture: Handle<Texture>,
+}
+
+/// A program of the `ShaderRepo` with named uniform values and textures.
+/// Every texture gets its own unit, counting up from `FIRST_MATERIAL_TEXTURE_UNIT`.
+pub struct Material {
+    pub id: MaterialId,
+    pub shader: String,
+    pub pass: Pass,
+    program: u32,
+    uniforms: Vec<(String, UniformValue)>,
+    textures: Vec<TextureSlot>,
+}
+
+impl Material {
+    pub fn new(id: MaterialId, shader: &str) -> Result<Self> {
+        let program = shader::get_shader_repo()
+            .program(shader)
+            .ok_or_else(|| EngineError::UnknownShader(String::from(shader)))?;
+        Ok(Material {
+            id,
+            shader: String::from(shader),
+            pass: Pass::Opaque,
+            program,
+            uniforms: Vec::new(),
+            textures: Vec::new(),
+        })
+    }
+
+    /// Builds the material of a file, `load_texture` resolves the texture paths.
+    pub fn from_def<F>(id: MaterialId, def: &MaterialDef, mut load_texture: F) -> Result<Self>
+    where
+        F: FnMut(&str) -> Result<Handle<Texture>>,
+    {
+        let mut material = Material::new(id, &def.shader)?;
+        material.pass = def.pass;
+        for (name, value) in def.uniforms.iter() {
+            material.set_uniform(name, UniformValue::from(*value));
+        }
+        for (sampler, path) in def.textures.iter() {
+            material.set_texture(sampler, load_texture(path)?);
+        }
+        Ok(material)
+    }
+
+    pub fn program(&self) -> u32 {
+        self.program
+    }
+
+    pub fn set_uniform(&mut self, name: &str, value: UniformValue) {
+        match self.uniforms.iter_mut().find(|(n, _)| n == name) {
+            Some((_, v)) => *v = value,
+            None => self.uniforms.push((String::from(name), value)),
+        }
+    }
+
+    /// Replaces the texture of `sampler` or gives it the next free unit.
+    pub fn set_texture(&mut self, sampler: &str, texture: Handle<Texture>) {
+        match self.textures.iter_mut().find(|slot| slot.sampler == sampler) {
+            Some(slot) => slot.texture = texture,
+            None => {
+                let unit = FIRST_MATERIAL_TEXTURE_UNIT + self.textures.len() as u32;
+                self.textures.push(TextureSlot {
+                    sampler: String::from(sampler),
+                    unit,
+                    texture,
+                });
+            }
+        }
+    }
+
+    /// Uses the program and binds all uniforms and textures for immediate drawing.
+    pub fn bind(&self) {
+        unsafe { gl::UseProgram(self.program) };
+        for slot in self.textures.iter() {
+            unsafe { gl::ActiveTexture(gl::TEXTURE0 + slot.unit) };
+            slot.texture.get().bind();
+            let location = render_queue::uniform_location(self.program, &slot.sampler);
+            UniformValue::Int(slot.unit as i32).apply(location);
+        }
+        for (name, value) in self.uniforms.iter() {
+            value.apply(render_queue::uniform_location(self.program, name));
+        }
+    }
+
+    /// A render queue item drawing `mesh` with this material, per object uniforms can be added to it.
+    pub fn draw_item(&self, mesh: MeshToken) -> DrawItem {
+        let mut item = DrawItem::new(mesh, self.program)
+            .material(self.id.0)
+            .pass(self.pass);
+        for slot in self.textures.iter() {
+            item = item
+                .texture(slot.unit, slot.texture.get().id())
+                .uniform(slot.sampler.clone(), UniformValue::Int(slot.unit as i32));
+        }
+        for (name, value) in self.uniforms.iter() {
+            item = item.uniform(name.clone(), *value);
+        }
+        item
+    }
+}
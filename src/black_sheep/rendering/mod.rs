@@ -4,8 +4,45 @@ pub mod shader;
 
 pub mod rendertarget;
 
+pub mod frustum;
+
+pub mod render_queue;
+
+pub mod material;
+
+pub mod shadow;
+
+pub mod lights;
+
+pub mod environment;
+
+pub mod pbr;
+
+pub mod postprocess;
+
+pub mod deferred;
+
+pub mod skybox;
+
+pub mod debug_draw;
+
 pub mod loader;
 
+pub mod gltf_import;
+
+/// Texture units the engine keeps bound for ImGui, the `TextureId` of an ImGui image is its unit.
+pub const FONT_TEXTURE_UNIT: u32 = 0;
+pub const IMAGE_TEXTURE_UNIT: u32 = 1;
+pub const GIZMO_TEXTURE_UNIT: u32 = 2;
+/// The point light shadow cube map, see `shadow::PointShadow`.
+pub const SHADOW_TEXTURE_UNIT: u32 = 3;
+/// Image based lighting maps of the active `environment::Environment`.
+pub const IRRADIANCE_TEXTURE_UNIT: u32 = 4;
+pub const PREFILTER_TEXTURE_UNIT: u32 = 5;
+pub const BRDF_LUT_TEXTURE_UNIT: u32 = 6;
+/// Materials allocate their units from here on.
+pub const FIRST_MATERIAL_TEXTURE_UNIT: u32 = 7;
+
 pub struct Texture(u32);
 
 impl Texture {
@@ -13,6 +50,10 @@ impl Texture {
         Texture(t)
     }
 
+    pub fn id(&self) -> u32 {
+        self.0
+    }
+
     pub fn bind(&self) {
         unsafe {
             gl::BindTexture(gl::TEXTURE_2D, self.0);
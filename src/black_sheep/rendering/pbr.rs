@@ -0,0 +1,144 @@
+use cgmath::{Matrix4, Vector3, Vector4};
+
+use super::{
+    gltf_import::{AlphaMode, GltfPrimitive, GltfScene, PbrMaterial},
+    render_queue::{DrawItem, Pass, RenderState, UniformValue},
+    shader::get_shader_repo,
+    FIRST_MATERIAL_TEXTURE_UNIT,
+};
+
+/// Per frame uniforms of the pbr shader.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PbrView {
+    pub view_projection: Matrix4<f32>,
+    pub camera_position: Vector3<f32>,
+    pub exposure: f32,
+    pub tonemap: bool,
+}
+
+/// The glTF default material, for primitives without one.
+fn default_material() -> PbrMaterial {
+    PbrMaterial {
+        name: None,
+        base_color_factor: Vector4::new(1.0, 1.0, 1.0, 1.0),
+        base_color_texture: None,
+        metallic_factor: 1.0,
+        roughness_factor: 1.0,
+        metallic_roughness_texture: None,
+        normal_texture: None,
+        normal_scale: 1.0,
+        occlusion_texture: None,
+        occlusion_strength: 1.0,
+        emissive_factor: Vector3::new(0.0, 0.0, 0.0),
+        emissive_texture: None,
+        alpha_mode: AlphaMode::Opaque,
+        double_sided: false,
+    }
+}
+
+/// A render queue item drawing `primitive` of `scene` with its metallic-roughness material.
+/// Textures go to the units from `FIRST_MATERIAL_TEXTURE_UNIT` on, lights, shadows and image
+/// based lighting are added by the caller.
+pub fn draw_item(
+    scene: &GltfScene,
+    primitive: &GltfPrimitive,
+    model: Matrix4<f32>,
+    view: &PbrView,
+) -> DrawItem {
+    let fallback;
+    let material = match primitive.material.and_then(|i| scene.materials.get(i)) {
+        Some(m) => m,
+        None => {
+            fallback = default_material();
+            &fallback
+        }
+    };
+    let program = get_shader_repo().program("pbr").unwrap();
+
+    let (pass, alpha_cutoff) = match material.alpha_mode {
+        AlphaMode::Opaque => (Pass::Opaque, -1.0),
+        AlphaMode::Mask(cutoff) => (Pass::Opaque, cutoff),
+        AlphaMode::Blend => (Pass::Transparent, -1.0),
+    };
+    let mut state = match pass {
+        Pass::Transparent => RenderState::transparent(),
+        _ => RenderState::opaque(),
+    };
+    state.cull_face = !material.double_sided;
+
+    let center = match &primitive.mesh.bounds {
+        Some(bounds) => (bounds.min + bounds.max) * 0.5,
+        None => Vector3::new(0.0, 0.0, 0.0),
+    };
+    let position = (model * center.extend(1.0)).truncate();
+
+    let mut item = DrawItem::new(primitive.mesh.clone(), program)
+        .material(primitive.material.map(|i| i as u32 + 1).unwrap_or(0))
+        .pass(pass)
+        .state(state)
+        .position(position)
+        .uniform("M", UniformValue::Mat4(model))
+        .uniform("VP", UniformValue::Mat4(view.view_projection))
+        .uniform("camera_position", UniformValue::Vec3(view.camera_position))
+        .uniform("exposure", UniformValue::Float(view.exposure))
+        .uniform("tonemap", UniformValue::Int(view.tonemap as i32))
+        .uniform(
+            "base_color_factor",
+            UniformValue::Vec4(material.base_color_factor),
+        )
+        .uniform(
+            "metallic_factor",
+            UniformValue::Float(material.metallic_factor),
+        )
+        .uniform(
+            "roughness_factor",
+            UniformValue::Float(material.roughness_factor),
+        )
+        .uniform("normal_scale", UniformValue::Float(material.normal_scale))
+        .uniform(
+            "occlusion_strength",
+            UniformValue::Float(material.occlusion_strength),
+        )
+        .uniform(
+            "emissive_factor",
+            UniformValue::Vec3(material.emissive_factor),
+        )
+        .uniform("alpha_cutoff", UniformValue::Float(alpha_cutoff));
+
+    let maps = [
+        (
+            "base_color_map",
+            "has_base_color_map",
+            material.base_color_texture,
+        ),
+        (
+            "metallic_roughness_map",
+            "has_metallic_roughness_map",
+            material.metallic_roughness_texture,
+        ),
+        ("normal_map", "has_normal_map", material.normal_texture),
+        (
+            "occlusion_map",
+            "has_occlusion_map",
+            material.occlusion_texture,
+        ),
+        (
+            "emissive_map",
+            "has_emissive_map",
+            material.emissive_texture,
+        ),
+    ];
+    for (i, (sampler, flag, texture)) in maps.iter().enumerate() {
+        let unit = FIRST_MATERIAL_TEXTURE_UNIT + i as u32;
+        item = item.uniform(*sampler, UniformValue::Int(unit as i32));
+        match texture.and_then(|t| scene.textures.get(t)) {
+            Some(texture) => {
+                item = item
+                    .texture(unit, texture.id())
+                    .uniform(*flag, UniformValue::Int(1));
+            }
+            None => item = item.uniform(*flag, UniformValue::Int(0)),
+        }
+    }
+    item
+}
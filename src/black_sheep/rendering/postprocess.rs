@@ -0,0 +1,337 @@
+use std::sync::Mutex;
+
+use cgmath::Vector2;
+
+use super::{
+    rendertarget::{bound_framebuffer, RenderTarget, RenderTargetBuilder},
+    shader::get_shader_repo,
+    FIRST_MATERIAL_TEXTURE_UNIT,
+};
+use crate::black_sheep::error::{EngineError, Result};
+
+/// Slices (and texels per slice edge) of the color grading lookup table.
+pub const LUT_SIZE: u32 = 16;
+
+// the passes run after the render queue, so they can borrow the material units
+const SOURCE_UNIT: u32 = FIRST_MATERIAL_TEXTURE_UNIT;
+const BLOOM_UNIT: u32 = FIRST_MATERIAL_TEXTURE_UNIT + 1;
+const LUT_UNIT: u32 = FIRST_MATERIAL_TEXTURE_UNIT + 2;
+
+lazy_static! {
+    static ref POST_SETTINGS: Mutex<PostSettings> = Mutex::new(PostSettings::default());
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PostSettings {
+    /// render the scene into a float target and run the passes below,
+    /// otherwise the scene goes straight to the output framebuffer
+    pub enabled: bool,
+    pub exposure: f32,
+    /// ACES filmic curve
+    pub tonemap: bool,
+    pub gamma_correct: bool,
+    pub gamma: f32,
+    pub bloom: bool,
+    /// brightness above which pixels bloom
+    pub bloom_threshold: f32,
+    pub bloom_intensity: f32,
+    /// horizontal + vertical blur iterations at half resolution
+    pub bloom_passes: i32,
+    pub fxaa: bool,
+    pub vignette: bool,
+    pub vignette_strength: f32,
+    /// distance from the center where darkening starts, 1 is the corner
+    pub vignette_radius: f32,
+    pub lut: bool,
+    pub lut_strength: f32,
+}
+
+impl Default for PostSettings {
+    fn default() -> Self {
+        PostSettings {
+            enabled: true,
+            exposure: 1.0,
+            tonemap: true,
+            gamma_correct: true,
+            gamma: 2.2,
+            bloom: true,
+            bloom_threshold: 1.0,
+            bloom_intensity: 0.6,
+            bloom_passes: 4,
+            fxaa: true,
+            vignette: false,
+            vignette_strength: 0.5,
+            vignette_radius: 0.5,
+            lut: false,
+            lut_strength: 1.0,
+        }
+    }
+}
+
+pub fn settings() -> PostSettings {
+    POST_SETTINGS.lock().map(|s| *s).unwrap_or_default()
+}
+
+pub fn set_settings(settings: PostSettings) {
+    if let Ok(mut s) = POST_SETTINGS.lock() {
+        *s = settings;
+    }
+}
+
+/// HDR scene target and the fullscreen passes that turn it into the displayed image:
+/// bloom, exposure and tone mapping, gamma, color grading, vignette and FXAA.
+pub struct PostProcess {
+    /// multisampled if the engine is configured for MSAA, resolved before the passes
+    scene: RenderTarget,
+    /// half resolution ping pong targets of the bloom blur
+    bloom: [RenderTarget; 2],
+    /// tone mapped image, input of the FXAA pass
+    ldr: RenderTarget,
+    lut: u32,
+    empty_vao: u32,
+    /// framebuffer that was bound when `begin` was called
+    output: u32,
+    settings: PostSettings,
+}
+
+impl PostProcess {
+    /// All targets follow the scene viewport. Leaves the previously bound framebuffer bound.
+    pub fn new(samples: i32) -> Result<Self> {
+        let previous = bound_framebuffer();
+        let scene = RenderTargetBuilder::viewport_sized(1.0)
+            .color(gl::RGBA16F)
+            .depth_renderbuffer()
+            .samples(samples)
+            .build()?;
+        let bloom = [
+            RenderTargetBuilder::viewport_sized(0.5)
+                .color(gl::RGBA16F)
+                .build()?,
+            RenderTargetBuilder::viewport_sized(0.5)
+                .color(gl::RGBA16F)
+                .build()?,
+        ];
+        let ldr = RenderTargetBuilder::viewport_sized(1.0)
+            .color(gl::RGBA8)
+            .build()?;
+        let mut empty_vao = 0;
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, previous);
+            gl::GenVertexArrays(1, &mut empty_vao);
+        }
+        Ok(PostProcess {
+            scene,
+            bloom,
+            ldr,
+            lut: gen_lut(&film_lut()),
+            empty_vao,
+            output: previous,
+            settings: settings(),
+        })
+    }
+
+    /// Replaces the color grading table with an image of `LUT_SIZE` slices side by side,
+    /// `LUT_SIZE * LUT_SIZE` wide and `LUT_SIZE` high.
+    pub fn load_lut(&mut self, path: &str) -> Result<()> {
+        let image = image::open(path)
+            .map_err(|e| EngineError::Image(String::from(path), e))?
+            .into_rgba8();
+        let expected = (LUT_SIZE * LUT_SIZE, LUT_SIZE);
+        if image.dimensions() != expected {
+            return Err(EngineError::ImageSize(String::from(path), image.dimensions(), expected));
+        }
+        unsafe {
+            gl::DeleteTextures(1, &self.lut);
+        }
+        self.lut = gen_lut(image.as_raw());
+        Ok(())
+    }
+
+    /// Picks up the current settings and, if enabled, binds the HDR scene target for the scene
+    /// to be drawn into. Viewport and clearing are left to the caller.
+    pub fn begin(&mut self) {
+        self.settings = settings();
+        if !self.settings.enabled {
+            return;
+        }
+        self.output = bound_framebuffer();
+        self.scene.bind_framebuffer();
+    }
+
+    /// Runs the passes into the framebuffer that was bound at `begin` and leaves it bound,
+    /// with the viewport covering the scene.
+    pub fn end(&mut self) {
+        if !self.settings.enabled {
+            return;
+        }
+        self.scene.resolve();
+        let s = self.settings;
+        let (width, height) = (self.scene.width, self.scene.height);
+        // fetched every frame, a hot reload only updates the uniform locations in the repo
+        let repo = get_shader_repo();
+        unsafe {
+            gl::Disable(gl::DEPTH_TEST);
+            gl::Disable(gl::BLEND);
+            gl::BindVertexArray(self.empty_vao);
+        }
+
+        if s.bloom {
+            // binding first, a resized viewport rebuilds the targets
+            self.bloom[1].bind_framebuffer();
+            self.bloom[0].bind_framebuffer();
+            let (bw, bh) = (self.bloom[0].width, self.bloom[0].height);
+            bind_texture(SOURCE_UNIT, self.scene.texture(0));
+            set_viewport(bw, bh);
+            let bright = repo.post_bright;
+            bright.use_program();
+            bright.set_source(SOURCE_UNIT as i32);
+            bright.set_threshold(s.bloom_threshold);
+            bright.set_knee(s.bloom_threshold * 0.5);
+            draw_fullscreen();
+
+            let blur = repo.post_blur;
+            blur.use_program();
+            blur.set_source(SOURCE_UNIT as i32);
+            let texel = Vector2::new(1.0 / bw as f32, 1.0 / bh as f32);
+            for _ in 0..s.bloom_passes {
+                bind_texture(SOURCE_UNIT, self.bloom[0].texture(0));
+                self.bloom[1].bind_framebuffer();
+                blur.set_direction(Vector2::new(texel.x, 0.0));
+                draw_fullscreen();
+
+                bind_texture(SOURCE_UNIT, self.bloom[1].texture(0));
+                self.bloom[0].bind_framebuffer();
+                blur.set_direction(Vector2::new(0.0, texel.y));
+                draw_fullscreen();
+            }
+        }
+
+        if s.fxaa {
+            self.ldr.bind_framebuffer();
+        } else {
+            unsafe {
+                gl::BindFramebuffer(gl::FRAMEBUFFER, self.output);
+            }
+        }
+        set_viewport(width, height);
+        bind_texture(SOURCE_UNIT, self.scene.texture(0));
+        bind_texture(BLOOM_UNIT, self.bloom[0].texture(0));
+        bind_texture(LUT_UNIT, self.lut);
+        let c = repo.post_composite;
+        c.use_program();
+        c.set_scene(SOURCE_UNIT as i32);
+        c.set_bloom(BLOOM_UNIT as i32);
+        c.set_lut(LUT_UNIT as i32);
+        c.set_exposure(s.exposure);
+        c.set_tonemap(s.tonemap as i32);
+        c.set_gamma_correct(s.gamma_correct as i32);
+        c.set_gamma(s.gamma);
+        c.set_bloom_enabled(s.bloom as i32);
+        c.set_bloom_intensity(s.bloom_intensity);
+        c.set_lut_enabled(s.lut as i32);
+        c.set_lut_strength(s.lut_strength);
+        c.set_vignette_enabled(s.vignette as i32);
+        c.set_vignette_strength(s.vignette_strength);
+        c.set_vignette_radius(s.vignette_radius);
+        draw_fullscreen();
+
+        if s.fxaa {
+            unsafe {
+                gl::BindFramebuffer(gl::FRAMEBUFFER, self.output);
+            }
+            bind_texture(SOURCE_UNIT, self.ldr.texture(0));
+            let fxaa = repo.post_fxaa;
+            fxaa.use_program();
+            fxaa.set_source(SOURCE_UNIT as i32);
+            fxaa.set_texel(Vector2::new(1.0 / width as f32, 1.0 / height as f32));
+            draw_fullscreen();
+        }
+
+        unsafe {
+            gl::BindVertexArray(0);
+            gl::Enable(gl::BLEND);
+            gl::Enable(gl::DEPTH_TEST);
+        }
+    }
+}
+
+impl Drop for PostProcess {
+    fn drop(&mut self) {
+        #[cfg(not(feature = "debug_off"))]
+        println!("PostProcess cleanup lut: {}", self.lut);
+        unsafe {
+            gl::DeleteTextures(1, &self.lut);
+            gl::DeleteVertexArrays(1, &self.empty_vao);
+        }
+    }
+}
+
+fn set_viewport(width: i32, height: i32) {
+    unsafe {
+        gl::Viewport(0, 0, width, height);
+    }
+}
+
+fn bind_texture(unit: u32, texture: u32) {
+    unsafe {
+        gl::ActiveTexture(gl::TEXTURE0 + unit);
+        gl::BindTexture(gl::TEXTURE_2D, texture);
+    }
+}
+
+fn draw_fullscreen() {
+    unsafe {
+        gl::DrawArrays(gl::TRIANGLES, 0, 3);
+    }
+}
+
+fn gen_lut(rgba: &[u8]) -> u32 {
+    let mut lut = 0;
+    unsafe {
+        gl::GenTextures(1, &mut lut);
+        gl::BindTexture(gl::TEXTURE_2D, lut);
+        gl::TexImage2D(
+            gl::TEXTURE_2D,
+            0,
+            gl::RGBA8 as i32,
+            (LUT_SIZE * LUT_SIZE) as i32,
+            LUT_SIZE as i32,
+            0,
+            gl::RGBA,
+            gl::UNSIGNED_BYTE,
+            rgba.as_ptr() as *const std::ffi::c_void,
+        );
+        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as i32);
+        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
+        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as i32);
+        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as i32);
+    }
+    lut
+}
+
+/// Built in grade: a bit more contrast, warm highlights and cool shadows.
+fn film_lut() -> Vec<u8> {
+    let n = LUT_SIZE as usize;
+    let mut data = Vec::with_capacity(n * n * n * 4);
+    let max = (n - 1) as f32;
+    for g in 0..n {
+        for b in 0..n {
+            for r in 0..n {
+                let [r, g, b] = [r as f32 / max, g as f32 / max, b as f32 / max];
+                let luma = 0.299 * r + 0.587 * g + 0.114 * b;
+                let contrast = |c: f32| c * c * (3.0 - 2.0 * c) * 0.6 + c * 0.4;
+                let shift = (luma - 0.5) * 0.08;
+                let graded = [
+                    contrast(r) + shift,
+                    contrast(g) + shift * 0.3,
+                    contrast(b) - shift,
+                ];
+                for c in graded.iter() {
+                    data.push((c.max(0.0).min(1.0) * 255.0).round() as u8);
+                }
+                data.push(255);
+            }
+        }
+    }
+    data
+}
@@ -0,0 +1,362 @@
+use std::{borrow::Cow, collections::HashMap, ffi::CString, sync::Mutex};
+
+use cgmath::{Array, InnerSpace, Matrix, Matrix4, Vector2, Vector3, Vector4};
+use serde::{Deserialize, Serialize};
+
+use super::geometry::mesh::MeshToken;
+
+lazy_static! {
+    static ref RENDER_QUEUE: Mutex<RenderQueue> = Mutex::new(RenderQueue::default());
+}
+
+/// Passes are executed in this order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Pass {
+    Opaque = 0,
+    /// after the opaque geometry, only fills what is still empty
+    Sky = 1,
+    /// sorted back to front
+    Transparent = 2,
+    /// drawn last without depth test
+    Overlay = 3,
+}
+
+impl Default for Pass {
+    fn default() -> Self {
+        Pass::Opaque
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UniformValue {
+    Int(i32),
+    Float(f32),
+    Vec2(Vector2<f32>),
+    Vec3(Vector3<f32>),
+    Vec4(Vector4<f32>),
+    Mat4(Matrix4<f32>),
+}
+
+impl UniformValue {
+    pub fn apply(&self, location: i32) {
+        unsafe {
+            match self {
+                UniformValue::Int(v) => gl::Uniform1i(location, *v),
+                UniformValue::Float(v) => gl::Uniform1f(location, *v),
+                UniformValue::Vec2(v) => gl::Uniform2fv(location, 1, v.as_ptr()),
+                UniformValue::Vec3(v) => gl::Uniform3fv(location, 1, v.as_ptr()),
+                UniformValue::Vec4(v) => gl::Uniform4fv(location, 1, v.as_ptr()),
+                UniformValue::Mat4(v) => gl::UniformMatrix4fv(location, 1, gl::FALSE, v.as_ptr()),
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Primitive {
+    Triangles,
+    Lines,
+    Points,
+    /// the instance attributes have to be attached to the mesh's vertex array already
+    TrianglesInstanced(i32),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RenderState {
+    pub depth_test: bool,
+    pub depth_write: bool,
+    pub blend: bool,
+    pub cull_face: bool,
+}
+
+impl RenderState {
+    pub fn opaque() -> Self {
+        RenderState {
+            depth_test: true,
+            depth_write: true,
+            blend: false,
+            cull_face: false,
+        }
+    }
+
+    /// Alpha blended, tests depth but doesn't write it.
+    pub fn transparent() -> Self {
+        RenderState {
+            depth_test: true,
+            depth_write: false,
+            blend: true,
+            cull_face: false,
+        }
+    }
+
+    /// Tests depth but doesn't write it, like transparent items but without blending.
+    pub fn sky() -> Self {
+        RenderState {
+            depth_test: true,
+            depth_write: false,
+            blend: false,
+            cull_face: false,
+        }
+    }
+
+    pub fn overlay() -> Self {
+        RenderState {
+            depth_test: false,
+            depth_write: false,
+            blend: true,
+            cull_face: false,
+        }
+    }
+
+    /// What the rest of the engine expects after the queue ran.
+    fn engine_default() -> Self {
+        RenderState {
+            depth_test: true,
+            depth_write: true,
+            blend: true,
+            cull_face: false,
+        }
+    }
+
+    fn apply(&self, current: Option<&RenderState>) {
+        let set = |cap: u32, on: bool, was: Option<bool>| unsafe {
+            if was != Some(on) {
+                if on {
+                    gl::Enable(cap);
+                } else {
+                    gl::Disable(cap);
+                }
+            }
+        };
+        set(gl::DEPTH_TEST, self.depth_test, current.map(|c| c.depth_test));
+        set(gl::BLEND, self.blend, current.map(|c| c.blend));
+        set(gl::CULL_FACE, self.cull_face, current.map(|c| c.cull_face));
+        if current.map(|c| c.depth_write) != Some(self.depth_write) {
+            unsafe { gl::DepthMask(if self.depth_write { gl::TRUE } else { gl::FALSE }) };
+        }
+    }
+}
+
+impl Default for RenderState {
+    fn default() -> Self {
+        Self::opaque()
+    }
+}
+
+/// Everything needed for one draw call.
+#[derive(Debug, Clone)]
+pub struct DrawItem {
+    pub mesh: MeshToken,
+    pub primitive: Primitive,
+    /// program id, see `ShaderRepo::program`
+    pub program: u32,
+    /// items with the same material share uniforms and textures, only used for sorting
+    pub material: u32,
+    pub uniforms: Vec<(Cow<'static, str>, UniformValue)>,
+    /// (texture unit, target, texture id), the target is `TEXTURE_2D` or `TEXTURE_CUBE_MAP`
+    pub textures: Vec<(u32, u32, u32)>,
+    pub state: RenderState,
+    pub pass: Pass,
+    /// world space position used for depth sorting
+    pub position: Vector3<f32>,
+}
+
+impl DrawItem {
+    pub fn new(mesh: MeshToken, program: u32) -> Self {
+        DrawItem {
+            mesh,
+            primitive: Primitive::Triangles,
+            program,
+            material: 0,
+            uniforms: Vec::new(),
+            textures: Vec::new(),
+            state: RenderState::opaque(),
+            pass: Pass::Opaque,
+            position: Vector3::from_value(0.0),
+        }
+    }
+
+    pub fn primitive(mut self, primitive: Primitive) -> Self {
+        self.primitive = primitive;
+        self
+    }
+
+    pub fn material(mut self, material: u32) -> Self {
+        self.material = material;
+        self
+    }
+
+    pub fn uniform<N: Into<Cow<'static, str>>>(mut self, name: N, value: UniformValue) -> Self {
+        self.uniforms.push((name.into(), value));
+        self
+    }
+
+    pub fn texture(mut self, unit: u32, texture: u32) -> Self {
+        self.textures.push((unit, gl::TEXTURE_2D, texture));
+        self
+    }
+
+    pub fn texture_cube(mut self, unit: u32, texture: u32) -> Self {
+        self.textures.push((unit, gl::TEXTURE_CUBE_MAP, texture));
+        self
+    }
+
+    /// Sets the pass together with its usual render state.
+    pub fn pass(mut self, pass: Pass) -> Self {
+        self.pass = pass;
+        self.state = match pass {
+            Pass::Opaque => RenderState::opaque(),
+            Pass::Sky => RenderState::sky(),
+            Pass::Transparent => RenderState::transparent(),
+            Pass::Overlay => RenderState::overlay(),
+        };
+        self
+    }
+
+    pub fn state(mut self, state: RenderState) -> Self {
+        self.state = state;
+        self
+    }
+
+    pub fn position(mut self, position: Vector3<f32>) -> Self {
+        self.position = position;
+        self
+    }
+
+    /// Pass in the top bits. Opaque items are grouped by program and material and then drawn
+    /// front to back, transparent ones are ordered back to front first.
+    fn sort_key(&self, eye: Vector3<f32>) -> u64 {
+        let pass = (self.pass as u64) << 62;
+        let program = (self.program as u64) & 0xffff;
+        let material = (self.material as u64) & 0x3fff;
+        // bit patterns of positive floats sort like the floats
+        let depth = ((self.position - eye).magnitude().to_bits() >> 2) as u64;
+        match self.pass {
+            Pass::Transparent => pass | (!depth & 0x3fff_ffff) << 32 | program << 16 | material,
+            _ => pass | program << 46 | material << 32 | depth,
+        }
+    }
+
+    fn draw(&self) {
+        match self.primitive {
+            Primitive::Triangles => self.mesh.draw_triangle_elements(),
+            Primitive::Lines => self.mesh.draw_line_elements(),
+            Primitive::Points => self.mesh.draw_point_elements(),
+            Primitive::TrianglesInstanced(n) => self.mesh.draw_triangle_elements_instanced(n),
+        }
+    }
+}
+
+/// State changes saved by sorting, shown in the debug panel.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct QueueStats {
+    pub items: u32,
+    pub program_changes: u32,
+    pub texture_binds: u32,
+    pub state_changes: u32,
+}
+
+#[derive(Default)]
+struct RenderQueue {
+    items: Vec<DrawItem>,
+    locations: HashMap<u32, HashMap<String, i32>>,
+}
+
+impl RenderQueue {
+    fn location(&mut self, program: u32, name: &str) -> i32 {
+        let locations = self.locations.entry(program).or_default();
+        if let Some(location) = locations.get(name) {
+            return *location;
+        }
+        let location = uniform_location(program, name);
+        #[cfg(not(feature = "debug_off"))]
+        if location < 0 {
+            println!("program {} has no uniform {}", program, name);
+        }
+        locations.insert(String::from(name), location);
+        location
+    }
+
+    fn execute(&mut self, eye: Vector3<f32>) -> QueueStats {
+        let mut items = std::mem::take(&mut self.items);
+        items.sort_by_cached_key(|item| item.sort_key(eye));
+
+        let mut stats = QueueStats {
+            items: items.len() as u32,
+            ..Default::default()
+        };
+        let mut program = None;
+        let mut state = None;
+        let mut textures: HashMap<u32, (u32, u32)> = HashMap::new();
+        let mut vertex_array = None;
+
+        for item in items.iter() {
+            if state != Some(item.state) {
+                item.state.apply(state.as_ref());
+                state = Some(item.state);
+                stats.state_changes += 1;
+            }
+            if program != Some(item.program) {
+                unsafe { gl::UseProgram(item.program) };
+                program = Some(item.program);
+                stats.program_changes += 1;
+            }
+            for &(unit, target, texture) in item.textures.iter() {
+                if textures.insert(unit, (target, texture)) != Some((target, texture)) {
+                    unsafe {
+                        gl::ActiveTexture(gl::TEXTURE0 + unit);
+                        gl::BindTexture(target, texture);
+                    }
+                    stats.texture_binds += 1;
+                }
+            }
+            for (name, value) in item.uniforms.iter() {
+                let location = self.location(item.program, name);
+                if location >= 0 {
+                    value.apply(location);
+                }
+            }
+            if vertex_array != Some(item.mesh.uid) {
+                item.mesh.bind_vertex_array();
+                vertex_array = Some(item.mesh.uid);
+            }
+            item.draw();
+        }
+
+        RenderState::engine_default().apply(state.as_ref());
+
+        // keep the allocation for the next frame
+        items.clear();
+        self.items = items;
+        stats
+    }
+}
+
+/// Queues an item for the next `execute`.
+pub fn submit(item: DrawItem) {
+    if let Ok(mut rq) = RENDER_QUEUE.lock() {
+        rq.items.push(item);
+    }
+}
+
+/// Sorts and draws everything submitted since the last call, `eye` is the camera position
+/// used for depth sorting. Called by the engine after the draw function.
+pub fn execute(eye: Vector3<f32>) -> QueueStats {
+    match RENDER_QUEUE.lock() {
+        Ok(mut rq) => rq.execute(eye),
+        Err(_) => QueueStats::default(),
+    }
+}
+
+/// Forgets cached uniform locations, they can move when a program is relinked.
+pub fn invalidate_locations(program: u32) {
+    if let Ok(mut rq) = RENDER_QUEUE.lock() {
+        rq.locations.remove(&program);
+    }
+}
+
+/// `glGetUniformLocation`, -1 if the program has no active uniform of that name.
+pub fn uniform_location(program: u32, name: &str) -> i32 {
+    let c_name = CString::new(name).unwrap();
+    unsafe { gl::GetUniformLocation(program, c_name.as_ptr()) }
+}
@@ -1,27 +1,404 @@
+use std::sync::Mutex;
+
 use gl;
+use image::RgbaImage;
+
+use crate::black_sheep::error::{self, EngineError, Result};
+
+lazy_static! {
+    /// size of the scene viewport, followed by targets built with `viewport_sized`
+    static ref VIEWPORT_SIZE: Mutex<[i32; 2]> = Mutex::new([1, 1]);
+}
+
+/// Called by the engine at startup and whenever the window is resized.
+pub fn set_viewport_size(width: i32, height: i32) {
+    if let Ok(mut size) = VIEWPORT_SIZE.lock() {
+        *size = [width.max(1), height.max(1)];
+    }
+}
+
+pub fn viewport_size() -> [i32; 2] {
+    VIEWPORT_SIZE.lock().map(|s| *s).unwrap_or([1, 1])
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DepthAttachment {
+    None,
+    /// depth testing only
+    Renderbuffer,
+    /// can be sampled after rendering, see `RenderTarget::depth_texture`
+    Texture,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TargetSize {
+    Fixed(i32, i32),
+    /// the scene viewport scaled by the factor, rebuilt when the window is resized
+    Viewport(f32),
+}
+
+impl TargetSize {
+    fn get(&self) -> (i32, i32) {
+        match *self {
+            TargetSize::Fixed(w, h) => (w, h),
+            TargetSize::Viewport(scale) => {
+                let [w, h] = viewport_size();
+                (
+                    ((w as f32 * scale) as i32).max(1),
+                    ((h as f32 * scale) as i32).max(1),
+                )
+            }
+        }
+    }
+}
+
+/// Describes a `RenderTarget`: size, color attachment formats, depth and sample count.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RenderTargetBuilder {
+    size: TargetSize,
+    /// internal formats in attachment order, e.g. `gl::RGBA16F` or `gl::R32UI`
+    colors: Vec<u32>,
+    depth: DepthAttachment,
+    samples: i32,
+}
+
+impl RenderTargetBuilder {
+    pub fn new(width: i32, height: i32) -> Self {
+        RenderTargetBuilder {
+            size: TargetSize::Fixed(width, height),
+            colors: Vec::new(),
+            depth: DepthAttachment::None,
+            samples: 0,
+        }
+    }
+
+    /// Follows the scene viewport, `scale` 0.5 gives a half resolution target.
+    pub fn viewport_sized(scale: f32) -> Self {
+        RenderTargetBuilder {
+            size: TargetSize::Viewport(scale),
+            ..Self::new(1, 1)
+        }
+    }
+
+    /// Adds the next color attachment.
+    pub fn color(mut self, format: u32) -> Self {
+        self.colors.push(format);
+        self
+    }
+
+    pub fn depth_renderbuffer(mut self) -> Self {
+        self.depth = DepthAttachment::Renderbuffer;
+        self
+    }
+
+    pub fn depth_texture(mut self) -> Self {
+        self.depth = DepthAttachment::Texture;
+        self
+    }
+
+    /// Multisampled target, clamped to what the driver supports. The attachments are
+    /// renderbuffers, `RenderTarget::resolve` blits them into sampleable textures.
+    pub fn samples(mut self, samples: i32) -> Self {
+        self.samples = samples;
+        self
+    }
+
+    /// Leaves the new framebuffer bound.
+    pub fn build(self) -> Result<RenderTarget> {
+        let (width, height) = self.size.get();
+        let mut rt = RenderTarget {
+            frame_buffer: 0,
+            color_attachments: Vec::new(),
+            depth_attachment: 0,
+            width,
+            height,
+            desc: self,
+            resolve_target: None,
+        };
+        rt.create()?;
+        Ok(rt)
+    }
+}
 
+/// Framebuffer with any number of color attachments and an optional depth attachment.
 pub struct RenderTarget {
     pub frame_buffer: u32,
-    pub render_texture: u32,
-    pub depth_render_buffer: u32,
+    /// textures, or renderbuffers if multisampled
+    color_attachments: Vec<u32>,
+    depth_attachment: u32,
+    pub width: i32,
+    pub height: i32,
+    desc: RenderTargetBuilder,
+    /// single sampled copy of a multisampled target
+    resolve_target: Option<Box<RenderTarget>>,
 }
 
 impl RenderTarget {
-    pub fn new(width: i32, height: i32) -> Self {
-        let rt = Self {
-            frame_buffer: gen_framebuffer(),
-            render_texture: gen_empty_texture(width, height),
-            depth_render_buffer: gen_depthbuffer(width, height),
+    /// One RGBA8 color texture and a depth renderbuffer.
+    pub fn new(width: i32, height: i32) -> Result<Self> {
+        RenderTargetBuilder::new(width, height)
+            .color(gl::RGBA8)
+            .depth_renderbuffer()
+            .build()
+    }
+
+    /// Sampleable texture of color attachment `index`, the resolved one for multisampled targets.
+    pub fn texture(&self, index: usize) -> u32 {
+        match &self.resolve_target {
+            Some(resolved) => resolved.texture(index),
+            None => self.color_attachments[index],
+        }
+    }
+
+    pub fn depth_texture(&self) -> Option<u32> {
+        match (&self.resolve_target, self.desc.depth) {
+            (Some(resolved), _) => resolved.depth_texture(),
+            (None, DepthAttachment::Texture) => Some(self.depth_attachment),
+            _ => None,
+        }
+    }
+
+    /// Binds the framebuffer, rebuilding it first if it follows the viewport and that changed.
+    pub fn bind_framebuffer(&mut self) {
+        self.follow_viewport();
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, self.frame_buffer);
+        }
+    }
+
+    /// Binds the first color texture to the active unit.
+    pub fn bind_texture(&self) {
+        unsafe {
+            gl::BindTexture(gl::TEXTURE_2D, self.texture(0));
+        }
+    }
+
+    /// Blits all attachments of a multisampled target into its textures,
+    /// does nothing for single sampled ones. Restores the framebuffer binding.
+    pub fn resolve(&self) {
+        let resolved = match &self.resolve_target {
+            Some(resolved) => resolved,
+            None => return,
+        };
+        let previous = bound_framebuffer();
+        let (w, h) = (self.width, self.height);
+        unsafe {
+            gl::BindFramebuffer(gl::READ_FRAMEBUFFER, self.frame_buffer);
+            gl::BindFramebuffer(gl::DRAW_FRAMEBUFFER, resolved.frame_buffer);
+            for i in 0..self.color_attachments.len() as u32 {
+                gl::ReadBuffer(gl::COLOR_ATTACHMENT0 + i);
+                gl::DrawBuffer(gl::COLOR_ATTACHMENT0 + i);
+                gl::BlitFramebuffer(0, 0, w, h, 0, 0, w, h, gl::COLOR_BUFFER_BIT, gl::NEAREST);
+            }
+            if self.desc.depth == DepthAttachment::Texture {
+                gl::BlitFramebuffer(0, 0, w, h, 0, 0, w, h, gl::DEPTH_BUFFER_BIT, gl::NEAREST);
+            }
+            set_draw_buffers(self.color_attachments.len());
+            gl::BindFramebuffer(gl::FRAMEBUFFER, previous);
+        }
+    }
+
+    /// Reads color attachment 0, resolving first if multisampled.
+    pub fn read_pixels(&self) -> RgbaImage {
+        self.resolve();
+        let source = match &self.resolve_target {
+            Some(resolved) => resolved.frame_buffer,
+            None => self.frame_buffer,
+        };
+        unsafe {
+            gl::BindFramebuffer(gl::READ_FRAMEBUFFER, source);
+            gl::ReadBuffer(gl::COLOR_ATTACHMENT0);
+        }
+        read_pixels(self.width, self.height)
+    }
+
+    pub fn save_png(&self, path: &str) -> image::ImageResult<()> {
+        self.read_pixels().save_with_format(path, image::ImageFormat::Png)
+    }
+
+    /// Deletes all attachments and builds the framebuffer again at the new size.
+    /// A viewport sized target becomes a fixed size one. Restores the framebuffer binding.
+    pub fn resize(&mut self, width: i32, height: i32) {
+        self.desc.size = TargetSize::Fixed(width, height);
+        self.rebuild();
+    }
+
+    fn follow_viewport(&mut self) {
+        if let TargetSize::Viewport(_) = self.desc.size {
+            if self.desc.size.get() != (self.width, self.height) {
+                self.rebuild();
+            }
+        }
+    }
+
+    fn rebuild(&mut self) {
+        let previous = bound_framebuffer();
+        self.cleanup();
+        let (width, height) = self.desc.size.get();
+        self.width = width;
+        self.height = height;
+        if let Err(e) = self.create() {
+            error::report(e);
+        }
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, previous);
+        }
+    }
+
+    fn create(&mut self) -> Result<()> {
+        let samples = if self.desc.samples > 1 {
+            let mut max = 0;
+            unsafe {
+                gl::GetIntegerv(gl::MAX_SAMPLES, &mut max);
+            }
+            self.desc.samples.min(max)
+        } else {
+            0
+        };
+        let (width, height) = (self.width, self.height);
+
+        self.resolve_target = if samples > 1 {
+            let mut desc = self.desc.clone();
+            desc.size = TargetSize::Fixed(width, height);
+            desc.samples = 0;
+            if desc.depth == DepthAttachment::Renderbuffer {
+                desc.depth = DepthAttachment::None;
+            }
+            Some(Box::new(desc.build()?))
+        } else {
+            None
+        };
+
+        self.frame_buffer = gen_framebuffer();
+        self.color_attachments = self
+            .desc
+            .colors
+            .iter()
+            .enumerate()
+            .map(|(i, format)| {
+                let attachment = gl::COLOR_ATTACHMENT0 + i as u32;
+                if samples > 1 {
+                    let rb = gen_renderbuffer(width, height, *format, samples);
+                    unsafe {
+                        gl::FramebufferRenderbuffer(
+                            gl::FRAMEBUFFER,
+                            attachment,
+                            gl::RENDERBUFFER,
+                            rb,
+                        );
+                    }
+                    rb
+                } else {
+                    let texture = gen_color_texture(width, height, *format);
+                    unsafe {
+                        gl::FramebufferTexture(gl::FRAMEBUFFER, attachment, texture, 0);
+                    }
+                    texture
+                }
+            })
+            .collect();
+
+        self.depth_attachment = match (self.desc.depth, samples > 1) {
+            (DepthAttachment::None, _) => 0,
+            (DepthAttachment::Renderbuffer, _) | (DepthAttachment::Texture, true) => {
+                let rb = gen_renderbuffer(width, height, gl::DEPTH_COMPONENT24, samples);
+                unsafe {
+                    gl::FramebufferRenderbuffer(
+                        gl::FRAMEBUFFER,
+                        gl::DEPTH_ATTACHMENT,
+                        gl::RENDERBUFFER,
+                        rb,
+                    );
+                }
+                rb
+            }
+            (DepthAttachment::Texture, false) => {
+                let texture = gen_depth_texture(width, height);
+                unsafe {
+                    gl::FramebufferTexture(gl::FRAMEBUFFER, gl::DEPTH_ATTACHMENT, texture, 0);
+                }
+                texture
+            }
         };
+
         unsafe {
-            gl::FramebufferTexture(gl::FRAMEBUFFER, gl::COLOR_ATTACHMENT0, rt.render_texture, 0);
+            set_draw_buffers(self.color_attachments.len());
+            let status = gl::CheckFramebufferStatus(gl::FRAMEBUFFER);
+            if status != gl::FRAMEBUFFER_COMPLETE {
+                return Err(EngineError::FramebufferIncomplete(status));
+            }
+        }
+        Ok(())
+    }
 
-            gl::DrawBuffer(gl::COLOR_ATTACHMENT0);
-            if gl::CheckFramebufferStatus(gl::FRAMEBUFFER) != gl::FRAMEBUFFER_COMPLETE {
-                panic!("framebuffer not complete");
+    fn depth_is_renderbuffer(&self) -> bool {
+        self.desc.depth == DepthAttachment::Renderbuffer || self.resolve_target.is_some()
+    }
+
+    pub fn cleanup(&mut self) {
+        let multisampled = self.resolve_target.is_some();
+        unsafe {
+            if multisampled {
+                gl::DeleteRenderbuffers(
+                    self.color_attachments.len() as i32,
+                    self.color_attachments.as_ptr(),
+                );
+            } else {
+                gl::DeleteTextures(
+                    self.color_attachments.len() as i32,
+                    self.color_attachments.as_ptr(),
+                );
             }
+            if self.depth_attachment != 0 {
+                if self.depth_is_renderbuffer() {
+                    gl::DeleteRenderbuffers(1, &self.depth_attachment);
+                } else {
+                    gl::DeleteTextures(1, &self.depth_attachment);
+                }
+            }
+            gl::DeleteFramebuffers(1, &self.frame_buffer);
+        }
+        self.color_attachments.clear();
+        self.depth_attachment = 0;
+        self.frame_buffer = 0;
+        // dropping the resolve target deletes its objects
+        self.resolve_target = None;
+    }
+}
+
+/// Depth only target rendering into the six faces of a cube map at once, for point light shadows.
+pub struct DepthCubeTarget {
+    pub frame_buffer: u32,
+    pub depth_cube: u32,
+    pub size: i32,
+}
+
+impl DepthCubeTarget {
+    pub fn new(size: i32) -> Result<Self> {
+        let previous = bound_framebuffer();
+        let mut depth_cube = 0;
+        unsafe {
+            gl::GenTextures(1, &mut depth_cube);
+        }
+        let mut target = Self {
+            frame_buffer: gen_framebuffer(),
+            depth_cube,
+            size,
+        };
+        target.resize(size);
+        let status = unsafe {
+            gl::FramebufferTexture(gl::FRAMEBUFFER, gl::DEPTH_ATTACHMENT, depth_cube, 0);
+            gl::DrawBuffer(gl::NONE);
+            gl::ReadBuffer(gl::NONE);
+            gl::CheckFramebufferStatus(gl::FRAMEBUFFER)
+        };
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, previous);
         }
-        rt
+        if status != gl::FRAMEBUFFER_COMPLETE {
+            return Err(EngineError::FramebufferIncomplete(status));
+        }
+        Ok(target)
     }
 
     pub fn bind_framebuffer(&self) {
@@ -32,60 +409,131 @@ impl RenderTarget {
 
     pub fn bind_texture(&self) {
         unsafe {
-            gl::BindTexture(gl::TEXTURE_2D, self.render_texture);
+            gl::BindTexture(gl::TEXTURE_CUBE_MAP, self.depth_cube);
         }
     }
 
-    pub fn resize(&self, width: i32, height: i32) {
+    /// Reallocates all six faces with `size` * `size` texels.
+    pub fn resize(&mut self, size: i32) {
+        self.size = size;
         unsafe {
-            gl::BindTexture(gl::TEXTURE_2D, self.render_texture);
-            gl::TexImage2D(
-                gl::TEXTURE_2D,
-                0,
-                gl::RGBA as i32,
-                width,
-                height,
-                0,
-                gl::RGBA,
-                gl::UNSIGNED_BYTE,
-                0 as *const std::ffi::c_void,
-            );
-            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as i32);
-            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
-            gl::BindRenderbuffer(gl::RENDERBUFFER, self.depth_render_buffer);
-            gl::RenderbufferStorage(gl::RENDERBUFFER, gl::DEPTH_COMPONENT, width, height);
-            gl::FramebufferRenderbuffer(
-                gl::FRAMEBUFFER,
-                gl::DEPTH_ATTACHMENT,
-                gl::RENDERBUFFER,
-                self.depth_render_buffer,
-            );
+            gl::BindTexture(gl::TEXTURE_CUBE_MAP, self.depth_cube);
+            for face in 0..6 {
+                gl::TexImage2D(
+                    gl::TEXTURE_CUBE_MAP_POSITIVE_X + face,
+                    0,
+                    gl::DEPTH_COMPONENT as i32,
+                    size,
+                    size,
+                    0,
+                    gl::DEPTH_COMPONENT,
+                    gl::FLOAT,
+                    std::ptr::null(),
+                );
+            }
+            gl::TexParameteri(gl::TEXTURE_CUBE_MAP, gl::TEXTURE_MIN_FILTER, gl::NEAREST as i32);
+            gl::TexParameteri(gl::TEXTURE_CUBE_MAP, gl::TEXTURE_MAG_FILTER, gl::NEAREST as i32);
+            gl::TexParameteri(gl::TEXTURE_CUBE_MAP, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as i32);
+            gl::TexParameteri(gl::TEXTURE_CUBE_MAP, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as i32);
+            gl::TexParameteri(gl::TEXTURE_CUBE_MAP, gl::TEXTURE_WRAP_R, gl::CLAMP_TO_EDGE as i32);
         }
     }
 
     pub fn cleanup(&self) {
         unsafe {
-            gl::DeleteTextures(1, &self.render_texture);
+            gl::DeleteTextures(1, &self.depth_cube);
             gl::DeleteFramebuffers(1, &self.frame_buffer);
-            gl::DeleteRenderbuffers(1, &self.depth_render_buffer);
         }
     }
 }
 
-fn gen_depthbuffer(width: i32, height: i32) -> u32 {
-    let mut depthrenderbuffer = 0;
+impl Drop for DepthCubeTarget {
+    fn drop(&mut self) {
+        #[cfg(not(feature = "debug_off"))]
+        println!(
+            "DepthCubeTarget cleanup t: {}, fb: {}",
+            self.depth_cube, self.frame_buffer
+        );
+        self.cleanup();
+    }
+}
+
+fn gen_renderbuffer(width: i32, height: i32, format: u32, samples: i32) -> u32 {
+    let mut renderbuffer = 0;
+    unsafe {
+        gl::GenRenderbuffers(1, &mut renderbuffer);
+        gl::BindRenderbuffer(gl::RENDERBUFFER, renderbuffer);
+        if samples > 1 {
+            gl::RenderbufferStorageMultisample(gl::RENDERBUFFER, samples, format, width, height);
+        } else {
+            gl::RenderbufferStorage(gl::RENDERBUFFER, format, width, height);
+        }
+    }
+    renderbuffer
+}
+
+fn gen_depth_texture(width: i32, height: i32) -> u32 {
+    let mut texture = 0;
+    unsafe {
+        gl::GenTextures(1, &mut texture);
+        gl::BindTexture(gl::TEXTURE_2D, texture);
+        gl::TexImage2D(
+            gl::TEXTURE_2D,
+            0,
+            gl::DEPTH_COMPONENT24 as i32,
+            width,
+            height,
+            0,
+            gl::DEPTH_COMPONENT,
+            gl::FLOAT,
+            std::ptr::null(),
+        );
+        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::NEAREST as i32);
+        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::NEAREST as i32);
+        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as i32);
+        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as i32);
+    }
+    texture
+}
+
+/// Routes fragment outputs 0..count to the color attachments of the bound framebuffer.
+unsafe fn set_draw_buffers(count: usize) {
+    if count == 0 {
+        gl::DrawBuffer(gl::NONE);
+        gl::ReadBuffer(gl::NONE);
+    } else {
+        let buffers: Vec<u32> = (0..count as u32).map(|i| gl::COLOR_ATTACHMENT0 + i).collect();
+        gl::DrawBuffers(count as i32, buffers.as_ptr());
+    }
+}
+
+/// Reads the color buffer of the currently bound read framebuffer, flipped so row 0 is the top.
+pub fn read_pixels(width: i32, height: i32) -> RgbaImage {
+    let mut data = vec![0u8; (width * height * 4) as usize];
     unsafe {
-        gl::GenRenderbuffers(1, &mut depthrenderbuffer);
-        gl::BindRenderbuffer(gl::RENDERBUFFER, depthrenderbuffer);
-        gl::RenderbufferStorage(gl::RENDERBUFFER, gl::DEPTH_COMPONENT, width, height);
-        gl::FramebufferRenderbuffer(
-            gl::FRAMEBUFFER,
-            gl::DEPTH_ATTACHMENT,
-            gl::RENDERBUFFER,
-            depthrenderbuffer,
+        gl::PixelStorei(gl::PACK_ALIGNMENT, 1);
+        gl::ReadPixels(
+            0,
+            0,
+            width,
+            height,
+            gl::RGBA,
+            gl::UNSIGNED_BYTE,
+            data.as_mut_ptr() as *mut std::ffi::c_void,
         );
     }
-    depthrenderbuffer
+    let mut im = RgbaImage::from_raw(width as u32, height as u32, data).unwrap();
+    image::imageops::flip_vertical_in_place(&mut im);
+    im
+}
+
+/// The framebuffer currently bound for drawing, to restore it after rendering somewhere else.
+pub fn bound_framebuffer() -> u32 {
+    let mut fb = 0;
+    unsafe {
+        gl::GetIntegerv(gl::DRAW_FRAMEBUFFER_BINDING, &mut fb);
+    }
+    fb as u32
 }
 
 pub fn unbind_framebuffer() {
@@ -103,7 +551,21 @@ pub fn gen_framebuffer() -> u32 {
     buffer_name
 }
 
-pub fn gen_empty_texture(width: i32, height: i32) -> u32 {
+/// Texture without data bound to `TEXTURE_2D`, edge clamped and linear filtered
+/// unless the format is an integer one.
+pub fn gen_color_texture(width: i32, height: i32, format: u32) -> u32 {
+    let (pixel_format, data_type, integer) = match format {
+        gl::RGBA16F | gl::RGBA32F | gl::RGB16F | gl::R11F_G11F_B10F => {
+            (gl::RGBA, gl::FLOAT, false)
+        }
+        gl::RG16F | gl::RG32F => (gl::RG, gl::FLOAT, false),
+        gl::R16F | gl::R32F => (gl::RED, gl::FLOAT, false),
+        gl::R32UI | gl::R16UI | gl::R8UI => (gl::RED_INTEGER, gl::UNSIGNED_INT, true),
+        gl::R32I | gl::R16I | gl::R8I => (gl::RED_INTEGER, gl::INT, true),
+        gl::RGBA32UI | gl::RGBA16UI | gl::RGBA8UI => (gl::RGBA_INTEGER, gl::UNSIGNED_INT, true),
+        _ => (gl::RGBA, gl::UNSIGNED_BYTE, false),
+    };
+    let filter = if integer { gl::NEAREST } else { gl::LINEAR };
     let mut texture_name = 0;
     unsafe {
         gl::GenTextures(1, &mut texture_name);
@@ -111,16 +573,18 @@ pub fn gen_empty_texture(width: i32, height: i32) -> u32 {
         gl::TexImage2D(
             gl::TEXTURE_2D,
             0,
-            gl::RGBA as i32,
+            format as i32,
             width,
             height,
             0,
-            gl::RGBA,
-            gl::UNSIGNED_BYTE,
+            pixel_format,
+            data_type,
             0 as *const std::ffi::c_void,
         );
-        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as i32);
-        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
+        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, filter as i32);
+        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, filter as i32);
+        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as i32);
+        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as i32);
     }
     texture_name
 }
@@ -129,8 +593,8 @@ impl Drop for RenderTarget {
     fn drop(&mut self) {
         #[cfg(not(feature = "debug_off"))]
         println!(
-            "RenderTarget cleanup t: {}, fb: {}, dp: {}",
-            self.render_texture, self.frame_buffer, self.depth_render_buffer
+            "RenderTarget cleanup t: {:?}, fb: {}, dp: {}",
+            self.color_attachments, self.frame_buffer, self.depth_attachment
         );
         self.cleanup();
     }
@@ -1,9 +1,12 @@
 pub mod shader_structs;
 mod shader_util;
 
-use std::sync::Mutex;
+use std::{collections::HashMap, sync::Mutex};
 
 use shader_structs::*;
+
+use super::render_queue;
+use crate::black_sheep::error::{self, EngineError, Result};
 use shader_util::*;
 
 #[derive(Default, Clone)]
@@ -14,18 +17,33 @@ pub struct ShaderRepo {
     pub simple: SimpleShaderProgram,
     pub color_3d: Color3D,
     pub color_3d_light: Color3DLight,
+    pub color_3d_light_instanced: Color3DLightInstanced,
+    pub shadow_cube: ShadowCube,
     pub gizmo: GizmoProgram,
     pub point_2d: Point2D,
     pub simple_2d: Simple2D,
     pub colored_triangles: ColoredTriangles,
     pub sprite: Sprite,
+    pub pbr: Pbr,
+    pub equirect_to_cube: EquirectToCube,
+    pub irradiance: IrradianceConvolution,
+    pub prefilter: PrefilterEnvironment,
+    pub brdf_lut: BrdfIntegration,
+    pub post_bright: PostBright,
+    pub post_blur: PostBlur,
+    pub post_composite: PostComposite,
+    pub post_fxaa: PostFxaa,
+    pub gbuffer: GBufferGeometry,
+    pub deferred_light: DeferredLight,
+    pub skybox: SkyboxProgram,
+    programs: HashMap<&'static str, u32>,
 }
 
 lazy_static! {
     static ref SHADER_REPO: Mutex<Option<ShaderRepo>> = Mutex::new(None);
 }
 
-pub fn init() {
+pub fn init() -> Result<()> {
     let sr = SHADER_REPO.lock();
     if sr.is_err() {
         panic!("shader_repo locked failed");
@@ -34,10 +52,11 @@ pub fn init() {
     let mut sr = sr.unwrap();
 
     if sr.is_some() {
-        panic!("shader_repo already initialized")
+        return Err(EngineError::AlreadyInitialized("shader_repo"));
     }
 
-    *sr = Some(ShaderRepo::new());
+    *sr = Some(ShaderRepo::new()?);
+    Ok(())
 }
 
 pub fn cleanup() {
@@ -48,6 +67,33 @@ pub fn cleanup() {
     }
 }
 
+/// Reloads every program that uses one of the `changed` files.
+pub fn reload_changed(changed: &[String]) {
+    if let Ok(mut sr) = SHADER_REPO.lock() {
+        if let Some(sr) = &mut *sr {
+            for files in SHADER_FILES.iter() {
+                if files.paths().iter().any(|p| changed.contains(p)) {
+                    match sr.reload(files.name) {
+                        Ok(()) => {
+                            if let Some(program) = sr.program(files.name) {
+                                render_queue::invalidate_locations(program);
+                            }
+                            #[cfg(not(feature = "debug_off"))]
+                            println!("reloaded shader {}", files.name);
+                        }
+                        Err(e) => error::report(e),
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Paths of all shader source files, for watching them.
+pub fn source_files() -> Vec<String> {
+    SHADER_FILES.iter().flat_map(|f| f.paths()).collect()
+}
+
 pub fn get_shader_repo() -> ShaderRepo {
     let sr = SHADER_REPO.lock();
     if sr.is_err() {
@@ -63,11 +109,14 @@ pub fn get_shader_repo() -> ShaderRepo {
 }
 
 impl ShaderRepo {
-    fn new() -> Self {
+    fn new() -> Result<Self> {
+        let mut programs = HashMap::new();
+
         let mut point_cloud = CloudGeometryShaderProgram::new();
         {
-            let program = build_shader_program(GVS_SRC_CLOUD, Some(GS_SRC_CLOUD), GFS_SRC_CLOUD);
+            let program = build_shader_program(GVS_SRC_CLOUD, Some(GS_SRC_CLOUD), GFS_SRC_CLOUD)?;
             point_cloud.setup(&program);
+            programs.insert("point_cloud", program);
         }
 
         let mut circle_point_cloud = CircleCloudGeometryShaderProgram::new();
@@ -76,65 +125,194 @@ impl ShaderRepo {
                 GVS_SRC_CIRCLE_CLOUD,
                 Some(GS_SRC_CIRCLE_CLOUD),
                 GFS_SRC_CIRCLE_CLOUD,
-            );
+            )?;
             circle_point_cloud.setup(&program);
+            programs.insert("circle_point_cloud", program);
         }
 
         let mut imgui = ImguiShaderProgram::new();
         {
-            let program = build_shader_program(IMGUI_VS_SRC, None, IMGUI_FS_SRC);
+            let program = build_shader_program(IMGUI_VS_SRC, None, IMGUI_FS_SRC)?;
             imgui.setup(&program);
+            programs.insert("imgui", program);
         }
 
         let mut simple = SimpleShaderProgram::new();
         {
-            let program = build_shader_program(SIMPLE_VS_SRC, None, SIMPLE_FS_SRC);
+            let program = build_shader_program(SIMPLE_VS_SRC, None, SIMPLE_FS_SRC)?;
             simple.setup(&program);
+            programs.insert("simple", program);
         }
 
         let mut color_3d = Color3D::new();
         {
-            let program = build_shader_program(COLOR3D_VS_SRC, None, COLOR3D_FS_SRC);
+            let program = build_shader_program(COLOR3D_VS_SRC, None, COLOR3D_FS_SRC)?;
             color_3d.setup(&program);
+            programs.insert("color_3d", program);
         }
 
         let mut color_3d_light = Color3DLight::new();
         {
-            let program = build_shader_program(COLOR3DLIGHT_VS_SRC, None, COLOR3DLIGHT_FS_SRC);
+            let program = build_shader_program(
+                COLOR3DLIGHT_VS_SRC,
+                None,
+                &insert_chunks(COLOR3DLIGHT_FS_SRC, &[LIGHTS_CHUNK_SRC]),
+            )?;
             color_3d_light.setup(&program);
+            programs.insert("color_3d_light", program);
+        }
+
+        let mut color_3d_light_instanced = Color3DLightInstanced::new();
+        {
+            let program = build_shader_program(
+                COLOR3DLIGHT_INSTANCED_VS_SRC,
+                None,
+                &insert_chunks(COLOR3DLIGHT_INSTANCED_FS_SRC, &[LIGHTS_CHUNK_SRC]),
+            )?;
+            color_3d_light_instanced.setup(&program);
+            programs.insert("color_3d_light_instanced", program);
+        }
+
+        let mut shadow_cube = ShadowCube::new();
+        {
+            let program = build_shader_program(
+                SHADOW_CUBE_VS_SRC,
+                Some(SHADOW_CUBE_GS_SRC),
+                SHADOW_CUBE_FS_SRC,
+            )?;
+            shadow_cube.setup(&program);
+            programs.insert("shadow_cube", program);
+        }
+
+        let mut pbr = Pbr::new();
+        {
+            let program = build_shader_program(
+                PBR_VS_SRC,
+                None,
+                &insert_chunks(PBR_FS_SRC, &[LIGHTS_CHUNK_SRC]),
+            )?;
+            pbr.setup(&program);
+            programs.insert("pbr", program);
+        }
+
+        let mut equirect_to_cube = EquirectToCube::new();
+        {
+            let program =
+                build_shader_program(EQUIRECT_TO_CUBE_VS_SRC, None, EQUIRECT_TO_CUBE_FS_SRC)?;
+            equirect_to_cube.setup(&program);
+            programs.insert("equirect_to_cube", program);
+        }
+
+        let mut irradiance = IrradianceConvolution::new();
+        {
+            let program = build_shader_program(IRRADIANCE_VS_SRC, None, IRRADIANCE_FS_SRC)?;
+            irradiance.setup(&program);
+            programs.insert("irradiance", program);
+        }
+
+        let mut prefilter = PrefilterEnvironment::new();
+        {
+            let program = build_shader_program(PREFILTER_VS_SRC, None, PREFILTER_FS_SRC)?;
+            prefilter.setup(&program);
+            programs.insert("prefilter", program);
+        }
+
+        let mut brdf_lut = BrdfIntegration::new();
+        {
+            let program = build_shader_program(BRDF_LUT_VS_SRC, None, BRDF_LUT_FS_SRC)?;
+            brdf_lut.setup(&program);
+            programs.insert("brdf_lut", program);
+        }
+
+        let mut post_bright = PostBright::new();
+        {
+            let program = build_shader_program(POST_BRIGHT_VS_SRC, None, POST_BRIGHT_FS_SRC)?;
+            post_bright.setup(&program);
+            programs.insert("post_bright", program);
+        }
+
+        let mut post_blur = PostBlur::new();
+        {
+            let program = build_shader_program(POST_BLUR_VS_SRC, None, POST_BLUR_FS_SRC)?;
+            post_blur.setup(&program);
+            programs.insert("post_blur", program);
+        }
+
+        let mut post_composite = PostComposite::new();
+        {
+            let program = build_shader_program(POST_COMPOSITE_VS_SRC, None, POST_COMPOSITE_FS_SRC)?;
+            post_composite.setup(&program);
+            programs.insert("post_composite", program);
+        }
+
+        let mut post_fxaa = PostFxaa::new();
+        {
+            let program = build_shader_program(POST_FXAA_VS_SRC, None, POST_FXAA_FS_SRC)?;
+            post_fxaa.setup(&program);
+            programs.insert("post_fxaa", program);
+        }
+
+        let mut gbuffer = GBufferGeometry::new();
+        {
+            let program = build_shader_program(GBUFFER_VS_SRC, None, GBUFFER_FS_SRC)?;
+            gbuffer.setup(&program);
+            programs.insert("gbuffer", program);
+        }
+
+        let mut deferred_light = DeferredLight::new();
+        {
+            let program = build_shader_program(
+                DEFERRED_LIGHT_VS_SRC,
+                None,
+                &insert_chunks(DEFERRED_LIGHT_FS_SRC, &[LIGHTS_CHUNK_SRC]),
+            )?;
+            deferred_light.setup(&program);
+            programs.insert("deferred_light", program);
+        }
+
+        let mut skybox = SkyboxProgram::new();
+        {
+            let program = build_shader_program(SKYBOX_VS_SRC, None, SKYBOX_FS_SRC)?;
+            skybox.setup(&program);
+            programs.insert("skybox", program);
         }
 
         let mut gizmo = GizmoProgram::new();
         {
-            let program = build_shader_program(GIZMO_VS, Some(GIZMO_GS), GIZMO_FS);
+            let program = build_shader_program(GIZMO_VS, Some(GIZMO_GS), GIZMO_FS)?;
             gizmo.setup(&program);
+            programs.insert("gizmo", program);
         }
 
         let mut point_2d = Point2D::new();
         {
-            let program = build_shader_program(CIRLE_2D_VS, Some(CIRLE_2D_GS), CIRLE_2D_FS);
+            let program = build_shader_program(CIRLE_2D_VS, Some(CIRLE_2D_GS), CIRLE_2D_FS)?;
             point_2d.setup(&program);
+            programs.insert("point_2d", program);
         }
         let mut simple_2d = Simple2D::new();
         {
-            let program = build_shader_program(SIMPLE_2D_VS, None, SIMPLE_2D_FS);
+            let program = build_shader_program(SIMPLE_2D_VS, None, SIMPLE_2D_FS)?;
             simple_2d.setup(&program);
+            programs.insert("simple_2d", program);
         }
 
         let mut colored_triangles = ColoredTriangles::new();
         {
             let program =
-                build_shader_program(COLORED_TRIANGLES_VS_SRC, None, COLORED_TRIANGLES_FS_SRC);
+                build_shader_program(COLORED_TRIANGLES_VS_SRC, None, COLORED_TRIANGLES_FS_SRC)?;
             colored_triangles.setup(&program);
+            programs.insert("colored_triangles", program);
         }
 
         let mut sprite = Sprite::new();
         {
-            let program = build_shader_program(SPRITE_VS_SRC, None, SPRITE_FS_SRC);
+            let program = build_shader_program(SPRITE_VS_SRC, None, SPRITE_FS_SRC)?;
             sprite.setup(&program);
+            programs.insert("sprite", program);
         }
 
-        ShaderRepo {
+        Ok(ShaderRepo {
             imgui,
             point_cloud,
             circle_point_cloud,
@@ -145,9 +323,77 @@ impl ShaderRepo {
             simple_2d,
             colored_triangles,
             color_3d_light,
+            color_3d_light_instanced,
+            shadow_cube,
             sprite,
+            pbr,
+            equirect_to_cube,
+            irradiance,
+            prefilter,
+            brdf_lut,
+            post_bright,
+            post_blur,
+            post_composite,
+            post_fxaa,
+            gbuffer,
+            deferred_light,
+            skybox,
+            programs,
+        })
+    }
+
+    /// Program id of a shader by its field name.
+    pub fn program(&self, name: &str) -> Option<u32> {
+        self.programs.get(name).copied()
+    }
+
+    fn setup_program(&mut self, name: &str, program: u32) {
+        match name {
+            "imgui" => self.imgui.setup(&program),
+            "point_cloud" => self.point_cloud.setup(&program),
+            "circle_point_cloud" => self.circle_point_cloud.setup(&program),
+            "simple" => self.simple.setup(&program),
+            "color_3d" => self.color_3d.setup(&program),
+            "color_3d_light" => self.color_3d_light.setup(&program),
+            "color_3d_light_instanced" => self.color_3d_light_instanced.setup(&program),
+            "shadow_cube" => self.shadow_cube.setup(&program),
+            "gizmo" => self.gizmo.setup(&program),
+            "point_2d" => self.point_2d.setup(&program),
+            "simple_2d" => self.simple_2d.setup(&program),
+            "colored_triangles" => self.colored_triangles.setup(&program),
+            "sprite" => self.sprite.setup(&program),
+            "pbr" => self.pbr.setup(&program),
+            "equirect_to_cube" => self.equirect_to_cube.setup(&program),
+            "irradiance" => self.irradiance.setup(&program),
+            "prefilter" => self.prefilter.setup(&program),
+            "brdf_lut" => self.brdf_lut.setup(&program),
+            "post_bright" => self.post_bright.setup(&program),
+            "post_blur" => self.post_blur.setup(&program),
+            "post_composite" => self.post_composite.setup(&program),
+            "post_fxaa" => self.post_fxaa.setup(&program),
+            "gbuffer" => self.gbuffer.setup(&program),
+            "deferred_light" => self.deferred_light.setup(&program),
+            "skybox" => self.skybox.setup(&program),
+            _ => (),
         }
     }
+
+    /// Rebuilds the program from the files in `SHADER_RES_DIR`.
+    /// The program id stays the same, uniform locations are only queried again for the copy
+    /// in the repo, so program structs have to be fetched with `get_shader_repo` when drawing
+    /// instead of being kept around.
+    pub fn reload(&mut self, name: &str) -> Result<()> {
+        let files = SHADER_FILES
+            .iter()
+            .find(|f| f.name == name)
+            .ok_or_else(|| EngineError::UnknownShader(String::from(name)))?;
+        let program = self
+            .program(name)
+            .ok_or_else(|| EngineError::UnknownShader(String::from(name)))?;
+        files.relink(program)?;
+        self.setup_program(name, program);
+        Ok(())
+    }
     fn cleanup(&mut self) {
         self.imgui.cleanup();
         self.point_cloud.cleanup();
@@ -155,9 +401,23 @@ impl ShaderRepo {
         self.simple.cleanup();
         self.color_3d.cleanup();
         self.color_3d_light.cleanup();
+        self.color_3d_light_instanced.cleanup();
+        self.shadow_cube.cleanup();
         self.gizmo.cleanup();
         self.point_2d.cleanup();
         self.simple_2d.cleanup();
         self.colored_triangles.cleanup();
+        self.skybox.cleanup();
+        self.deferred_light.cleanup();
+        self.gbuffer.cleanup();
+        self.post_fxaa.cleanup();
+        self.post_composite.cleanup();
+        self.post_blur.cleanup();
+        self.post_bright.cleanup();
+        self.brdf_lut.cleanup();
+        self.prefilter.cleanup();
+        self.irradiance.cleanup();
+        self.equirect_to_cube.cleanup();
+        self.pbr.cleanup();
     }
 }
@@ -0,0 +1,12 @@
+#version 450
+
+in vec3 col;
+
+in vec4 world_pos;
+in vec4 world_nor;
+
+out vec4 out_color;
+
+void main(){
+	out_color = vec4(col * lighting(world_pos.xyz, normalize(world_nor.xyz)), 1.0);
+}
@@ -0,0 +1,19 @@
+#version 450
+
+in layout (location = 0) vec3 position;
+in layout (location = 1) vec3 normal;
+in layout (location = 5) mat4 M;
+in layout (location = 9) vec3 instance_col;
+
+uniform mat4 VP;
+
+out vec4 world_pos;
+out vec4 world_nor;
+out vec3 col;
+
+void main(){
+	world_pos = M * vec4(position,1);
+	world_nor = M * vec4(normal,0);
+	col = instance_col;
+	gl_Position =  VP * world_pos;
+}
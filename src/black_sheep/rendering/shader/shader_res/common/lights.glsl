@@ -0,0 +1,89 @@
+// Shared by every lit fragment shader, inserted after the #version line.
+
+#define MAX_LIGHTS 16
+
+struct Light{
+	vec4 position_kind;   // w: 0 directional, 1 point, 2 spot
+	vec4 direction_range;
+	vec4 color_intensity;
+	vec4 cone;            // cos of the inner and outer spot angle
+};
+
+layout(std140, binding = 0) uniform Lights{
+	Light lights[MAX_LIGHTS];
+	int light_count;
+};
+
+uniform samplerCube shadow_map;
+uniform float far_plane;
+uniform float shadow_bias;
+uniform float pcf_radius;
+uniform int shadows;
+uniform int shadow_light;
+
+const vec3 pcf_offsets[20] = vec3[](
+	vec3( 1, 1, 1), vec3( 1,-1, 1), vec3(-1,-1, 1), vec3(-1, 1, 1),
+	vec3( 1, 1,-1), vec3( 1,-1,-1), vec3(-1,-1,-1), vec3(-1, 1,-1),
+	vec3( 1, 1, 0), vec3( 1,-1, 0), vec3(-1,-1, 0), vec3(-1, 1, 0),
+	vec3( 1, 0, 1), vec3(-1, 0, 1), vec3( 1, 0,-1), vec3(-1, 0,-1),
+	vec3( 0, 1, 1), vec3( 0,-1, 1), vec3( 0,-1,-1), vec3( 0, 1,-1)
+);
+
+// fraction of the pcf samples that are in shadow
+float shadow(vec3 frag_pos, vec3 light_position){
+	if(shadows == 0){
+		return 0.0;
+	}
+	vec3 to_frag = frag_pos - light_position;
+	float current = length(to_frag);
+	float s = 0.0;
+	for(int i = 0; i < 20; ++i){
+		float closest = texture(shadow_map, to_frag + pcf_offsets[i] * pcf_radius).r * far_plane;
+		if(current - shadow_bias > closest){
+			s += 1.0;
+		}
+	}
+	return s / 20.0;
+}
+
+// inverse square falloff that reaches zero at the range of the light
+float attenuation(float dist, float range){
+	float x = clamp(1.0 - pow(dist / range, 4.0), 0.0, 1.0);
+	return x * x / (dist * dist + 1.0);
+}
+
+// radiance arriving from light i and the direction towards it
+vec3 incoming(int i, vec3 pos, out vec3 dir){
+	Light l = lights[i];
+	int kind = int(l.position_kind.w);
+	vec3 radiance = l.color_intensity.rgb * l.color_intensity.a;
+
+	if(kind == 0){
+		dir = -normalize(l.direction_range.xyz);
+	}else{
+		vec3 to_light = l.position_kind.xyz - pos;
+		float dist = length(to_light);
+		dir = to_light / dist;
+		radiance *= attenuation(dist, l.direction_range.w);
+		if(kind == 2){
+			float theta = dot(-dir, normalize(l.direction_range.xyz));
+			radiance *= smoothstep(l.cone.y, l.cone.x, theta);
+		}
+	}
+	if(i == shadow_light){
+		radiance *= 1.0 - shadow(pos, l.position_kind.xyz);
+	}
+	return radiance;
+}
+
+// diffuse light of all lights
+vec3 lighting(vec3 pos, vec3 nor){
+	vec3 sum = vec3(0.0);
+	for(int i = 0; i < min(light_count, MAX_LIGHTS); ++i){
+		vec3 dir;
+		vec3 radiance = incoming(i, pos, dir);
+		sum += radiance * max(0.0, dot(dir, nor));
+	}
+	return sum;
+}
+
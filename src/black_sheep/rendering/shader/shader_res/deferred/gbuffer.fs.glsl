@@ -0,0 +1,21 @@
+#version 450
+
+in vec3 world_pos;
+in vec3 world_nor;
+in vec3 col;
+in float view_depth;
+
+// x: specular strength, y: roughness
+uniform vec2 material;
+
+layout (location = 0) out vec4 albedo;
+layout (location = 1) out vec4 normal;
+layout (location = 2) out vec4 position;
+layout (location = 3) out vec4 material_out;
+
+void main(){
+	albedo = vec4(col, 1.0);
+	normal = vec4(normalize(world_nor), 0.0);
+	position = vec4(world_pos, view_depth);
+	material_out = vec4(material, 0.0, 1.0);
+}
@@ -0,0 +1,27 @@
+#version 450
+
+in layout (location = 0) vec3 position;
+in layout (location = 1) vec3 normal;
+in layout (location = 5) mat4 instance_M;
+in layout (location = 9) vec3 instance_col;
+
+uniform mat4 VP;
+uniform mat4 M;
+uniform vec3 color;
+// 1: model matrix and color come from the instance attributes
+uniform int instanced;
+
+out vec3 world_pos;
+out vec3 world_nor;
+out vec3 col;
+out float view_depth;
+
+void main(){
+	mat4 model = instanced != 0 ? instance_M : M;
+	vec4 wp = model * vec4(position, 1.0);
+	world_pos = wp.xyz;
+	world_nor = (model * vec4(normal, 0.0)).xyz;
+	col = instanced != 0 ? instance_col : color;
+	gl_Position = VP * wp;
+	view_depth = gl_Position.w;
+}
@@ -0,0 +1,63 @@
+#version 450
+
+uniform sampler2D g_albedo;
+uniform sampler2D g_normal;
+uniform sampler2D g_position;
+uniform sampler2D g_material;
+uniform sampler2D g_depth;
+
+uniform vec3 camera_position;
+// 0: lit, 1: albedo, 2: normal, 3: position, 4: depth, 5: material
+uniform int channel;
+// view depth shown as white in the depth channel
+uniform float depth_range;
+
+in vec2 ndc;
+
+out vec4 out_color;
+
+// diffuse plus a blinn-phong highlight scaled by the specular strength
+vec3 light_contribution(int i, vec3 pos, vec3 nor, vec3 view, vec2 material){
+	vec3 dir;
+	vec3 radiance = incoming(i, pos, dir);
+	float n_dot_l = max(0.0, dot(dir, nor));
+	float shininess = mix(256.0, 4.0, material.y);
+	float spec = material.x * pow(max(0.0, dot(nor, normalize(dir + view))), shininess);
+	return radiance * n_dot_l + radiance * spec * step(0.0, n_dot_l);
+}
+
+void main(){
+	vec2 uv = ndc * 0.5 + 0.5;
+	float depth = texture(g_depth, uv).r;
+	if(depth >= 1.0){
+		// nothing was drawn here, keep the clear color
+		discard;
+	}
+	gl_FragDepth = depth;
+
+	vec3 albedo = texture(g_albedo, uv).rgb;
+	vec3 nor = texture(g_normal, uv).xyz;
+	vec4 position = texture(g_position, uv);
+	vec2 material = texture(g_material, uv).xy;
+
+	vec3 color;
+	if(channel == 1){
+		color = albedo;
+	}else if(channel == 2){
+		color = nor * 0.5 + 0.5;
+	}else if(channel == 3){
+		color = fract(position.xyz / 10.0);
+	}else if(channel == 4){
+		color = vec3(clamp(position.w / depth_range, 0.0, 1.0));
+	}else if(channel == 5){
+		color = vec3(material, 0.0);
+	}else{
+		vec3 view = normalize(camera_position - position.xyz);
+		vec3 sum = vec3(0.0);
+		for(int i = 0; i < min(light_count, MAX_LIGHTS); ++i){
+			sum += light_contribution(i, position.xyz, nor, view, material);
+		}
+		color = albedo * sum;
+	}
+	out_color = vec4(color, 1.0);
+}
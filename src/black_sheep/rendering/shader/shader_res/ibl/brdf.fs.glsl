@@ -0,0 +1,66 @@
+#version 450
+
+const float PI = 3.14159265359;
+
+uniform int sample_count;
+
+in vec2 ndc;
+
+out vec2 out_color;
+
+float radical_inverse(uint bits){
+	bits = (bits << 16u) | (bits >> 16u);
+	bits = ((bits & 0x55555555u) << 1u) | ((bits & 0xAAAAAAAAu) >> 1u);
+	bits = ((bits & 0x33333333u) << 2u) | ((bits & 0xCCCCCCCCu) >> 2u);
+	bits = ((bits & 0x0F0F0F0Fu) << 4u) | ((bits & 0xF0F0F0F0u) >> 4u);
+	bits = ((bits & 0x00FF00FFu) << 8u) | ((bits & 0xFF00FF00u) >> 8u);
+	return float(bits) * 2.3283064365386963e-10;
+}
+
+vec2 hammersley(uint i, uint n){
+	return vec2(float(i) / float(n), radical_inverse(i));
+}
+
+vec3 importance_sample_ggx(vec2 xi, vec3 N, float roughness){
+	float a = roughness * roughness;
+	float phi = 2.0 * PI * xi.x;
+	float cos_theta = sqrt((1.0 - xi.y) / (1.0 + (a * a - 1.0) * xi.y));
+	float sin_theta = sqrt(1.0 - cos_theta * cos_theta);
+	vec3 h = vec3(cos(phi) * sin_theta, sin(phi) * sin_theta, cos_theta);
+	vec3 up = abs(N.z) < 0.999 ? vec3(0.0, 0.0, 1.0) : vec3(1.0, 0.0, 0.0);
+	vec3 tangent = normalize(cross(up, N));
+	vec3 bitangent = cross(N, tangent);
+	return normalize(tangent * h.x + bitangent * h.y + N * h.z);
+}
+
+float geometry_ibl(float NdotV, float NdotL, float roughness){
+	float k = roughness * roughness / 2.0;
+	return NdotV / (NdotV * (1.0 - k) + k) * NdotL / (NdotL * (1.0 - k) + k);
+}
+
+// scale and bias to F0 of the split sum approximation, x: NdotV, y: roughness
+void main(){
+	vec2 uv = ndc * 0.5 + 0.5;
+	float NdotV = max(uv.x, 1e-3);
+	float roughness = uv.y;
+	vec3 V = vec3(sqrt(1.0 - NdotV * NdotV), 0.0, NdotV);
+	vec3 N = vec3(0.0, 0.0, 1.0);
+
+	uint n = uint(sample_count);
+	float a = 0.0;
+	float b = 0.0;
+	for(uint i = 0u; i < n; ++i){
+		vec3 H = importance_sample_ggx(hammersley(i, n), N, roughness);
+		vec3 L = normalize(2.0 * dot(V, H) * H - V);
+		float NdotL = max(L.z, 0.0);
+		float NdotH = max(H.z, 0.0);
+		float VdotH = max(dot(V, H), 0.0);
+		if(NdotL > 0.0){
+			float G_vis = geometry_ibl(NdotV, NdotL, roughness) * VdotH / (NdotH * NdotV);
+			float Fc = pow(1.0 - VdotH, 5.0);
+			a += (1.0 - Fc) * G_vis;
+			b += Fc * G_vis;
+		}
+	}
+	out_color = vec2(a, b) / float(n);
+}
@@ -0,0 +1,29 @@
+#version 450
+
+const float PI = 3.14159265359;
+
+uniform sampler2D equirect;
+uniform int face;
+
+in vec2 ndc;
+
+out vec4 out_color;
+
+// direction through a texel of a cube map face, uv in [-1, 1]
+vec3 cube_dir(int face, vec2 uv){
+	switch(face){
+		case 0: return normalize(vec3( 1.0, -uv.y, -uv.x));
+		case 1: return normalize(vec3(-1.0, -uv.y,  uv.x));
+		case 2: return normalize(vec3( uv.x,  1.0,  uv.y));
+		case 3: return normalize(vec3( uv.x, -1.0, -uv.y));
+		case 4: return normalize(vec3( uv.x, -uv.y,  1.0));
+		default: return normalize(vec3(-uv.x, -uv.y, -1.0));
+	}
+}
+
+void main(){
+	vec3 dir = cube_dir(face, ndc);
+	// row 0 of the image is the top of the sky
+	vec2 uv = vec2(atan(dir.z, dir.x) / (2.0 * PI) + 0.5, 0.5 - asin(dir.y) / PI);
+	out_color = vec4(texture(equirect, uv).rgb, 1.0);
+}
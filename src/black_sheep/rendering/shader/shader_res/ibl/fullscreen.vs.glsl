@@ -0,0 +1,9 @@
+#version 450
+
+// one triangle covering the screen, drawn with glDrawArrays(GL_TRIANGLES, 0, 3) and no buffers
+out vec2 ndc;
+
+void main(){
+	ndc = vec2(float((gl_VertexID & 1) << 2) - 1.0, float((gl_VertexID & 2) << 1) - 1.0);
+	gl_Position = vec4(ndc, 0.0, 1.0);
+}
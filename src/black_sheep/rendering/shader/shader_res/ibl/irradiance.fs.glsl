@@ -0,0 +1,42 @@
+#version 450
+
+const float PI = 3.14159265359;
+
+uniform samplerCube environment;
+uniform int face;
+
+in vec2 ndc;
+
+out vec4 out_color;
+
+// direction through a texel of a cube map face, uv in [-1, 1]
+vec3 cube_dir(int face, vec2 uv){
+	switch(face){
+		case 0: return normalize(vec3( 1.0, -uv.y, -uv.x));
+		case 1: return normalize(vec3(-1.0, -uv.y,  uv.x));
+		case 2: return normalize(vec3( uv.x,  1.0,  uv.y));
+		case 3: return normalize(vec3( uv.x, -1.0, -uv.y));
+		case 4: return normalize(vec3( uv.x, -uv.y,  1.0));
+		default: return normalize(vec3(-uv.x, -uv.y, -1.0));
+	}
+}
+
+void main(){
+	vec3 N = cube_dir(face, ndc);
+	vec3 up = abs(N.y) < 0.999 ? vec3(0.0, 1.0, 0.0) : vec3(1.0, 0.0, 0.0);
+	vec3 right = normalize(cross(up, N));
+	up = cross(N, right);
+
+	vec3 irradiance = vec3(0.0);
+	float samples = 0.0;
+	float delta = 0.025;
+	for(float phi = 0.0; phi < 2.0 * PI; phi += delta){
+		for(float theta = 0.0; theta < 0.5 * PI; theta += delta){
+			vec3 t = vec3(sin(theta) * cos(phi), sin(theta) * sin(phi), cos(theta));
+			vec3 dir = t.x * right + t.y * up + t.z * N;
+			irradiance += textureLod(environment, dir, 2.0).rgb * cos(theta) * sin(theta);
+			samples += 1.0;
+		}
+	}
+	out_color = vec4(PI * irradiance / samples, 1.0);
+}
@@ -0,0 +1,82 @@
+#version 450
+
+const float PI = 3.14159265359;
+const uint SAMPLES = 512u;
+
+uniform samplerCube environment;
+uniform int face;
+uniform float roughness;
+// edge length of a face of the environment cube map
+uniform float resolution;
+
+in vec2 ndc;
+
+out vec4 out_color;
+
+// direction through a texel of a cube map face, uv in [-1, 1]
+vec3 cube_dir(int face, vec2 uv){
+	switch(face){
+		case 0: return normalize(vec3( 1.0, -uv.y, -uv.x));
+		case 1: return normalize(vec3(-1.0, -uv.y,  uv.x));
+		case 2: return normalize(vec3( uv.x,  1.0,  uv.y));
+		case 3: return normalize(vec3( uv.x, -1.0, -uv.y));
+		case 4: return normalize(vec3( uv.x, -uv.y,  1.0));
+		default: return normalize(vec3(-uv.x, -uv.y, -1.0));
+	}
+}
+
+float radical_inverse(uint bits){
+	bits = (bits << 16u) | (bits >> 16u);
+	bits = ((bits & 0x55555555u) << 1u) | ((bits & 0xAAAAAAAAu) >> 1u);
+	bits = ((bits & 0x33333333u) << 2u) | ((bits & 0xCCCCCCCCu) >> 2u);
+	bits = ((bits & 0x0F0F0F0Fu) << 4u) | ((bits & 0xF0F0F0F0u) >> 4u);
+	bits = ((bits & 0x00FF00FFu) << 8u) | ((bits & 0xFF00FF00u) >> 8u);
+	return float(bits) * 2.3283064365386963e-10;
+}
+
+vec2 hammersley(uint i, uint n){
+	return vec2(float(i) / float(n), radical_inverse(i));
+}
+
+vec3 importance_sample_ggx(vec2 xi, vec3 N, float roughness){
+	float a = roughness * roughness;
+	float phi = 2.0 * PI * xi.x;
+	float cos_theta = sqrt((1.0 - xi.y) / (1.0 + (a * a - 1.0) * xi.y));
+	float sin_theta = sqrt(1.0 - cos_theta * cos_theta);
+	vec3 h = vec3(cos(phi) * sin_theta, sin(phi) * sin_theta, cos_theta);
+	vec3 up = abs(N.z) < 0.999 ? vec3(0.0, 0.0, 1.0) : vec3(1.0, 0.0, 0.0);
+	vec3 tangent = normalize(cross(up, N));
+	vec3 bitangent = cross(N, tangent);
+	return normalize(tangent * h.x + bitangent * h.y + N * h.z);
+}
+
+float distribution_ggx(float NdotH, float roughness){
+	float a = roughness * roughness;
+	float a2 = a * a;
+	float d = NdotH * NdotH * (a2 - 1.0) + 1.0;
+	return a2 / (PI * d * d);
+}
+
+void main(){
+	vec3 N = cube_dir(face, ndc);
+	vec3 V = N;
+
+	vec3 color = vec3(0.0);
+	float weight = 0.0;
+	for(uint i = 0u; i < SAMPLES; ++i){
+		vec3 H = importance_sample_ggx(hammersley(i, SAMPLES), N, roughness);
+		vec3 L = normalize(2.0 * dot(V, H) * H - V);
+		float NdotL = dot(N, L);
+		if(NdotL > 0.0){
+			// read from a blurrier mip where few samples cover a large solid angle
+			float NdotH = max(dot(N, H), 0.0);
+			float pdf = distribution_ggx(NdotH, roughness) / 4.0 + 1e-4;
+			float sa_texel = 4.0 * PI / (6.0 * resolution * resolution);
+			float sa_sample = 1.0 / (float(SAMPLES) * pdf + 1e-4);
+			float mip = roughness == 0.0 ? 0.0 : 0.5 * log2(sa_sample / sa_texel);
+			color += textureLod(environment, L, mip).rgb * NdotL;
+			weight += NdotL;
+		}
+	}
+	out_color = vec4(color / weight, 1.0);
+}
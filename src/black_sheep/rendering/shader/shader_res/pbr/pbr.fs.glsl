@@ -0,0 +1,164 @@
+#version 450
+
+const float PI = 3.14159265359;
+
+in vec3 world_pos;
+in vec3 world_nor;
+in vec4 world_tan;
+in vec2 tex_uv;
+
+out vec4 out_color;
+
+uniform vec3 camera_position;
+
+uniform vec4 base_color_factor;
+uniform float metallic_factor;
+uniform float roughness_factor;
+uniform float normal_scale;
+uniform float occlusion_strength;
+uniform vec3 emissive_factor;
+// negative if the material is not alpha masked
+uniform float alpha_cutoff;
+
+uniform int has_base_color_map;
+uniform int has_metallic_roughness_map;
+uniform int has_normal_map;
+uniform int has_occlusion_map;
+uniform int has_emissive_map;
+uniform sampler2D base_color_map;
+uniform sampler2D metallic_roughness_map;
+uniform sampler2D normal_map;
+uniform sampler2D occlusion_map;
+uniform sampler2D emissive_map;
+
+uniform samplerCube irradiance_map;
+uniform samplerCube prefilter_map;
+uniform sampler2D brdf_lut;
+uniform float prefilter_mips;
+uniform float ibl_intensity;
+
+uniform float exposure;
+uniform int tonemap;
+
+vec4 srgb_to_linear(vec4 c){
+	return vec4(pow(c.rgb, vec3(2.2)), c.a);
+}
+
+// tangent frame from screen space derivatives, for meshes without tangents
+mat3 cotangent_frame(vec3 N, vec3 p, vec2 uv){
+	vec3 dp1 = dFdx(p);
+	vec3 dp2 = dFdy(p);
+	vec2 duv1 = dFdx(uv);
+	vec2 duv2 = dFdy(uv);
+	vec3 dp2perp = cross(dp2, N);
+	vec3 dp1perp = cross(N, dp1);
+	vec3 T = dp2perp * duv1.x + dp1perp * duv2.x;
+	vec3 B = dp2perp * duv1.y + dp1perp * duv2.y;
+	float invmax = inversesqrt(max(dot(T,T), dot(B,B)));
+	return mat3(T * invmax, B * invmax, N);
+}
+
+vec3 surface_normal(){
+	vec3 N = normalize(world_nor);
+	if(has_normal_map == 0){
+		return N;
+	}
+	vec3 n = texture(normal_map, tex_uv).xyz * 2.0 - 1.0;
+	n.xy *= normal_scale;
+	mat3 TBN;
+	if(dot(world_tan.xyz, world_tan.xyz) < 0.01){
+		TBN = cotangent_frame(N, world_pos, tex_uv);
+	}else{
+		vec3 T = normalize(world_tan.xyz - N * dot(N, world_tan.xyz));
+		TBN = mat3(T, cross(N, T) * world_tan.w, N);
+	}
+	return normalize(TBN * n);
+}
+
+float distribution_ggx(float NdotH, float roughness){
+	float a = roughness * roughness;
+	float a2 = a * a;
+	float d = NdotH * NdotH * (a2 - 1.0) + 1.0;
+	return a2 / (PI * d * d);
+}
+
+float geometry_smith(float NdotV, float NdotL, float roughness){
+	float k = (roughness + 1.0) * (roughness + 1.0) / 8.0;
+	return NdotV / (NdotV * (1.0 - k) + k) * NdotL / (NdotL * (1.0 - k) + k);
+}
+
+vec3 fresnel_schlick(float cos_theta, vec3 F0){
+	return F0 + (1.0 - F0) * pow(clamp(1.0 - cos_theta, 0.0, 1.0), 5.0);
+}
+
+vec3 fresnel_schlick_roughness(float cos_theta, vec3 F0, float roughness){
+	return F0 + (max(vec3(1.0 - roughness), F0) - F0) * pow(clamp(1.0 - cos_theta, 0.0, 1.0), 5.0);
+}
+
+vec3 aces(vec3 x){
+	return clamp((x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14), 0.0, 1.0);
+}
+
+void main(){
+	vec4 base = base_color_factor;
+	if(has_base_color_map == 1){
+		base *= srgb_to_linear(texture(base_color_map, tex_uv));
+	}
+	if(alpha_cutoff >= 0.0 && base.a < alpha_cutoff){
+		discard;
+	}
+
+	float metallic = metallic_factor;
+	float roughness = roughness_factor;
+	if(has_metallic_roughness_map == 1){
+		vec4 mr = texture(metallic_roughness_map, tex_uv);
+		roughness *= mr.g;
+		metallic *= mr.b;
+	}
+	roughness = clamp(roughness, 0.04, 1.0);
+
+	vec3 N = surface_normal();
+	vec3 V = normalize(camera_position - world_pos);
+	float NdotV = max(dot(N, V), 1e-4);
+	vec3 F0 = mix(vec3(0.04), base.rgb, metallic);
+
+	vec3 Lo = vec3(0.0);
+	for(int i = 0; i < min(light_count, MAX_LIGHTS); ++i){
+		vec3 L;
+		vec3 radiance = incoming(i, world_pos, L);
+		float NdotL = max(dot(N, L), 0.0);
+		if(NdotL <= 0.0){
+			continue;
+		}
+		vec3 H = normalize(V + L);
+		float NdotH = max(dot(N, H), 0.0);
+		vec3 F = fresnel_schlick(max(dot(H, V), 0.0), F0);
+		vec3 specular = distribution_ggx(NdotH, roughness) * geometry_smith(NdotV, NdotL, roughness) * F
+			/ (4.0 * NdotV * NdotL + 1e-4);
+		vec3 kd = (1.0 - F) * (1.0 - metallic);
+		Lo += (kd * base.rgb / PI + specular) * radiance * NdotL;
+	}
+
+	vec3 F = fresnel_schlick_roughness(NdotV, F0, roughness);
+	vec3 kd = (1.0 - F) * (1.0 - metallic);
+	vec3 diffuse = texture(irradiance_map, N).rgb * base.rgb;
+	vec3 prefiltered = textureLod(prefilter_map, reflect(-V, N), roughness * prefilter_mips).rgb;
+	vec2 brdf = texture(brdf_lut, vec2(NdotV, roughness)).rg;
+	vec3 specular = prefiltered * (F * brdf.x + brdf.y);
+	float ao = 1.0;
+	if(has_occlusion_map == 1){
+		ao = 1.0 + occlusion_strength * (texture(occlusion_map, tex_uv).r - 1.0);
+	}
+	vec3 ambient = (kd * diffuse + specular) * ao * ibl_intensity;
+
+	vec3 emissive = emissive_factor;
+	if(has_emissive_map == 1){
+		emissive *= srgb_to_linear(texture(emissive_map, tex_uv)).rgb;
+	}
+
+	vec3 color = Lo + ambient + emissive;
+	if(tonemap == 1){
+		color = pow(aces(color * exposure), vec3(1.0 / 2.2));
+	}
+	out_color = vec4(color, base.a);
+}
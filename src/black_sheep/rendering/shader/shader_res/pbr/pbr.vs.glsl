@@ -0,0 +1,23 @@
+#version 450
+
+in layout (location = 0) vec3 position;
+in layout (location = 1) vec3 normal;
+in layout (location = 2) vec2 uv;
+in layout (location = 4) vec4 tangent;
+
+uniform mat4 M;
+uniform mat4 VP;
+
+out vec3 world_pos;
+out vec3 world_nor;
+out vec4 world_tan;
+out vec2 tex_uv;
+
+void main(){
+	vec4 p = M * vec4(position,1);
+	world_pos = p.xyz;
+	world_nor = mat3(M) * normal;
+	world_tan = vec4(mat3(M) * tangent.xyz, tangent.w);
+	tex_uv = uv;
+	gl_Position = VP * p;
+}
@@ -0,0 +1,21 @@
+#version 450
+
+uniform sampler2D source;
+// one texel along the blur axis, in uv units
+uniform vec2 direction;
+
+in vec2 ndc;
+
+out vec4 out_color;
+
+const float WEIGHTS[5] = float[](0.227027, 0.1945946, 0.1216216, 0.054054, 0.016216);
+
+void main(){
+	vec2 uv = ndc * 0.5 + 0.5;
+	vec3 color = texture(source, uv).rgb * WEIGHTS[0];
+	for(int i = 1; i < 5; ++i){
+		color += texture(source, uv + direction * float(i)).rgb * WEIGHTS[i];
+		color += texture(source, uv - direction * float(i)).rgb * WEIGHTS[i];
+	}
+	out_color = vec4(color, 1.0);
+}
@@ -0,0 +1,19 @@
+#version 450
+
+uniform sampler2D source;
+uniform float threshold;
+// width of the soft transition below the threshold
+uniform float knee;
+
+in vec2 ndc;
+
+out vec4 out_color;
+
+void main(){
+	vec3 color = texture(source, ndc * 0.5 + 0.5).rgb;
+	float brightness = max(color.r, max(color.g, color.b));
+	float soft = clamp(brightness - threshold + knee, 0.0, 2.0 * knee);
+	soft = soft * soft / (4.0 * knee + 1e-5);
+	float contribution = max(soft, brightness - threshold) / max(brightness, 1e-5);
+	out_color = vec4(color * contribution, 1.0);
+}
@@ -0,0 +1,67 @@
+#version 450
+
+// 16x16x16 color cube laid out as 16 slices side by side, blue selects the slice
+const float LUT_SIZE = 16.0;
+
+uniform sampler2D scene;
+uniform sampler2D bloom;
+uniform sampler2D lut;
+
+uniform float exposure;
+uniform int tonemap;
+uniform int gamma_correct;
+uniform float gamma;
+uniform int bloom_enabled;
+uniform float bloom_intensity;
+uniform int lut_enabled;
+uniform float lut_strength;
+uniform int vignette_enabled;
+uniform float vignette_strength;
+uniform float vignette_radius;
+
+in vec2 ndc;
+
+out vec4 out_color;
+
+vec3 aces(vec3 x){
+	return clamp((x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14), 0.0, 1.0);
+}
+
+vec3 grade(vec3 color){
+	float slice = color.b * (LUT_SIZE - 1.0);
+	float slice_0 = floor(slice);
+	float slice_1 = min(slice_0 + 1.0, LUT_SIZE - 1.0);
+	// sample texel centers so neighbouring slices don't bleed in
+	vec2 uv = vec2(
+		(0.5 + color.r * (LUT_SIZE - 1.0)) / (LUT_SIZE * LUT_SIZE),
+		(0.5 + color.g * (LUT_SIZE - 1.0)) / LUT_SIZE
+	);
+	vec3 a = texture(lut, uv + vec2(slice_0 / LUT_SIZE, 0.0)).rgb;
+	vec3 b = texture(lut, uv + vec2(slice_1 / LUT_SIZE, 0.0)).rgb;
+	return mix(a, b, slice - slice_0);
+}
+
+void main(){
+	vec2 uv = ndc * 0.5 + 0.5;
+	vec3 color = texture(scene, uv).rgb;
+	if(bloom_enabled != 0){
+		color += texture(bloom, uv).rgb * bloom_intensity;
+	}
+
+	color *= exposure;
+	if(tonemap != 0){
+		color = aces(color);
+	}
+	color = clamp(color, 0.0, 1.0);
+	if(gamma_correct != 0){
+		color = pow(color, vec3(1.0 / gamma));
+	}
+	if(lut_enabled != 0){
+		color = mix(color, grade(color), lut_strength);
+	}
+	if(vignette_enabled != 0){
+		float d = length(ndc) / sqrt(2.0);
+		color *= 1.0 - vignette_strength * smoothstep(vignette_radius, 1.0, d);
+	}
+	out_color = vec4(color, 1.0);
+}
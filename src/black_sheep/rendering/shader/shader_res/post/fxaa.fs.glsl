@@ -0,0 +1,120 @@
+#version 450
+
+// FXAA 3.11 quality 10 style edge search, simplified
+const float EDGE_THRESHOLD = 0.125;
+const float EDGE_THRESHOLD_MIN = 0.0312;
+const float SUBPIXEL_QUALITY = 0.75;
+const int SEARCH_STEPS = 8;
+
+uniform sampler2D source;
+// size of one texel in uv units
+uniform vec2 texel;
+
+in vec2 ndc;
+
+out vec4 out_color;
+
+float luma(vec3 c){
+	return dot(c, vec3(0.299, 0.587, 0.114));
+}
+
+float luma_at(vec2 uv){
+	return luma(texture(source, uv).rgb);
+}
+
+void main(){
+	vec2 uv = ndc * 0.5 + 0.5;
+	vec3 center = texture(source, uv).rgb;
+	float l_c = luma(center);
+	float l_n = luma_at(uv + vec2(0.0, texel.y));
+	float l_s = luma_at(uv - vec2(0.0, texel.y));
+	float l_e = luma_at(uv + vec2(texel.x, 0.0));
+	float l_w = luma_at(uv - vec2(texel.x, 0.0));
+
+	float l_min = min(l_c, min(min(l_n, l_s), min(l_e, l_w)));
+	float l_max = max(l_c, max(max(l_n, l_s), max(l_e, l_w)));
+	float range = l_max - l_min;
+	if(range < max(EDGE_THRESHOLD_MIN, l_max * EDGE_THRESHOLD)){
+		out_color = vec4(center, 1.0);
+		return;
+	}
+
+	float l_ne = luma_at(uv + texel);
+	float l_sw = luma_at(uv - texel);
+	float l_nw = luma_at(uv + vec2(-texel.x, texel.y));
+	float l_se = luma_at(uv + vec2(texel.x, -texel.y));
+
+	float horizontal = abs(l_nw + l_ne - 2.0 * l_n) + 2.0 * abs(l_w + l_e - 2.0 * l_c)
+		+ abs(l_sw + l_se - 2.0 * l_s);
+	float vertical = abs(l_nw + l_sw - 2.0 * l_w) + 2.0 * abs(l_n + l_s - 2.0 * l_c)
+		+ abs(l_ne + l_se - 2.0 * l_e);
+	bool is_horizontal = horizontal >= vertical;
+
+	// step across the edge towards the side with the stronger gradient
+	float l_1 = is_horizontal ? l_s : l_w;
+	float l_2 = is_horizontal ? l_n : l_e;
+	float gradient_1 = abs(l_1 - l_c);
+	float gradient_2 = abs(l_2 - l_c);
+	float step_length = is_horizontal ? texel.y : texel.x;
+	float l_local;
+	if(gradient_1 >= gradient_2){
+		step_length = -step_length;
+		l_local = 0.5 * (l_1 + l_c);
+	} else {
+		l_local = 0.5 * (l_2 + l_c);
+	}
+	float gradient_scaled = 0.25 * max(gradient_1, gradient_2);
+
+	vec2 edge_uv = uv;
+	if(is_horizontal){
+		edge_uv.y += step_length * 0.5;
+	} else {
+		edge_uv.x += step_length * 0.5;
+	}
+	vec2 offset = is_horizontal ? vec2(texel.x, 0.0) : vec2(0.0, texel.y);
+
+	// walk along the edge in both directions until the luma changes
+	vec2 uv_1 = edge_uv - offset;
+	vec2 uv_2 = edge_uv + offset;
+	float end_1 = luma_at(uv_1) - l_local;
+	float end_2 = luma_at(uv_2) - l_local;
+	bool done_1 = abs(end_1) >= gradient_scaled;
+	bool done_2 = abs(end_2) >= gradient_scaled;
+	for(int i = 0; i < SEARCH_STEPS && !(done_1 && done_2); ++i){
+		if(!done_1){
+			uv_1 -= offset * 1.5;
+			end_1 = luma_at(uv_1) - l_local;
+			done_1 = abs(end_1) >= gradient_scaled;
+		}
+		if(!done_2){
+			uv_2 += offset * 1.5;
+			end_2 = luma_at(uv_2) - l_local;
+			done_2 = abs(end_2) >= gradient_scaled;
+		}
+	}
+
+	float distance_1 = is_horizontal ? uv.x - uv_1.x : uv.y - uv_1.y;
+	float distance_2 = is_horizontal ? uv_2.x - uv.x : uv_2.y - uv.y;
+	bool closer_1 = distance_1 < distance_2;
+	float distance_min = min(distance_1, distance_2);
+	float edge_length = distance_1 + distance_2;
+	float pixel_offset = 0.5 - distance_min / edge_length;
+	bool center_smaller = l_c < l_local;
+	bool correct = ((closer_1 ? end_1 : end_2) < 0.0) != center_smaller;
+	float final_offset = correct ? pixel_offset : 0.0;
+
+	// blend subpixel aliasing like single bright pixels
+	float l_average = (2.0 * (l_n + l_s + l_e + l_w) + l_ne + l_nw + l_se + l_sw) / 12.0;
+	float subpixel = clamp(abs(l_average - l_c) / range, 0.0, 1.0);
+	subpixel = smoothstep(0.0, 1.0, subpixel);
+	subpixel = subpixel * subpixel * SUBPIXEL_QUALITY;
+	final_offset = max(final_offset, subpixel);
+
+	vec2 final_uv = uv;
+	if(is_horizontal){
+		final_uv.y += final_offset * step_length;
+	} else {
+		final_uv.x += final_offset * step_length;
+	}
+	out_color = vec4(texture(source, final_uv).rgb, 1.0);
+}
@@ -0,0 +1,11 @@
+#version 450
+
+uniform vec3 light_position;
+uniform float far_plane;
+
+in vec4 world_pos;
+
+void main(){
+	// linear distance to the light, so the lit shaders can compare it directly
+	gl_FragDepth = length(world_pos.xyz - light_position) / far_plane;
+}
@@ -0,0 +1,12 @@
+#version 450
+
+in layout (location = 0) vec3 position;
+in layout (location = 5) mat4 instance_M;
+
+uniform mat4 M;
+uniform int instanced;
+
+void main(){
+	mat4 model = instanced == 1 ? instance_M : M;
+	gl_Position = model * vec4(position,1);
+}
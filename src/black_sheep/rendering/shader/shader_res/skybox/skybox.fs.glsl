@@ -0,0 +1,36 @@
+#version 450
+
+uniform samplerCube sky;
+// 1: gradient sky with a sun instead of the cube map
+uniform int procedural;
+// points towards the sun
+uniform vec3 sun_direction;
+uniform vec3 sun_color;
+uniform vec3 zenith_color;
+uniform vec3 horizon_color;
+uniform vec3 ground_color;
+uniform float intensity;
+
+in vec3 dir;
+
+out vec4 out_color;
+
+vec3 procedural_sky(vec3 d){
+	float h = d.y;
+	vec3 color;
+	if(h >= 0.0){
+		color = mix(horizon_color, zenith_color, pow(h, 0.5));
+	}else{
+		color = mix(horizon_color, ground_color, pow(-h, 0.3));
+	}
+	float sun = max(dot(d, normalize(sun_direction)), 0.0);
+	// disc plus a soft halo, only above the horizon
+	color += sun_color * (pow(sun, 2000.0) * 30.0 + pow(sun, 12.0) * 0.4) * step(0.0, h);
+	return color;
+}
+
+void main(){
+	vec3 d = normalize(dir);
+	vec3 color = procedural != 0 ? procedural_sky(d) : texture(sky, d).rgb;
+	out_color = vec4(color * intensity, 1.0);
+}
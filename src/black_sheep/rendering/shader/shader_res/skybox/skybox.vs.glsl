@@ -0,0 +1,16 @@
+#version 450
+
+// the unit cube of the mesh repo, spanning 0 to 1
+in layout (location = 0) vec3 position;
+
+// projection * view without the camera translation
+uniform mat4 VP;
+
+out vec3 dir;
+
+void main(){
+	dir = position * 2.0 - 1.0;
+	vec4 p = VP * vec4(dir, 1.0);
+	// just in front of the far plane, so it only shows where nothing else was drawn
+	gl_Position = vec4(p.xy, p.w * 0.99999, p.w);
+}
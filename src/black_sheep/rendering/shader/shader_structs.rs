@@ -42,8 +42,33 @@ shader_program!(
         uniform mat4 MVP;
         uniform mat4 M;
         uniform vec3 col;
+        uniform int shadow_map;
+        uniform float far_plane;
+        uniform float shadow_bias;
+        uniform float pcf_radius;
+        uniform int shadows;
+        uniform int shadow_light;
+    }
+);
+
+shader_program!(
+    Color3DLightInstanced{
+        uniform mat4 VP;
+        uniform int shadow_map;
+        uniform float far_plane;
+        uniform float shadow_bias;
+        uniform float pcf_radius;
+        uniform int shadows;
+        uniform int shadow_light;
+    }
+);
+
+shader_program!(
+    ShadowCube{
+        uniform mat4 M;
+        uniform int instanced;
         uniform vec3 light_position;
-        uniform float light_power;
+        uniform float far_plane;
     }
 );
 
@@ -80,3 +105,120 @@ shader_program!(
         uniform vec2 BillboardSize; 
     }
 );
+
+shader_program!(
+    Pbr{
+        uniform mat4 M;
+        uniform mat4 VP;
+        uniform vec3 camera_position;
+        uniform float exposure;
+        uniform int tonemap;
+    }
+);
+
+shader_program!(
+    EquirectToCube{
+        uniform int equirect;
+        uniform int face;
+    }
+);
+
+shader_program!(
+    IrradianceConvolution{
+        uniform int environment;
+        uniform int face;
+    }
+);
+
+shader_program!(
+    PrefilterEnvironment{
+        uniform int environment;
+        uniform int face;
+        uniform float roughness;
+        uniform float resolution;
+    }
+);
+
+shader_program!(
+    BrdfIntegration{
+        uniform int sample_count;
+    }
+);
+
+shader_program!(
+    PostBright{
+        uniform int source;
+        uniform float threshold;
+        uniform float knee;
+    }
+);
+
+shader_program!(
+    PostBlur{
+        uniform int source;
+        uniform vec2 direction;
+    }
+);
+
+shader_program!(
+    PostComposite{
+        uniform int scene;
+        uniform int bloom;
+        uniform int lut;
+        uniform float exposure;
+        uniform int tonemap;
+        uniform int gamma_correct;
+        uniform float gamma;
+        uniform int bloom_enabled;
+        uniform float bloom_intensity;
+        uniform int lut_enabled;
+        uniform float lut_strength;
+        uniform int vignette_enabled;
+        uniform float vignette_strength;
+        uniform float vignette_radius;
+    }
+);
+
+shader_program!(
+    PostFxaa{
+        uniform int source;
+        uniform vec2 texel;
+    }
+);
+
+shader_program!(
+    GBufferGeometry{
+        uniform mat4 VP;
+        uniform mat4 M;
+        uniform vec3 color;
+        uniform int instanced;
+        uniform vec2 material;
+    }
+);
+
+shader_program!(
+    DeferredLight{
+        uniform int g_albedo;
+        uniform int g_normal;
+        uniform int g_position;
+        uniform int g_material;
+        uniform int g_depth;
+        uniform vec3 camera_position;
+        uniform int channel;
+        uniform float depth_range;
+    }
+);
+
+shader_program!(
+    SkyboxProgram{
+        uniform mat4 VP;
+        uniform int sky;
+        uniform int procedural;
+        uniform vec3 sun_direction;
+        uniform vec3 sun_color;
+        uniform vec3 zenith_color;
+        uniform vec3 horizon_color;
+        uniform vec3 ground_color;
+        uniform float intensity;
+    }
+);
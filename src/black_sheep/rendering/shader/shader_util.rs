@@ -1,7 +1,8 @@
 use gl::types::*;
 use std::ffi::CString;
 use std::ptr;
-use std::str;
+
+use crate::black_sheep::error::{EngineError, Result};
 
 // // Shader sources
 
@@ -38,6 +39,57 @@ pub static COLOR3D_FS_SRC: &'static str = include_str!("./shader_res/3D_color/Sh
 pub static COLOR3DLIGHT_VS_SRC: &'static str = include_str!("./shader_res/3D_color_light/Shading.vs.glsl");
 pub static COLOR3DLIGHT_FS_SRC: &'static str = include_str!("./shader_res/3D_color_light/Shading.fs.glsl");
 
+pub static COLOR3DLIGHT_INSTANCED_VS_SRC: &'static str =
+    include_str!("./shader_res/3D_color_light/Instanced.vs.glsl");
+pub static COLOR3DLIGHT_INSTANCED_FS_SRC: &'static str =
+    include_str!("./shader_res/3D_color_light/Instanced.fs.glsl");
+
+pub static SHADOW_CUBE_VS_SRC: &'static str = include_str!("./shader_res/shadow_cube/vs.glsl");
+pub static SHADOW_CUBE_GS_SRC: &'static str = include_str!("./shader_res/shadow_cube/gs.glsl");
+pub static SHADOW_CUBE_FS_SRC: &'static str = include_str!("./shader_res/shadow_cube/fs.glsl");
+
+pub static PBR_VS_SRC: &'static str = include_str!("./shader_res/pbr/pbr.vs.glsl");
+pub static PBR_FS_SRC: &'static str = include_str!("./shader_res/pbr/pbr.fs.glsl");
+
+pub static EQUIRECT_TO_CUBE_VS_SRC: &'static str =
+    include_str!("./shader_res/ibl/fullscreen.vs.glsl");
+pub static EQUIRECT_TO_CUBE_FS_SRC: &'static str =
+    include_str!("./shader_res/ibl/equirect_to_cube.fs.glsl");
+
+pub static IRRADIANCE_VS_SRC: &'static str = include_str!("./shader_res/ibl/fullscreen.vs.glsl");
+pub static IRRADIANCE_FS_SRC: &'static str = include_str!("./shader_res/ibl/irradiance.fs.glsl");
+
+pub static PREFILTER_VS_SRC: &'static str = include_str!("./shader_res/ibl/fullscreen.vs.glsl");
+pub static PREFILTER_FS_SRC: &'static str = include_str!("./shader_res/ibl/prefilter.fs.glsl");
+
+pub static BRDF_LUT_VS_SRC: &'static str = include_str!("./shader_res/ibl/fullscreen.vs.glsl");
+pub static BRDF_LUT_FS_SRC: &'static str = include_str!("./shader_res/ibl/brdf.fs.glsl");
+
+pub static POST_BRIGHT_VS_SRC: &'static str = include_str!("./shader_res/ibl/fullscreen.vs.glsl");
+pub static POST_BRIGHT_FS_SRC: &'static str = include_str!("./shader_res/post/bright.fs.glsl");
+
+pub static POST_BLUR_VS_SRC: &'static str = include_str!("./shader_res/ibl/fullscreen.vs.glsl");
+pub static POST_BLUR_FS_SRC: &'static str = include_str!("./shader_res/post/blur.fs.glsl");
+
+pub static POST_COMPOSITE_VS_SRC: &'static str =
+    include_str!("./shader_res/ibl/fullscreen.vs.glsl");
+pub static POST_COMPOSITE_FS_SRC: &'static str =
+    include_str!("./shader_res/post/composite.fs.glsl");
+
+pub static POST_FXAA_VS_SRC: &'static str = include_str!("./shader_res/ibl/fullscreen.vs.glsl");
+pub static POST_FXAA_FS_SRC: &'static str = include_str!("./shader_res/post/fxaa.fs.glsl");
+
+pub static GBUFFER_VS_SRC: &'static str = include_str!("./shader_res/deferred/gbuffer.vs.glsl");
+pub static GBUFFER_FS_SRC: &'static str = include_str!("./shader_res/deferred/gbuffer.fs.glsl");
+
+pub static DEFERRED_LIGHT_VS_SRC: &'static str =
+    include_str!("./shader_res/ibl/fullscreen.vs.glsl");
+pub static DEFERRED_LIGHT_FS_SRC: &'static str =
+    include_str!("./shader_res/deferred/light.fs.glsl");
+
+pub static SKYBOX_VS_SRC: &'static str = include_str!("./shader_res/skybox/skybox.vs.glsl");
+pub static SKYBOX_FS_SRC: &'static str = include_str!("./shader_res/skybox/skybox.fs.glsl");
+
 pub static SPRITE_VS_SRC: &'static str = include_str!("./shader_res/sprite/sprite.vs.glsl");
 pub static SPRITE_FS_SRC: &'static str = include_str!("./shader_res/sprite/sprite.fs.glsl");
 
@@ -46,14 +98,321 @@ pub static COLORED_TRIANGLES_VS_SRC: &'static str =
 pub static COLORED_TRIANGLES_FS_SRC: &'static str =
     include_str!("./shader_res/colored_triangles/fs.glsl");
 
+/// Lights block, shadow lookup and attenuation shared by the lit fragment shaders.
+pub static LIGHTS_CHUNK: &'static str = "common/lights.glsl";
+pub static LIGHTS_CHUNK_SRC: &'static str = include_str!("./shader_res/common/lights.glsl");
+
+/// Directory the shader sources are read from when they are reloaded at runtime.
+pub static SHADER_RES_DIR: &'static str = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/src/black_sheep/rendering/shader/shader_res"
+);
+
+/// Source files of a program in the `ShaderRepo`, relative to `SHADER_RES_DIR`.
+pub struct ShaderFiles {
+    pub name: &'static str,
+    pub vs: &'static str,
+    pub gs: Option<&'static str>,
+    pub fs: &'static str,
+    /// chunks inserted after the `#version` line of the fragment shader
+    pub fs_chunks: &'static [&'static str],
+}
+
+pub static SHADER_FILES: &'static [ShaderFiles] = &[
+    ShaderFiles {
+        name: "skybox",
+        vs: "skybox/skybox.vs.glsl",
+        gs: None,
+        fs: "skybox/skybox.fs.glsl",
+        fs_chunks: &[],
+    },
+    ShaderFiles {
+        name: "deferred_light",
+        vs: "ibl/fullscreen.vs.glsl",
+        gs: None,
+        fs: "deferred/light.fs.glsl",
+        fs_chunks: &[LIGHTS_CHUNK],
+    },
+    ShaderFiles {
+        name: "gbuffer",
+        vs: "deferred/gbuffer.vs.glsl",
+        gs: None,
+        fs: "deferred/gbuffer.fs.glsl",
+        fs_chunks: &[],
+    },
+    ShaderFiles {
+        name: "post_fxaa",
+        vs: "ibl/fullscreen.vs.glsl",
+        gs: None,
+        fs: "post/fxaa.fs.glsl",
+        fs_chunks: &[],
+    },
+    ShaderFiles {
+        name: "post_composite",
+        vs: "ibl/fullscreen.vs.glsl",
+        gs: None,
+        fs: "post/composite.fs.glsl",
+        fs_chunks: &[],
+    },
+    ShaderFiles {
+        name: "post_blur",
+        vs: "ibl/fullscreen.vs.glsl",
+        gs: None,
+        fs: "post/blur.fs.glsl",
+        fs_chunks: &[],
+    },
+    ShaderFiles {
+        name: "post_bright",
+        vs: "ibl/fullscreen.vs.glsl",
+        gs: None,
+        fs: "post/bright.fs.glsl",
+        fs_chunks: &[],
+    },
+    ShaderFiles {
+        name: "brdf_lut",
+        vs: "ibl/fullscreen.vs.glsl",
+        gs: None,
+        fs: "ibl/brdf.fs.glsl",
+        fs_chunks: &[],
+    },
+    ShaderFiles {
+        name: "prefilter",
+        vs: "ibl/fullscreen.vs.glsl",
+        gs: None,
+        fs: "ibl/prefilter.fs.glsl",
+        fs_chunks: &[],
+    },
+    ShaderFiles {
+        name: "irradiance",
+        vs: "ibl/fullscreen.vs.glsl",
+        gs: None,
+        fs: "ibl/irradiance.fs.glsl",
+        fs_chunks: &[],
+    },
+    ShaderFiles {
+        name: "equirect_to_cube",
+        vs: "ibl/fullscreen.vs.glsl",
+        gs: None,
+        fs: "ibl/equirect_to_cube.fs.glsl",
+        fs_chunks: &[],
+    },
+    ShaderFiles {
+        name: "pbr",
+        vs: "pbr/pbr.vs.glsl",
+        gs: None,
+        fs: "pbr/pbr.fs.glsl",
+        fs_chunks: &[LIGHTS_CHUNK],
+    },
+    ShaderFiles {
+        name: "imgui",
+        vs: "imgui/glsl_400.vert",
+        gs: None,
+        fs: "imgui/glsl_400.frag",
+        fs_chunks: &[],
+    },
+    ShaderFiles {
+        name: "point_cloud",
+        vs: "cloud/gvs_cloud.glsl",
+        gs: Some("cloud/gs_cloud.glsl"),
+        fs: "cloud/gfs_cloud.glsl",
+        fs_chunks: &[],
+    },
+    ShaderFiles {
+        name: "circle_point_cloud",
+        vs: "cloud_circle/gvs_cloud.glsl",
+        gs: Some("cloud_circle/gs_cloud.glsl"),
+        fs: "cloud_circle/gfs_cloud.glsl",
+        fs_chunks: &[],
+    },
+    ShaderFiles {
+        name: "simple",
+        vs: "simple/vs.glsl",
+        gs: None,
+        fs: "simple/fs.glsl",
+        fs_chunks: &[],
+    },
+    ShaderFiles {
+        name: "color_3d",
+        vs: "3D_color/Shading.vs.glsl",
+        gs: None,
+        fs: "3D_color/Shading.fs.glsl",
+        fs_chunks: &[],
+    },
+    ShaderFiles {
+        name: "color_3d_light",
+        vs: "3D_color_light/Shading.vs.glsl",
+        gs: None,
+        fs: "3D_color_light/Shading.fs.glsl",
+        fs_chunks: &[LIGHTS_CHUNK],
+    },
+    ShaderFiles {
+        name: "shadow_cube",
+        vs: "shadow_cube/vs.glsl",
+        gs: Some("shadow_cube/gs.glsl"),
+        fs: "shadow_cube/fs.glsl",
+        fs_chunks: &[],
+    },
+    ShaderFiles {
+        name: "color_3d_light_instanced",
+        vs: "3D_color_light/Instanced.vs.glsl",
+        gs: None,
+        fs: "3D_color_light/Instanced.fs.glsl",
+        fs_chunks: &[LIGHTS_CHUNK],
+    },
+    ShaderFiles {
+        name: "gizmo",
+        vs: "gizmo/vs.glsl",
+        gs: Some("gizmo/gs.glsl"),
+        fs: "gizmo/fs.glsl",
+        fs_chunks: &[],
+    },
+    ShaderFiles {
+        name: "point_2d",
+        vs: "2D_circles/vs.glsl",
+        gs: Some("2D_circles/gs.glsl"),
+        fs: "2D_circles/fs.glsl",
+        fs_chunks: &[],
+    },
+    ShaderFiles {
+        name: "simple_2d",
+        vs: "2D_simple/vs.glsl",
+        gs: None,
+        fs: "2D_simple/fs.glsl",
+        fs_chunks: &[],
+    },
+    ShaderFiles {
+        name: "colored_triangles",
+        vs: "colored_triangles/vs.glsl",
+        gs: None,
+        fs: "colored_triangles/fs.glsl",
+        fs_chunks: &[],
+    },
+    ShaderFiles {
+        name: "sprite",
+        vs: "sprite/sprite.vs.glsl",
+        gs: None,
+        fs: "sprite/sprite.fs.glsl",
+        fs_chunks: &[],
+    },
+];
+
+impl ShaderFiles {
+    pub fn paths(&self) -> Vec<String> {
+        let mut paths = vec![format!("{}/{}", SHADER_RES_DIR, self.vs)];
+        if let Some(gs) = self.gs {
+            paths.push(format!("{}/{}", SHADER_RES_DIR, gs));
+        }
+        paths.push(format!("{}/{}", SHADER_RES_DIR, self.fs));
+        for chunk in self.fs_chunks.iter() {
+            paths.push(format!("{}/{}", SHADER_RES_DIR, chunk));
+        }
+        paths
+    }
+
+    fn read(file: &str) -> Result<String> {
+        let path = format!("{}/{}", SHADER_RES_DIR, file);
+        std::fs::read_to_string(&path).map_err(|e| EngineError::Io(path, e))
+    }
+
+    /// Compiles the current files on disk and links them into the existing `program`,
+    /// so the program id stays valid for everyone holding a copy of it.
+    /// Nothing is changed if a stage fails to compile or the stages fail to link.
+    pub fn relink(&self, program: GLuint) -> Result<()> {
+        let vs_src = Self::read(self.vs)?;
+        let gs_src = match self.gs {
+            Some(gs) => Some(Self::read(gs)?),
+            None => None,
+        };
+        let mut chunks = Vec::with_capacity(self.fs_chunks.len());
+        for chunk in self.fs_chunks.iter() {
+            chunks.push(Self::read(chunk)?);
+        }
+        let chunks: Vec<&str> = chunks.iter().map(String::as_str).collect();
+        let fs_src = insert_chunks(&Self::read(self.fs)?, &chunks);
+
+        let vs = compile_shader(&vs_src, gl::VERTEX_SHADER)?;
+        let gs = match gs_src.map(|gs| compile_shader(&gs, gl::GEOMETRY_SHADER)) {
+            Some(Err(e)) => {
+                delete_shader(vs);
+                return Err(e);
+            }
+            Some(Ok(gs)) => Some(gs),
+            None => None,
+        };
+        let fs = match compile_shader(&fs_src, gl::FRAGMENT_SHADER) {
+            Ok(fs) => fs,
+            Err(e) => {
+                delete_shader(vs);
+                gs.map(|gs| delete_shader(gs));
+                return Err(e);
+            }
+        };
+
+        // a failed link leaves a program unusable, so the stages are tried on a scratch program
+        // before the live one is touched
+        let result = link_shaders(vs, gs, fs).and_then(|scratch| {
+            unsafe {
+                gl::DeleteProgram(scratch);
+                let mut count = 0;
+                let mut attached = [0; 3];
+                gl::GetAttachedShaders(program, 3, &mut count, attached.as_mut_ptr());
+                for shader in attached.iter().take(count as usize) {
+                    gl::DetachShader(program, *shader);
+                }
+            }
+            link_program(program, vs, gs, fs)
+        });
+
+        delete_shader(fs);
+        gs.map(|gs| delete_shader(gs));
+        delete_shader(vs);
+
+        result
+    }
+}
+
+/// Inserts the `chunks` after the `#version` line of `src`. The `#line` after them keeps
+/// the line numbers in compile errors pointing into `src`.
+pub fn insert_chunks(src: &str, chunks: &[&str]) -> String {
+    if chunks.is_empty() {
+        return src.to_string();
+    }
+    let (version, rest, first_line) = match src.find('\n') {
+        Some(i) if src.trim_start().starts_with("#version") => (&src[..=i], &src[i + 1..], 2),
+        _ => ("", src, 1),
+    };
+    let mut out = String::from(version);
+    for chunk in chunks.iter() {
+        out.push_str(chunk);
+        out.push('\n');
+    }
+    out.push_str(&format!("#line {}\n", first_line));
+    out.push_str(rest);
+    out
+}
+
 pub fn build_shader_program(
     vertex_shader: &str,
     geometry_shader: Option<&str>,
     fragment_shader: &str,
-) -> u32 {
-    let vs = compile_shader(vertex_shader, gl::VERTEX_SHADER);
-    let gs = geometry_shader.map(|gs| compile_shader(gs, gl::GEOMETRY_SHADER));
-    let fs = compile_shader(fragment_shader, gl::FRAGMENT_SHADER);
+) -> Result<u32> {
+    let vs = compile_shader(vertex_shader, gl::VERTEX_SHADER)?;
+    let gs = match geometry_shader.map(|gs| compile_shader(gs, gl::GEOMETRY_SHADER)) {
+        Some(Err(e)) => {
+            delete_shader(vs);
+            return Err(e);
+        }
+        Some(Ok(gs)) => Some(gs),
+        None => None,
+    };
+    let fs = match compile_shader(fragment_shader, gl::FRAGMENT_SHADER) {
+        Ok(fs) => fs,
+        Err(e) => {
+            delete_shader(vs);
+            gs.map(|gs| delete_shader(gs));
+            return Err(e);
+        }
+    };
 
     let program = link_shaders(vs, gs, fs);
 
@@ -64,7 +423,7 @@ pub fn build_shader_program(
     program
 }
 
-pub fn compile_shader(src: &str, _type: GLenum) -> GLuint {
+pub fn compile_shader(src: &str, _type: GLenum) -> Result<GLuint> {
     let shader;
     unsafe {
         shader = gl::CreateShader(_type);
@@ -89,22 +448,29 @@ pub fn compile_shader(src: &str, _type: GLenum) -> GLuint {
                 ptr::null_mut(),
                 buf.as_mut_ptr() as *mut GLchar,
             );
-            panic!(
-                "{}",
-                str::from_utf8(&buf)
-                    .ok()
-                    .expect("ShaderInfoLog not valid utf8")
-            );
+            gl::DeleteShader(shader);
+            return Err(EngineError::ShaderCompile(
+                String::from_utf8_lossy(&buf).into_owned(),
+            ));
         }
     }
-    shader
+    Ok(shader)
 }
 
-pub fn link_shaders(vs: GLuint, gs: Option<GLuint>, fs: GLuint) -> GLuint {
-    let program;
-    unsafe {
-        program = gl::CreateProgram();
+pub fn link_shaders(vs: GLuint, gs: Option<GLuint>, fs: GLuint) -> Result<GLuint> {
+    let program = unsafe { gl::CreateProgram() };
+    match link_program(program, vs, gs, fs) {
+        Ok(()) => Ok(program),
+        Err(e) => {
+            unsafe { gl::DeleteProgram(program) };
+            Err(e)
+        }
+    }
+}
 
+/// Attaches the shaders to `program` and links it.
+pub fn link_program(program: GLuint, vs: GLuint, gs: Option<GLuint>, fs: GLuint) -> Result<()> {
+    unsafe {
         gl::AttachShader(program, vs);
         if let Some(gs) = gs {
             gl::AttachShader(program, gs);
@@ -128,15 +494,12 @@ pub fn link_shaders(vs: GLuint, gs: Option<GLuint>, fs: GLuint) -> GLuint {
                 ptr::null_mut(),
                 buf.as_mut_ptr() as *mut GLchar,
             );
-            panic!(
-                "{}",
-                str::from_utf8(&buf)
-                    .ok()
-                    .expect("ProgramInfoLog not valid utf8")
-            );
+            return Err(EngineError::ShaderLink(
+                String::from_utf8_lossy(&buf).into_owned(),
+            ));
         }
     }
-    program
+    Ok(())
 }
 
 pub fn delete_shader(shader_id: u32) {
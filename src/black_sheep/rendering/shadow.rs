@@ -0,0 +1,178 @@
+use std::sync::Mutex;
+
+use cgmath::{perspective, vec3, Deg, EuclideanSpace, Matrix, Matrix4, Point3, Vector3};
+
+use super::{
+    geometry::{instance::InstanceBuffer, mesh::MeshToken},
+    render_queue::{uniform_location, DrawItem, UniformValue},
+    rendertarget::{bound_framebuffer, DepthCubeTarget},
+    shader::{get_shader_repo, shader_structs::ShadowCube},
+    SHADOW_TEXTURE_UNIT,
+};
+use crate::black_sheep::error::Result;
+
+/// Shadow map sizes offered in the debug panel.
+pub const SHADOW_RESOLUTIONS: [i32; 4] = [256, 512, 1024, 2048];
+
+lazy_static! {
+    static ref SHADOW_SETTINGS: Mutex<ShadowSettings> = Mutex::new(ShadowSettings::default());
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ShadowSettings {
+    pub enabled: bool,
+    /// edge length of one cube face
+    pub resolution: i32,
+    /// in world units
+    pub bias: f32,
+    /// how far the pcf samples are spread, in world units
+    pub pcf_radius: f32,
+    /// range of the light, nothing further away casts a shadow
+    pub far_plane: f32,
+}
+
+impl Default for ShadowSettings {
+    fn default() -> Self {
+        ShadowSettings {
+            enabled: true,
+            resolution: 1024,
+            bias: 0.15,
+            pcf_radius: 0.05,
+            far_plane: 150.0,
+        }
+    }
+}
+
+pub fn settings() -> ShadowSettings {
+    SHADOW_SETTINGS.lock().map(|s| *s).unwrap_or_default()
+}
+
+pub fn set_settings(settings: ShadowSettings) {
+    if let Ok(mut s) = SHADOW_SETTINGS.lock() {
+        *s = settings;
+    }
+}
+
+/// Shadow map of one point light.
+pub struct PointShadow {
+    target: DepthCubeTarget,
+    settings: ShadowSettings,
+    /// index of the shadowed light in the `Lights` block
+    light: Option<i32>,
+}
+
+impl PointShadow {
+    pub fn new() -> Result<Self> {
+        let settings = settings();
+        Ok(PointShadow {
+            target: DepthCubeTarget::new(settings.resolution)?,
+            settings,
+            light: None,
+        })
+    }
+
+    /// Renders the depth cube around the light, given as index in the `Lights` block and position
+    /// (see `LightBuffer::shadow_light`), `draw_casters` draws everything that casts a shadow.
+    /// Leaves the cube map bound to `SHADOW_TEXTURE_UNIT` and restores framebuffer and viewport.
+    pub fn render<F: FnMut(&ShadowCaster)>(
+        &mut self,
+        light: Option<(i32, Vector3<f32>)>,
+        mut draw_casters: F,
+    ) {
+        self.settings = settings();
+        self.light = light.map(|(index, _)| index);
+        if self.target.size != self.settings.resolution {
+            self.target.resize(self.settings.resolution);
+        }
+
+        let previous = bound_framebuffer();
+        let mut viewport = [0; 4];
+        unsafe {
+            gl::GetIntegerv(gl::VIEWPORT, viewport.as_mut_ptr());
+        }
+
+        self.target.bind_framebuffer();
+        unsafe {
+            gl::Viewport(0, 0, self.target.size, self.target.size);
+            gl::Clear(gl::DEPTH_BUFFER_BIT);
+        }
+
+        if let (true, Some((_, light_position))) = (self.settings.enabled, light) {
+            let matrices = face_matrices(light_position, self.settings.far_plane);
+            // fetched every frame, a hot reload only updates the uniform locations in the repo
+            let repo = get_shader_repo();
+            let program = repo.shadow_cube;
+            program.use_program();
+            program.set_light_position(light_position);
+            program.set_far_plane(self.settings.far_plane);
+            let program_id = repo.program("shadow_cube").unwrap();
+            let location = uniform_location(program_id, "shadow_matrices");
+            unsafe {
+                gl::UniformMatrix4fv(location, 6, gl::FALSE, matrices[0].as_ptr());
+            }
+            draw_casters(&ShadowCaster { program: &program });
+        }
+
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, previous);
+            gl::Viewport(viewport[0], viewport[1], viewport[2], viewport[3]);
+            gl::ActiveTexture(gl::TEXTURE0 + SHADOW_TEXTURE_UNIT);
+        }
+        self.target.bind_texture();
+    }
+
+    /// The uniforms the lit shaders need to sample this shadow map.
+    pub fn uniforms(&self) -> [(&'static str, UniformValue); 6] {
+        [
+            ("shadow_map", UniformValue::Int(SHADOW_TEXTURE_UNIT as i32)),
+            ("far_plane", UniformValue::Float(self.settings.far_plane)),
+            ("shadow_bias", UniformValue::Float(self.settings.bias)),
+            ("pcf_radius", UniformValue::Float(self.settings.pcf_radius)),
+            ("shadows", UniformValue::Int(self.settings.enabled as i32)),
+            ("shadow_light", UniformValue::Int(self.light.unwrap_or(-1))),
+        ]
+    }
+
+    /// Adds the shadow uniforms to a render queue item.
+    pub fn receive(&self, item: DrawItem) -> DrawItem {
+        self.uniforms()
+            .iter()
+            .fold(item, |item, (name, value)| item.uniform(*name, *value))
+    }
+}
+
+/// Handed to the caster callback of `PointShadow::render`, with the shadow program in use.
+pub struct ShadowCaster<'a> {
+    program: &'a ShadowCube,
+}
+
+impl<'a> ShadowCaster<'a> {
+    pub fn draw(&self, mesh: &MeshToken, model: Matrix4<f32>) {
+        self.program.set_instanced(0);
+        self.program.set_M(model);
+        mesh.bind_vertex_array();
+        mesh.draw_triangle_elements();
+    }
+
+    pub fn draw_instanced(&self, instances: &InstanceBuffer) {
+        self.program.set_instanced(1);
+        instances.draw_triangle_elements();
+    }
+}
+
+/// View projections for the cube faces in the order +x, -x, +y, -y, +z, -z.
+fn face_matrices(light_position: Vector3<f32>, far_plane: f32) -> [Matrix4<f32>; 6] {
+    let projection = perspective(Deg(90.0), 1.0, 0.1, far_plane);
+    let eye = Point3::from_vec(light_position);
+    let face = |dir: Vector3<f32>, up: Vector3<f32>| {
+        projection * Matrix4::look_at_rh(eye, eye + dir, up)
+    };
+    [
+        face(vec3(1.0, 0.0, 0.0), vec3(0.0, -1.0, 0.0)),
+        face(vec3(-1.0, 0.0, 0.0), vec3(0.0, -1.0, 0.0)),
+        face(vec3(0.0, 1.0, 0.0), vec3(0.0, 0.0, 1.0)),
+        face(vec3(0.0, -1.0, 0.0), vec3(0.0, 0.0, -1.0)),
+        face(vec3(0.0, 0.0, 1.0), vec3(0.0, -1.0, 0.0)),
+        face(vec3(0.0, 0.0, -1.0), vec3(0.0, -1.0, 0.0)),
+    ]
+}
@@ -0,0 +1,159 @@
+use cgmath::{Matrix4, Vector4};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    environment::{equirect_to_cube, gen_cube_map, load_hdr},
+    geometry::{get_mesh_repo, mesh::MeshToken},
+    render_queue::{DrawItem, Pass, UniformValue},
+    shader::get_shader_repo,
+    FIRST_MATERIAL_TEXTURE_UNIT,
+};
+use crate::black_sheep::error::{EngineError, Result};
+
+const SKY_CUBE_SIZE: i32 = 1024;
+
+/// The background of a scene.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum SkyDef {
+    /// Six images in the order +x, -x, +y, -y, +z, -z.
+    Cubemap([String; 6]),
+    /// A Radiance .hdr panorama.
+    Equirect(String),
+    /// A gradient with a sun, computed in the shader.
+    Procedural {
+        /// points towards the sun
+        sun_direction: [f32; 3],
+        sun_color: [f32; 3],
+        zenith: [f32; 3],
+        horizon: [f32; 3],
+        ground: [f32; 3],
+    },
+}
+
+impl Default for SkyDef {
+    fn default() -> Self {
+        SkyDef::Procedural {
+            sun_direction: [0.3, 0.6, -0.4],
+            sun_color: [1.0, 0.9, 0.7],
+            zenith: [0.15, 0.35, 0.8],
+            horizon: [0.75, 0.85, 0.95],
+            ground: [0.25, 0.22, 0.2],
+        }
+    }
+}
+
+/// Draws a `SkyDef` behind everything else, in the render queue's sky pass.
+pub struct Skybox {
+    cube: Option<u32>,
+    def: SkyDef,
+    program: u32,
+    mesh: MeshToken,
+    pub intensity: f32,
+}
+
+impl Skybox {
+    pub fn new(def: &SkyDef) -> Result<Self> {
+        let cube = match def {
+            SkyDef::Cubemap(faces) => Some(load_faces(faces)?),
+            SkyDef::Equirect(path) => {
+                let (width, height, data) = load_hdr(path)?;
+                Some(equirect_to_cube(width, height, &data, SKY_CUBE_SIZE))
+            }
+            SkyDef::Procedural { .. } => None,
+        };
+        let mesh = get_mesh_repo(|mr| MeshToken::from(mr.get_mesh_by_name("cube").unwrap()));
+
+        #[cfg(not(feature = "debug_off"))]
+        println!("skybox {:?}", def);
+
+        Ok(Skybox {
+            cube,
+            def: def.clone(),
+            program: get_shader_repo().program("skybox").unwrap(),
+            mesh,
+            intensity: 1.0,
+        })
+    }
+
+    /// The sky as seen from the camera, only the rotation of `view` is used.
+    pub fn draw_item(&self, view: Matrix4<f32>, projection: Matrix4<f32>) -> DrawItem {
+        let mut rotation = view;
+        rotation.w = Vector4::new(0.0, 0.0, 0.0, 1.0);
+
+        let mut item = DrawItem::new(self.mesh.clone(), self.program)
+            .pass(Pass::Sky)
+            .uniform("VP", UniformValue::Mat4(projection * rotation))
+            .uniform("intensity", UniformValue::Float(self.intensity))
+            .uniform("sky", UniformValue::Int(FIRST_MATERIAL_TEXTURE_UNIT as i32));
+        if let Some(cube) = self.cube {
+            item = item
+                .texture_cube(FIRST_MATERIAL_TEXTURE_UNIT, cube)
+                .uniform("procedural", UniformValue::Int(0));
+        }
+        if let SkyDef::Procedural {
+            sun_direction,
+            sun_color,
+            zenith,
+            horizon,
+            ground,
+        } = &self.def
+        {
+            item = item
+                .uniform("procedural", UniformValue::Int(1))
+                .uniform("sun_direction", UniformValue::Vec3((*sun_direction).into()))
+                .uniform("sun_color", UniformValue::Vec3((*sun_color).into()))
+                .uniform("zenith_color", UniformValue::Vec3((*zenith).into()))
+                .uniform("horizon_color", UniformValue::Vec3((*horizon).into()))
+                .uniform("ground_color", UniformValue::Vec3((*ground).into()));
+        }
+        item
+    }
+}
+
+impl Drop for Skybox {
+    fn drop(&mut self) {
+        if let Some(cube) = self.cube {
+            unsafe {
+                gl::DeleteTextures(1, &cube);
+            }
+        }
+    }
+}
+
+/// Uploads six square images of the same size into a new cube map.
+fn load_faces(faces: &[String; 6]) -> Result<u32> {
+    let mut images = Vec::with_capacity(6);
+    for path in faces.iter() {
+        let image = image::open(path)
+            .map_err(|e| EngineError::Image(path.clone(), e))?
+            .to_rgba8();
+        // every face has the size of the first one
+        let expected = match images.first() {
+            Some(first) => (first.width(), first.width()),
+            None => (image.width(), image.width()),
+        };
+        if image.dimensions() != expected {
+            return Err(EngineError::ImageSize(path.clone(), image.dimensions(), expected));
+        }
+        images.push(image);
+    }
+    let size = images[0].width() as i32;
+
+    let cube = gen_cube_map(size, 1);
+    unsafe {
+        for (face, image) in images.iter().enumerate() {
+            gl::TexSubImage2D(
+                gl::TEXTURE_CUBE_MAP_POSITIVE_X + face as u32,
+                0,
+                0,
+                0,
+                size,
+                size,
+                gl::RGBA,
+                gl::UNSIGNED_BYTE,
+                image.as_raw().as_ptr() as *const std::ffi::c_void,
+            );
+        }
+    }
+    Ok(cube)
+}
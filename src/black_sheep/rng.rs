@@ -0,0 +1,40 @@
+use std::sync::Mutex;
+
+use rand::{rngs::SmallRng, SeedableRng};
+
+lazy_static! {
+    static ref SEED: Mutex<Option<u64>> = Mutex::new(None);
+}
+
+/// Sets the seed all streams are derived from, called by the engine before `Logic` is created.
+pub fn init(seed: u64) {
+    #[cfg(not(feature = "debug_off"))]
+    println!("rng seed: {}", seed);
+
+    *SEED.lock().unwrap() = Some(seed);
+}
+
+pub fn seed() -> u64 {
+    SEED.lock().unwrap().expect("rng not initialized")
+}
+
+/// A random number generator only used by one system. Every stream starts from the engine seed
+/// combined with its name, so adding random numbers to one system doesn't change the others.
+/// The same name always gives the same sequence, so a system should ask for its stream once and keep it.
+pub fn stream(name: &str) -> SmallRng {
+    SmallRng::seed_from_u64(seed() ^ fnv1a(name))
+}
+
+pub fn time_seed() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_nanos() as u64)
+        .unwrap_or(0)
+}
+
+// std's hasher is not guaranteed to stay the same between releases
+fn fnv1a(s: &str) -> u64 {
+    s.bytes().fold(0xcbf29ce484222325, |h, b| {
+        (h ^ b as u64).wrapping_mul(0x100000001b3)
+    })
+}
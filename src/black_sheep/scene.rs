@@ -0,0 +1,146 @@
+use std::sync::Mutex;
+
+use cgmath::{Quaternion, SquareMatrix, Vector2, Vector3};
+use serde::{Deserialize, Serialize};
+
+use super::ecs::CHAINED_ECS;
+use super::error::{EngineError, Result};
+use super::rendering::{lights::Light, skybox::SkyDef};
+
+lazy_static! {
+    /// sky of the last spawned scene, the ecs doesn't hold it
+    static ref SPAWNED_SKY: Mutex<Option<SkyDef>> = Mutex::new(None);
+}
+
+/// Entities of a level, one entry per entity with the initial values of its components.
+/// Components that are derived every frame (like `mat`) are not part of the file.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Scene {
+    pub entities: Vec<SceneEntity>,
+    /// the procedural default sky when missing
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sky: Option<SkyDef>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum SceneEntity {
+    Ape {
+        pos: [f32; 3],
+        /// quaternion as [x, y, z, w]
+        #[serde(default = "identity_ori")]
+        ori: [f32; 4],
+        #[serde(default)]
+        direction: [f32; 3],
+        /// defaults to `ori`
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        target_ori: Option<[f32; 4]>,
+        #[serde(default = "white")]
+        col: [f32; 3],
+    },
+    Ball {
+        p: [f32; 2],
+        #[serde(default)]
+        v: [f32; 2],
+    },
+    Light(Light),
+}
+
+fn identity_ori() -> [f32; 4] {
+    [0.0, 0.0, 0.0, 1.0]
+}
+
+fn white() -> [f32; 3] {
+    [1.0, 1.0, 1.0]
+}
+
+impl Scene {
+    pub fn load(path: &str) -> Result<Self> {
+        let s = std::fs::read_to_string(path).map_err(|e| EngineError::Io(String::from(path), e))?;
+        ron::from_str(&s).map_err(|e| EngineError::Parse(String::from(path), e))
+    }
+
+    pub fn save(&self, path: &str) -> std::io::Result<()> {
+        let s = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default()).unwrap();
+        std::fs::write(path, s)
+    }
+
+    pub fn spawn(&self, ecs: &mut CHAINED_ECS) {
+        if let Ok(mut sky) = SPAWNED_SKY.lock() {
+            *sky = self.sky.clone();
+        }
+        for entity in self.entities.iter() {
+            match entity {
+                SceneEntity::Ape {
+                    pos,
+                    ori,
+                    direction,
+                    target_ori,
+                    col,
+                } => {
+                    ecs.add_ape_soa(
+                        Vector3::from(*pos),
+                        Quaternion::from(*ori),
+                        Vector3::from(*direction),
+                        Quaternion::from(target_ori.unwrap_or(*ori)),
+                        Vector3::from(*col),
+                        SquareMatrix::identity(),
+                    );
+                }
+                SceneEntity::Ball { p, v } => {
+                    ecs.add_ball_soa(Vector2::from(*p), Vector2::from(*v));
+                }
+                SceneEntity::Light(light) => {
+                    ecs.add_lamp_soa(*light);
+                }
+            }
+        }
+    }
+
+    /// Collects the current state of all apes, balls and lights, with the sky of the
+    /// last spawned scene.
+    pub fn capture(ecs: &mut CHAINED_ECS) -> Self {
+        let mut entities = Vec::new();
+
+        for (pos, ori, direction, target_ori, col) in ecs.get_ape_state_accessor().lock().iter() {
+            let ori: [f32; 4] = (*ori).into();
+            let target_ori: [f32; 4] = (*target_ori).into();
+            entities.push(SceneEntity::Ape {
+                pos: (*pos).into(),
+                ori,
+                direction: (*direction).into(),
+                target_ori: if ori == target_ori {
+                    None
+                } else {
+                    Some(target_ori)
+                },
+                col: (*col).into(),
+            });
+        }
+
+        for (p, v) in ecs.get_ball_state_accessor().lock().iter() {
+            entities.push(SceneEntity::Ball {
+                p: (*p).into(),
+                v: (*v).into(),
+            });
+        }
+
+        for (light, _) in ecs.get_gather_lights_accessor().lock().iter() {
+            entities.push(SceneEntity::Light(*light));
+        }
+
+        Scene {
+            entities,
+            sky: SPAWNED_SKY.lock().ok().and_then(|sky| sky.clone()),
+        }
+    }
+}
+
+pub fn save_ecs(ecs: &mut CHAINED_ECS, path: &str) {
+    match Scene::capture(ecs).save(path) {
+        Ok(()) => {
+            #[cfg(not(feature = "debug_off"))]
+            println!("scene saved to {}", path);
+        }
+        Err(e) => println!("could not save scene {}: {}", path, e),
+    }
+}
@@ -1,10 +1,295 @@
 use std::time::Duration;
 
-pub const UPS: u64 = 25;
-pub const UPS_F32: f32 = UPS as f32;
-pub const DT: f32 = 1.0 / UPS_F32;
-pub const DT_D: Duration = Duration::from_millis(1000 / UPS);
-
-pub const INIT_WINDOW_SIZE: [u32; 2] = [1100, 800];
-pub const INIT_WINDOW_SIZE_I32: [i32; 2] = [INIT_WINDOW_SIZE[0] as i32, INIT_WINDOW_SIZE[1] as i32];
-pub const INIT_WINDOW_SIZE_F32: [f32; 2] = [INIT_WINDOW_SIZE[0] as f32, INIT_WINDOW_SIZE[1] as f32];
+use cgmath::Deg;
+use serde::{Deserialize, Serialize};
+
+use super::error::{self, EngineError, Result};
+use super::gamestate::camera::Projection;
+
+pub const DEFAULT_CONFIG_PATH: &str = "./engine.ron";
+pub const DEFAULT_INPUT_PATH: &str = "./input.ron";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum RunMode {
+    Windowed,
+    /// No window and no GL context, only `Logic::update` is driven for `ticks` updates.
+    Headless { ticks: u64 },
+    /// Hidden window, every frame is rendered into a `RenderTarget` and saved as png into `out_dir`.
+    /// Runs exactly one update per frame with no interpolation, so the images are reproducible.
+    Offscreen { frames: u64, out_dir: String },
+}
+
+impl Default for RunMode {
+    fn default() -> Self {
+        RunMode::Windowed
+    }
+}
+
+/// Feel of the fly camera.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CameraSettings {
+    /// degrees per pixel of mouse motion
+    pub mouse_sensitivity: f32,
+    /// degrees per second at full stick deflection
+    pub look_speed: f32,
+    pub invert_y: bool,
+    /// top speed in units per second
+    pub speed: f32,
+    /// how fast the top speed is reached, per second
+    pub acceleration: f32,
+    /// how fast the camera stops without input, per second
+    pub damping: f32,
+}
+
+impl Default for CameraSettings {
+    fn default() -> Self {
+        CameraSettings {
+            mouse_sensitivity: 0.1,
+            look_speed: 120.0,
+            invert_y: false,
+            speed: 10.0,
+            acceleration: 8.0,
+            damping: 6.0,
+        }
+    }
+}
+
+impl CameraSettings {
+    pub fn y_sign(&self) -> f32 {
+        if self.invert_y {
+            -1.0
+        } else {
+            1.0
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EngineConfig {
+    /// updates per second of the fixed update loop
+    pub ups: u64,
+    pub window_size: [u32; 2],
+    pub window_title: String,
+    /// 0 disables multisampling
+    pub msaa_samples: u8,
+    pub vsync: bool,
+    /// vertical field of view in degrees
+    pub fov: f32,
+    pub near: f32,
+    pub far: f32,
+    /// width of the ImGui panel on the right, the 3D viewport gets the rest of the window
+    pub side_panel_width: f32,
+    pub camera: CameraSettings,
+    pub run_mode: RunMode,
+    /// scene file to spawn at startup
+    pub scene: Option<String>,
+    /// the ecs state is written to this scene file when the engine shuts down
+    pub save_scene: Option<String>,
+    /// equirectangular .hdr image for image based lighting, a gradient sky is used without one
+    pub environment: Option<String>,
+    /// color grading lookup table of the post processing, see `postprocess::PostProcess::load_lut`
+    pub color_lut: Option<String>,
+    /// file with the key, mouse and controller bindings, the built in bindings are used if it is missing
+    pub input_bindings: String,
+    /// `FakeController` script that drives the input instead of (or together with) real devices
+    pub fake_controller: Option<String>,
+    /// seed of the engine's random numbers, picked from the clock if not set
+    pub seed: Option<u64>,
+    /// the input of every tick is written to this file when the engine shuts down
+    pub record: Option<String>,
+    /// recording to play back instead of the live input, its seed and tick rate override the config
+    pub replay: Option<String>,
+}
+
+impl Default for EngineConfig {
+    fn default() -> Self {
+        EngineConfig {
+            ups: 25,
+            window_size: [1100, 800],
+            window_title: String::from("spam"),
+            msaa_samples: 16,
+            vsync: !cfg!(feature = "vsync_off"),
+            fov: 80.0,
+            near: 0.1,
+            far: 1000.0,
+            side_panel_width: 300.0,
+            camera: CameraSettings::default(),
+            run_mode: RunMode::Windowed,
+            scene: None,
+            save_scene: None,
+            environment: None,
+            color_lut: None,
+            input_bindings: String::from(DEFAULT_INPUT_PATH),
+            fake_controller: None,
+            seed: None,
+            record: None,
+            replay: None,
+        }
+    }
+}
+
+impl EngineConfig {
+    pub fn ups_f32(&self) -> f32 {
+        self.ups as f32
+    }
+
+    pub fn dt(&self) -> f32 {
+        1.0 / self.ups_f32()
+    }
+
+    pub fn dt_d(&self) -> Duration {
+        Duration::from_secs_f64(1.0 / self.ups as f64)
+    }
+
+    /// projection of the perspective cameras
+    pub fn perspective(&self) -> Projection {
+        Projection::Perspective {
+            fov: Deg(self.fov),
+            near: self.near,
+            far: self.far,
+        }
+    }
+
+    pub fn window_size_i32(&self) -> [i32; 2] {
+        [self.window_size[0] as i32, self.window_size[1] as i32]
+    }
+
+    pub fn window_size_f32(&self) -> [f32; 2] {
+        [self.window_size[0] as f32, self.window_size[1] as f32]
+    }
+
+    pub fn load(path: &str) -> Result<Self> {
+        let s = std::fs::read_to_string(path).map_err(|e| EngineError::Io(String::from(path), e))?;
+        let mut config: Self =
+            ron::from_str(&s).map_err(|e| EngineError::Parse(String::from(path), e))?;
+        config.ups = config.ups.max(1);
+        Ok(config)
+    }
+
+    pub fn save(&self, path: &str) -> std::io::Result<()> {
+        let s = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default()).unwrap();
+        std::fs::write(path, s)
+    }
+
+    /// Loads the file given with `--config <path>` (or `./engine.ron` if it exists)
+    /// and applies the remaining command line arguments on top of it.
+    pub fn from_args() -> Self {
+        let args: Vec<String> = std::env::args().skip(1).collect();
+
+        let path = args
+            .iter()
+            .position(|a| a == "--config")
+            .and_then(|i| args.get(i + 1))
+            .map(|p| p.as_str())
+            .unwrap_or(DEFAULT_CONFIG_PATH);
+
+        let mut config = match EngineConfig::load(path) {
+            Ok(config) => config,
+            // a missing default config is fine, everything else is reported
+            Err(EngineError::Io(_, e))
+                if path == DEFAULT_CONFIG_PATH && e.kind() == std::io::ErrorKind::NotFound =>
+            {
+                EngineConfig::default()
+            }
+            Err(e) => {
+                error::report(e);
+                EngineConfig::default()
+            }
+        };
+        config.apply_args(&args);
+        config
+    }
+
+    pub fn apply_args(&mut self, args: &[String]) {
+        fn value<T: std::str::FromStr>(args: &[String], i: usize) -> Option<T> {
+            args.get(i + 1).and_then(|v| v.parse().ok())
+        }
+
+        let mut default_ticks = false;
+        for (i, arg) in args.iter().enumerate() {
+            match arg.as_str() {
+                "--ups" => self.ups = value(args, i).unwrap_or(self.ups).max(1),
+                "--width" => self.window_size[0] = value(args, i).unwrap_or(self.window_size[0]),
+                "--height" => self.window_size[1] = value(args, i).unwrap_or(self.window_size[1]),
+                "--title" => {
+                    if let Some(title) = args.get(i + 1) {
+                        self.window_title = title.clone();
+                    }
+                }
+                "--msaa" => self.msaa_samples = value(args, i).unwrap_or(self.msaa_samples),
+                "--vsync" => self.vsync = true,
+                "--no-vsync" => self.vsync = false,
+                "--sensitivity" => {
+                    self.camera.mouse_sensitivity =
+                        value(args, i).unwrap_or(self.camera.mouse_sensitivity)
+                }
+                "--invert-y" => self.camera.invert_y = true,
+                "--fov" => self.fov = value(args, i).unwrap_or(self.fov),
+                "--near" => self.near = value(args, i).unwrap_or(self.near),
+                "--far" => self.far = value(args, i).unwrap_or(self.far),
+                "--panel-width" => {
+                    self.side_panel_width = value(args, i).unwrap_or(self.side_panel_width)
+                }
+                "--scene" => self.scene = args.get(i + 1).cloned(),
+                "--save-scene" => self.save_scene = args.get(i + 1).cloned(),
+                "--environment" => self.environment = args.get(i + 1).cloned(),
+                "--lut" => self.color_lut = args.get(i + 1).cloned(),
+                "--fake-controller" => self.fake_controller = args.get(i + 1).cloned(),
+                "--seed" => self.seed = value(args, i).or(self.seed),
+                "--record" => self.record = args.get(i + 1).cloned(),
+                "--replay" => self.replay = args.get(i + 1).cloned(),
+                "--input" => {
+                    if let Some(path) = args.get(i + 1) {
+                        self.input_bindings = path.clone();
+                    }
+                }
+                "--headless" => {
+                    let ticks = value(args, i);
+                    default_ticks = ticks.is_none();
+                    self.run_mode = RunMode::Headless { ticks: ticks.unwrap_or(0) };
+                }
+                "--offscreen" => {
+                    let frames = value(args, i).unwrap_or(1);
+                    let out_dir = args
+                        .get(i + 2)
+                        .cloned()
+                        .unwrap_or_else(|| String::from("./screenshots"));
+                    self.run_mode = RunMode::Offscreen { frames, out_dir };
+                }
+                _ => (),
+            }
+        }
+        // ten seconds by default, resolved last since --ups may follow --headless
+        if let RunMode::Headless { ticks } = &mut self.run_mode {
+            if default_ticks {
+                *ticks = self.ups * 10;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> EngineConfig {
+        let args: Vec<String> = args.iter().map(|a| String::from(*a)).collect();
+        let mut config = EngineConfig::default();
+        config.apply_args(&args);
+        config
+    }
+
+    #[test]
+    fn headless_ticks_dont_depend_on_the_argument_order() {
+        let before = parse(&["--headless", "--ups", "50"]);
+        let after = parse(&["--ups", "50", "--headless"]);
+        assert_eq!(before.run_mode, RunMode::Headless { ticks: 500 });
+        assert_eq!(before.run_mode, after.run_mode);
+        assert_eq!(
+            parse(&["--headless", "30", "--ups", "50"]).run_mode,
+            RunMode::Headless { ticks: 30 }
+        );
+    }
+}
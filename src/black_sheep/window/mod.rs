@@ -1,10 +1,15 @@
+use std::collections::HashMap;
+
 use sdl2::{
+    controller::GameController,
     event::Event,
     mouse::MouseUtil,
     video::{GLContext, Window},
-    EventPump,
+    EventPump, GameControllerSubsystem,
 };
 
+use super::settings::EngineConfig;
+
 pub mod window_util;
 
 pub struct SDLWindow {
@@ -12,21 +17,52 @@ pub struct SDLWindow {
     window: Window,
     _gl_context: GLContext,
     _mouse_util: MouseUtil,
+    controller_subsystem: Option<GameControllerSubsystem>,
+    /// open controllers by joystick instance id, closed when dropped
+    controllers: HashMap<u32, GameController>,
 }
 
 impl SDLWindow {
-    pub fn new() -> Self {
-        let (event_pump, window, _gl_context, _mouse_util) =
-            window_util::new_sdl_window_with_opengl_context();
+    pub fn new(config: &EngineConfig, offscreen: bool) -> Self {
+        let (event_pump, window, _gl_context, _mouse_util, controller_subsystem) =
+            window_util::new_sdl_window_with_opengl_context(config, offscreen);
 
         Self {
             event_pump,
             window,
             _gl_context,
             _mouse_util,
+            controller_subsystem,
+            controllers: HashMap::new(),
+        }
+    }
+
+    /// Opens the controller of a `ControllerDeviceAdded` event.
+    pub fn controller_added(&mut self, joystick_index: u32) {
+        if let Some(subsystem) = &self.controller_subsystem {
+            match subsystem.open(joystick_index) {
+                Ok(controller) => {
+                    #[cfg(not(feature = "debug_off"))]
+                    println!("controller connected: {}", controller.name());
+                    self.controllers.insert(controller.instance_id(), controller);
+                }
+                Err(e) => println!("could not open controller {}: {}", joystick_index, e),
+            }
         }
     }
 
+    /// Closes the controller of a `ControllerDeviceRemoved` event.
+    pub fn controller_removed(&mut self, instance_id: u32) {
+        if let Some(_controller) = self.controllers.remove(&instance_id) {
+            #[cfg(not(feature = "debug_off"))]
+            println!("controller disconnected: {}", _controller.name());
+        }
+    }
+
+    pub fn controller_names(&self) -> Vec<String> {
+        self.controllers.values().map(|c| c.name()).collect()
+    }
+
     pub fn poll_event(&mut self) -> Option<Event> {
         self.event_pump.poll_event()
     }
@@ -1,46 +1,68 @@
-use crate::black_sheep::settings::INIT_WINDOW_SIZE;
+use crate::black_sheep::settings::EngineConfig;
 
-pub fn new_sdl_window_with_opengl_context() -> (
+pub fn new_sdl_window_with_opengl_context(
+    config: &EngineConfig,
+    offscreen: bool,
+) -> (
     sdl2::EventPump,
     sdl2::video::Window,
     sdl2::video::GLContext,
     sdl2::mouse::MouseUtil,
+    Option<sdl2::GameControllerSubsystem>,
 ) {
+    if offscreen && std::env::var_os("SDL_VIDEODRIVER").is_none() {
+        // SDLs offscreen driver creates an EGL context without a display,
+        // with LIBGL_ALWAYS_SOFTWARE=1 this runs on mesas llvmpipe
+        std::env::set_var("SDL_VIDEODRIVER", "offscreen");
+    }
+
     let sdl_context = sdl2::init().unwrap();
     let video_context = sdl_context.video().unwrap();
 
     video_context.gl_attr().set_context_minor_version(4);
     video_context.gl_attr().set_context_minor_version(5);
 
-    video_context.gl_attr().set_multisample_buffers(1);
-    video_context.gl_attr().set_multisample_samples(16);
+    if config.msaa_samples > 0 {
+        video_context.gl_attr().set_multisample_buffers(1);
+        video_context.gl_attr().set_multisample_samples(config.msaa_samples);
+    }
 
     video_context.gl_attr().set_double_buffer(true);
     video_context.gl_attr().set_depth_size(24);
 
     let mouse = sdl_context.mouse();
 
+    // controllers are opened when SDL reports them, including the ones already plugged in
+    let controller = match sdl_context.game_controller() {
+        Ok(controller) => Some(controller),
+        Err(e) => {
+            println!("no game controller support: {}", e);
+            None
+        }
+    };
+
     let sdl_window = {
-        video_context
-            .window("spam", INIT_WINDOW_SIZE[0], INIT_WINDOW_SIZE[1])
-            .position_centered()
-            .resizable()
-            .opengl()
-            .build()
-            .unwrap()
+        let [w, h] = config.window_size;
+        let mut builder = video_context.window(&config.window_title, w, h);
+        builder.position_centered().resizable().opengl();
+        if offscreen {
+            builder.hidden();
+        }
+        builder.build().unwrap()
     };
 
     let sdl_gl = sdl_window.gl_create_context().unwrap();
     gl::load_with(|symbol| video_context.gl_get_proc_address(symbol) as *const _);
 
-    #[cfg(feature = "vsync_off")]
-    video_context.gl_set_swap_interval(0).unwrap();
+    if let Err(e) = video_context.gl_set_swap_interval(if config.vsync { 1 } else { 0 }) {
+        println!("could not set swap interval: {}", e);
+    }
 
     //toggle_wiregrid();
 
     let event_pump = sdl_context.event_pump().unwrap();
 
-    (event_pump, sdl_window, sdl_gl, mouse)
+    (event_pump, sdl_window, sdl_gl, mouse, controller)
 }
 
 pub fn gl_msaa(b: bool) {
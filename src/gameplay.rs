@@ -1,12 +1,32 @@
 use cgmath::{num_traits::Pow, *};
 
 use crate::black_sheep::ecs::*;
+use crate::black_sheep::gamestate::input::InputState;
+use crate::black_sheep::rng;
 
 #[inline]
 pub fn torus_r(v: Vector3<f32>, r_mj: f32) -> f32 {
     f32::sqrt((r_mj - f32::sqrt(v.x.pow(2) + v.z.pow(2))).pow(2) + v.y.pow(2))
 }
 
+/// Moves the apes along their direction and lets the ai pick the next one, once per tick.
+pub fn ape_update(ecs: &mut CHAINED_ECS) -> impl FnMut(&InputState) {
+    let mut circle = ecs.get_circle_accessor();
+    let positions = ecs.get_positions_accessor();
+    let mut pos_update = ecs.get_update_pos_ori_accessor();
+
+    move |_input: &InputState| {
+        {
+            let mut update = pos_update.lock();
+            for (pos, ori, direction, target_ori) in update.iter() {
+                *pos = *pos + *direction;
+                *ori = *target_ori;
+            }
+        }
+        run_ape_ai(&mut circle, &positions);
+    }
+}
+
 pub fn run_ape_ai(circle: &mut CircleAccessor, positions: &PositionsAccessor) {
     let mut c_l = circle.lock();
     let pos_s = positions.lock();
@@ -115,9 +135,9 @@ pub fn run_ape_ai(circle: &mut CircleAccessor, positions: &PositionsAccessor) {
 }
 
 pub fn gen_apes(ecs: &mut CHAINED_ECS) {
-    use rand::{thread_rng, Rng};
+    use rand::Rng;
 
-    let mut rng = thread_rng();
+    let mut rng = rng::stream("gen_apes");
 
     for i in 0..3 {
         for y in 0..3 {
@@ -135,3 +155,122 @@ pub fn gen_apes(ecs: &mut CHAINED_ECS) {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::black_sheep::{
+        gamestate::camera::Camera,
+        run_headless,
+        settings::{EngineConfig, RunMode},
+        Logic,
+    };
+
+    // the rng is global, every test seeds it the same so they can run in parallel
+    const SEED: u64 = 7;
+
+    fn headless_config(ticks: u64) -> EngineConfig {
+        EngineConfig {
+            run_mode: RunMode::Headless { ticks },
+            seed: Some(SEED),
+            ..EngineConfig::default()
+        }
+    }
+
+    /// Positions of all apes after `ticks` updates of the ape ai.
+    fn run_apes(ticks: u64) -> Vec<Vector3<f32>> {
+        run_apes_with(headless_config(ticks), ticks)
+    }
+
+    fn run_apes_with(config: EngineConfig, ticks: u64) -> Vec<Vector3<f32>> {
+        let mut ecs = run_headless(config, ticks, |ecs| {
+            gen_apes(ecs);
+            Logic {
+                update: ape_update(ecs),
+                draw: |_: f32, _: &dyn Camera, _: &Matrix4<f32>| {},
+            }
+        });
+        ape_positions(&mut ecs)
+    }
+
+    /// Like `run_apes_with`, but the move axes push every ape, so the result depends on the input.
+    fn run_steered_apes(config: EngineConfig, ticks: u64) -> Vec<Vector3<f32>> {
+        let mut ecs = run_headless(config, ticks, |ecs| {
+            gen_apes(ecs);
+            let mut apes = ape_update(ecs);
+            let mut steer = ecs.get_update_pos_ori_accessor();
+            Logic {
+                update: move |input: &InputState| {
+                    apes(input);
+                    let push = Vector3::new(
+                        input.axis("move_x"),
+                        input.axis("move_y"),
+                        input.axis("move_z"),
+                    );
+                    let mut update = steer.lock();
+                    for (pos, _, _, _) in update.iter() {
+                        *pos = *pos + push;
+                    }
+                },
+                draw: |_: f32, _: &dyn Camera, _: &Matrix4<f32>| {},
+            }
+        });
+        ape_positions(&mut ecs)
+    }
+
+    fn ape_positions(ecs: &mut CHAINED_ECS) -> Vec<Vector3<f32>> {
+        let accessor = ecs.get_positions_accessor();
+        let mut positions = Vec::new();
+        for (pos, _) in accessor.lock().iter() {
+            positions.push(*pos);
+        }
+        positions
+    }
+
+    #[test]
+    fn apes_move_headless() {
+        let start = run_apes(0);
+        let end = run_apes(200);
+
+        assert_eq!(start.len(), 9);
+        assert_eq!(end.len(), 9);
+        for p in end.iter() {
+            assert!(p.x.is_finite() && p.y.is_finite() && p.z.is_finite());
+        }
+        assert!(start.iter().zip(end.iter()).any(|(s, e)| s != e));
+    }
+
+    #[test]
+    fn headless_runs_are_deterministic() {
+        assert_eq!(run_apes(100), run_apes(100));
+    }
+
+    #[test]
+    fn replay_reproduces_recorded_run() {
+        let path = std::env::temp_dir().join("last_engine_replay_test.ron");
+        let path = path.to_string_lossy().into_owned();
+        let script = concat!(env!("CARGO_MANIFEST_DIR"), "/res/input/fake_fly.ron");
+
+        let recorded = run_steered_apes(
+            EngineConfig {
+                record: Some(path.clone()),
+                fake_controller: Some(String::from(script)),
+                ..headless_config(150)
+            },
+            150,
+        );
+        // the seed, the number of ticks and the input come from the recording
+        let replayed = run_steered_apes(
+            EngineConfig {
+                seed: None,
+                replay: Some(path.clone()),
+                ..headless_config(1)
+            },
+            1,
+        );
+        let _ = std::fs::remove_file(&path);
+
+        assert_ne!(recorded, run_apes(150), "the script didn't steer the apes");
+        assert_eq!(recorded, replayed);
+    }
+}
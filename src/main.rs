@@ -1,12 +1,28 @@
 #![feature(trait_alias)]
 
 use crate::black_sheep::{
-    gamestate::camera::structs::FlyingEye,
-    rendering::{geometry::mesh::MeshToken, loader::load_texture_from_path},
-    settings::DT,
+    assets::{self, Handle},
+    error::{self, Result},
+    gamestate::camera::Camera,
+    rendering::{
+        debug_draw,
+        deferred::{self, GBuffer},
+        environment::Environment,
+        frustum::{self, Frustum},
+        geometry::{instance::InstanceBuffer, mesh::MeshToken},
+        lights::{Light, LightBuffer},
+        shadow::PointShadow,
+        skybox::{SkyDef, Skybox},
+        gltf_import::GltfScene,
+        material::{Material, MaterialId},
+        pbr::{self, PbrView},
+        render_queue::{self, DrawItem, Primitive, UniformValue},
+    },
+    scene::Scene,
+    settings::{EngineConfig, RunMode},
 };
 use black_sheep::{DrawFunction, UpdateFunction};
-use cgmath::{vec2, vec3, InnerSpace, Matrix4, Vector2, Vector3, Zero};
+use cgmath::{vec3, InnerSpace, Matrix4, Vector2, Vector3, Zero};
 
 mod black_sheep;
 mod gameplay;
@@ -19,60 +35,143 @@ extern crate sdl2;
 #[macro_use]
 extern crate lazy_static;
 
+// everything that needs a GL context, created on the first draw call so headless runs never touch it
+struct DrawResources {
+    ape: Handle<GltfScene>,
+    torus: MeshToken,
+    sprite: MeshToken,
+    three_d: u32,
+    three_dl: u32,
+    billboard: Handle<Material>,
+    shadow: PointShadow,
+    lights: LightBuffer,
+    environment: Environment,
+    skybox: Skybox,
+    gbuffer: GBuffer,
+    // keeps the materials of the props loaded
+    _prop_materials: Vec<Handle<Material>>,
+    // rebuilt when a hot reload swaps the ape mesh
+    ape_instances: Option<InstanceBuffer>,
+}
+
+impl DrawResources {
+    fn new(environment: Option<&str>, sky: Option<&SkyDef>) -> Result<Self> {
+        let (torus, sprite) = black_sheep::rendering::geometry::get_mesh_repo(|mr| {
+            let torus = MeshToken::from(mr.get_mesh_by_name("torus").unwrap());
+            let sprite = MeshToken::from(mr.get_mesh_by_name("sprite").unwrap());
+            //let circles = MeshToken::from(mr.get_mesh_by_name("circles").unwrap());
+            (torus, sprite)
+        });
+
+        let (ape, billboard, prop_materials) = assets::get_asset_manager(|am| -> Result<_> {
+            Ok((
+                am.load_model("res/ape.glb")?,
+                am.load_material(BILLBOARD_MATERIAL)?,
+                vec![am.load_material(PROP_MATERIAL)?],
+            ))
+        })?;
+
+        let sky = sky.cloned().unwrap_or_default();
+        // an hdr sky lights the scene too, unless an environment is given explicitly
+        let environment = match (environment, &sky) {
+            (Some(path), _) | (None, SkyDef::Equirect(path)) => Environment::from_hdr(path)?,
+            _ => Environment::gradient()?,
+        };
+
+        let rendering = black_sheep::rendering::shader::get_shader_repo();
+        let program = |name: &str| rendering.program(name).unwrap();
+
+        Ok(DrawResources {
+            ape,
+            torus,
+            sprite,
+            three_d: program("color_3d"),
+            three_dl: program("color_3d_light_instanced"),
+            billboard,
+            shadow: PointShadow::new()?,
+            lights: LightBuffer::new(),
+            environment,
+            skybox: Skybox::new(&sky)?,
+            gbuffer: GBuffer::new()?,
+            _prop_materials: prop_materials,
+            ape_instances: None,
+        })
+    }
+}
+
+const BILLBOARD_MATERIAL: &str = "res/materials/nice_image.ron";
+const PROP_MATERIAL: &str = "res/materials/orange.ron";
+// where the big pbr shaded ape stands
+const PBR_APE_POSITION: [f32; 3] = [0.0, 0.0, -40.0];
+
 fn main() {
     #[cfg(not(feature = "debug_off"))]
     println!("Hello, world!");
 
-    black_sheep::run(|ecs| {
-        gameplay::gen_apes(ecs);
+    let config = EngineConfig::from_args();
+    let environment = config.environment.clone();
+    // props need meshes, which only exist with a GL context
+    let spawn_props = !matches!(config.run_mode, RunMode::Headless { .. });
+    let scene = config
+        .scene
+        .as_ref()
+        .and_then(|path| Scene::load(path).map_err(error::report).ok());
+    let sky = scene.as_ref().and_then(|s| s.sky.clone());
 
-        ecs.add_ball_soa(Vector2::new(5.0, -5.0), Vector2::zero());
+    black_sheep::run(config, |ecs| {
+        if let Some(scene) = &scene {
+            scene.spawn(ecs);
+        } else {
+            gameplay::gen_apes(ecs);
 
-        let mut circle = ecs.get_circle_accessor();
-        let positions = ecs.get_positions_accessor();
-        let mut pos_update = ecs.get_update_pos_ori_accessor();
+            ecs.add_ball_soa(Vector2::new(5.0, -5.0), Vector2::zero());
 
-        let update = move |_input| {
-            {
-                let mut update = pos_update.lock();
-                for (pos, ori, direction, target_ori) in update.iter() {
-                    *pos = *pos + *direction;
-                    *ori = *target_ori;
-                }
+            ecs.add_lamp_soa(Light::point(vec3(30.0, 30.0, 10.0), 1000.0, 150.0).with_shadows());
+            ecs.add_lamp_soa(Light::directional(vec3(-0.3, -1.0, -0.2), 0.15));
+            ecs.add_lamp_soa(
+                Light::spot(vec3(0.0, 25.0, 0.0), vec3(0.0, -1.0, 0.0), 15.0, 25.0, 800.0, 60.0)
+                    .color(vec3(0.4, 0.6, 1.0)),
+            );
+        }
+
+        if spawn_props {
+            let cube = black_sheep::rendering::geometry::get_mesh_repo(|mr| {
+                MeshToken::from(mr.get_mesh_by_name("cube").unwrap())
+            });
+            for x in [-25.0, 25.0].iter() {
+                ecs.add_prop_soa(
+                    Matrix4::from_translation(vec3(*x, 0.0, 0.0)),
+                    cube.clone(),
+                    MaterialId::from_path(PROP_MATERIAL),
+                );
             }
-            gameplay::run_ape_ai(&mut circle, &positions);
-        };
+        }
+
+        let update = gameplay::ape_update(ecs);
 
         let draw_m = ecs.get_draw_accessor();
+        let draw_props = ecs.get_draw_material_accessor();
+        let gather_lights = ecs.get_gather_lights_accessor();
 
         let mut calc_mat = ecs.get_calculate_mat_accessor();
 
         //let mut c_vec = Vec::new();
         //let mut simulate = ecs.get_simulate_accessor();
 
-        let _bb = black_sheep::setup::init_mesh().unwrap();
-
-        let (ape, torus,sprite) = black_sheep::rendering::geometry::get_mesh_repo(|mr| {
-            let ape = MeshToken::from(mr.get_mesh_by_name("ape").unwrap());
-            let torus = MeshToken::from(mr.get_mesh_by_name("torus").unwrap());
-            let sprite = MeshToken::from(mr.get_mesh_by_name("sprite").unwrap());
-            //let circles = MeshToken::from(mr.get_mesh_by_name("circles").unwrap());
-            (ape, torus,sprite)
-        });
-
-        let rendering = black_sheep::rendering::shader::get_shader_repo();
-
-        let three_d = rendering.color_3d;
-        let three_dl = rendering.color_3d_light;
-        let _circles_2d = rendering.point_2d;
-        let sprite_shader = rendering.sprite;
-        let nice_image = load_texture_from_path("./res/1322615842122.jpg").unwrap();
+        let mut resources = None;
         let mut last = None;
-        let draw = move |i: f32, cam: &FlyingEye, prj: &Matrix4<f32>| {
+        let draw = move |i: f32, cam: &dyn Camera, prj: &Matrix4<f32>| {
+            let r = match resources.get_or_insert_with(|| {
+                DrawResources::new(environment.as_deref(), sky.as_ref()).map_err(error::report)
+            }) {
+                Ok(r) => r,
+                Err(()) => return,
+            };
+
             let view = cam.get_i_view(i);
             let vp = prj * view;
+            let frustum = Frustum::from_matrix(&vp);
             for (p, o, direction, to, model) in calc_mat.lock().iter() {
-                
                 let q = o.slerp(*to, i);
                 let v = p + (direction * i);
                 last = Some(v.clone());
@@ -83,36 +182,137 @@ fn main() {
 
             let d_lock = draw_m.lock();
 
-            ape.bind_vertex_array();
-            three_dl.use_program();
-            three_dl.set_light_position(vec3(30.0, 30.0, 10.0));
-            three_dl.set_light_power(1000.0);
+            // a reloaded model without meshes is still valid glTF, the herd is skipped then
+            let ape = r
+                .ape
+                .get()
+                .meshes
+                .first()
+                .and_then(|m| m.primitives.first())
+                .map(|p| p.mesh.clone());
+            let bounds = ape.as_ref().and_then(|ape| ape.bounds);
+            let ape_slot = &mut r.ape_instances;
+            let mut ape_instances = ape.as_ref().map(|ape| match ape_slot {
+                Some(instances) if instances.mesh_uid() == ape.uid => instances,
+                slot => slot.insert(InstanceBuffer::new(ape.clone())),
+            });
 
-            for (m, c) in d_lock.iter() {
-                three_dl.set_MVP(vp * m);
-                three_dl.set_M(*m);
-                three_dl.set_col(*c);
-                ape.draw_triangle_elements();
+            // every ape casts a shadow, culling only applies to the camera
+            r.lights.gather(&gather_lights);
+            if let Some(instances) = &mut ape_instances {
+                instances.fill(d_lock.iter());
             }
+            let props = draw_props.lock();
+            let pbr_ape =
+                Matrix4::from_translation(PBR_APE_POSITION.into()) * Matrix4::from_scale(4.0);
+            let ape_scene = r.ape.get();
+            r.shadow.render(r.lights.shadow_light(), |caster| {
+                if let Some(instances) = &ape_instances {
+                    caster.draw_instanced(instances);
+                }
+                ape_scene.for_each_mesh_node(|node, mesh| {
+                    for primitive in mesh.primitives.iter() {
+                        caster.draw(&primitive.mesh, pbr_ape * node);
+                    }
+                });
+                for (m, mesh, _) in props.iter() {
+                    caster.draw(mesh, *m);
+                }
+            });
 
-            three_d.use_program();
-            three_d.set_MVP(vp);
-            three_d.set_col(Vector3::new(1.0, 0.0, 1.0));
-
-            torus.bind_vertex_array();
-            torus.draw_line_elements();
-
-            sprite_shader.use_program();
-            unsafe { gl::ActiveTexture(gl::TEXTURE0 + 1) };
-            nice_image.bind();
-            sprite_shader.set_CameraRight_worldspace(vec3(view.x.x, view.y.x, view.z.x));
-            sprite_shader.set_CameraUp_worldspace(vec3(view.x.y, view.y.y, view.z.y));
-            sprite_shader.set_VP(vp);
-            sprite_shader.set_BillboardPos(last.unwrap() + vec3(2.0,2.0,2.0));
-            sprite_shader.set_BillboardSize(vec2(2.0, 2.0));
-            sprite_shader.set_myTextureSampler(1);
-            sprite.bind_vertex_array();
-            sprite.draw_triangle_elements();
+            if let (Some(ape), Some(ape_instances)) = (ape, ape_instances) {
+                ape_instances.fill(d_lock.iter().filter(|(m, _)| match &bounds {
+                    Some(bounds) => {
+                        let visible = frustum.intersects_aabb(&bounds.transform(m));
+                        frustum::record_cull(visible);
+                        visible
+                    }
+                    None => true,
+                }));
+
+                if !ape_instances.is_empty() && deferred::settings().enabled {
+                    r.gbuffer.geometry_pass(vp, |gbuffer| {
+                        gbuffer.material(0.0, 1.0);
+                        gbuffer.draw_instanced(ape_instances);
+                    });
+                    r.gbuffer.light_pass(cam.pose(i).position, &r.shadow);
+                } else if !ape_instances.is_empty() {
+                    let count = ape_instances.len() as i32;
+                    render_queue::submit(
+                        r.shadow.receive(
+                            DrawItem::new(ape, r.three_dl)
+                                .primitive(Primitive::TrianglesInstanced(count))
+                                .uniform("VP", UniformValue::Mat4(vp)),
+                        ),
+                    );
+                }
+            }
+
+            let pbr_view = PbrView {
+                view_projection: vp,
+                camera_position: cam.pose(i).position,
+                exposure: 1.0,
+                // the post processing tone maps the whole frame
+                tonemap: !black_sheep::rendering::postprocess::settings().enabled,
+            };
+            r.environment.bind();
+            ape_scene.for_each_mesh_node(|node, mesh| {
+                for primitive in mesh.primitives.iter() {
+                    let item = pbr::draw_item(&ape_scene, primitive, pbr_ape * node, &pbr_view);
+                    render_queue::submit(r.environment.receive(r.shadow.receive(item)));
+                }
+            });
+            render_queue::submit(r.skybox.draw_item(view, *prj));
+
+            debug_draw::axes(pbr_ape, 2.0, 0.0);
+            if let Some(bounds) = &bounds {
+                let white = Vector3::new(1.0, 1.0, 1.0);
+                debug_draw::aabb(&bounds.transform(&pbr_ape), white, 0.0);
+                let label = pbr_ape * bounds.max.extend(1.0);
+                debug_draw::text_3d(label.truncate(), "pbr ape", white, 0.0);
+            }
+
+            render_queue::submit(
+                DrawItem::new(r.torus.clone(), r.three_d)
+                    .primitive(Primitive::Lines)
+                    .uniform("MVP", UniformValue::Mat4(vp))
+                    .uniform("col", UniformValue::Vec3(Vector3::new(1.0, 0.0, 1.0))),
+            );
+
+            assets::get_asset_manager(|am| {
+                for (m, mesh, material) in props.iter() {
+                    if let Some(material) = am.material(*material) {
+                        render_queue::submit(
+                            material
+                                .get()
+                                .draw_item(mesh.clone())
+                                .position(m.w.truncate())
+                                .uniform("MVP", UniformValue::Mat4(vp * m)),
+                        );
+                    }
+                }
+            });
+
+            // the billboard follows the last ape, there is none to follow in an empty scene
+            if let Some(last) = last {
+                let billboard_pos = last + vec3(2.0, 2.0, 2.0);
+                render_queue::submit(
+                    r.billboard
+                        .get()
+                        .draw_item(r.sprite.clone())
+                        .position(billboard_pos)
+                        .uniform(
+                            "CameraRight_worldspace",
+                            UniformValue::Vec3(vec3(view.x.x, view.y.x, view.z.x)),
+                        )
+                        .uniform(
+                            "CameraUp_worldspace",
+                            UniformValue::Vec3(vec3(view.x.y, view.y.y, view.z.y)),
+                        )
+                        .uniform("VP", UniformValue::Mat4(vp))
+                        .uniform("BillboardPos", UniformValue::Vec3(billboard_pos)),
+                );
+            }
         };
         black_sheep::Logic { update, draw }
     });
@@ -0,0 +1,141 @@
+//! Golden image regression test of the offscreen renderer.
+//!
+//! Renders a few frames of `res/scenes/apes.ron` with a fixed seed through `--offscreen` and
+//! compares them with the references in `res/golden`. Needs a GL driver, without a display
+//! mesa's llvmpipe works:
+//!
+//!     LIBGL_ALWAYS_SOFTWARE=1 cargo test --test golden -- --ignored
+//!
+//! With `BLESS_GOLDEN=1` the rendered frames replace the references instead.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use image::RgbaImage;
+
+const GOLDEN_DIR: &str = "res/golden";
+const FRAMES: u32 = 3;
+const ARGS: &[&str] = &[
+    "--scene",
+    "res/scenes/apes.ron",
+    "--seed",
+    "1",
+    "--width",
+    "640",
+    "--height",
+    "480",
+];
+/// per channel difference that still counts as equal, drivers round differently
+const TOLERANCE: u8 = 8;
+/// share of the pixels that may differ by more than `TOLERANCE`
+const MAX_DIFFERING: f64 = 0.001;
+
+#[derive(Debug, PartialEq)]
+struct ImageDiff {
+    differing_pixels: u64,
+    total_pixels: u64,
+}
+
+impl ImageDiff {
+    fn share(&self) -> f64 {
+        self.differing_pixels as f64 / self.total_pixels.max(1) as f64
+    }
+}
+
+/// Counts the pixels where any channel differs by more than `tolerance`.
+/// Images of different size differ everywhere.
+fn diff(actual: &RgbaImage, golden: &RgbaImage, tolerance: u8) -> ImageDiff {
+    let total_pixels = golden.width() as u64 * golden.height() as u64;
+    if actual.dimensions() != golden.dimensions() {
+        return ImageDiff {
+            differing_pixels: total_pixels,
+            total_pixels,
+        };
+    }
+    let differing_pixels = actual
+        .pixels()
+        .zip(golden.pixels())
+        .filter(|(a, g)| {
+            a.0.iter()
+                .zip(g.0.iter())
+                .any(|(a, g)| (*a as i16 - *g as i16).abs() > tolerance as i16)
+        })
+        .count() as u64;
+    ImageDiff {
+        differing_pixels,
+        total_pixels,
+    }
+}
+
+fn frame_name(frame: u32) -> String {
+    format!("frame_{:04}.png", frame)
+}
+
+fn render(out_dir: &Path) {
+    let status = Command::new(env!("CARGO_BIN_EXE_last_engine"))
+        .args(ARGS)
+        .arg("--offscreen")
+        .arg(FRAMES.to_string())
+        .arg(out_dir)
+        .status()
+        .expect("could not start the engine");
+    assert!(status.success(), "offscreen run failed: {}", status);
+}
+
+#[test]
+#[ignore = "needs a GL driver"]
+fn offscreen_frames_match_golden() {
+    let out_dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("golden");
+    let _ = std::fs::remove_dir_all(&out_dir);
+    render(&out_dir);
+
+    let bless = std::env::var_os("BLESS_GOLDEN").is_some();
+    let mut failures = Vec::new();
+    for frame in 0..FRAMES {
+        let name = frame_name(frame);
+        let actual = image::open(out_dir.join(&name)).unwrap().to_rgba8();
+        let golden_path = Path::new(GOLDEN_DIR).join(&name);
+        if bless {
+            std::fs::create_dir_all(GOLDEN_DIR).unwrap();
+            actual.save(&golden_path).unwrap();
+            continue;
+        }
+        let golden = match image::open(&golden_path) {
+            Ok(golden) => golden.to_rgba8(),
+            Err(e) => panic!("{}: {}, run with BLESS_GOLDEN=1", golden_path.display(), e),
+        };
+        let d = diff(&actual, &golden, TOLERANCE);
+        if d.share() > MAX_DIFFERING {
+            failures.push(format!(
+                "{}: {} of {} pixels differ",
+                name, d.differing_pixels, d.total_pixels
+            ));
+        }
+    }
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
+
+#[test]
+fn diff_counts_pixels_over_tolerance() {
+    let golden = RgbaImage::from_pixel(4, 4, image::Rgba([100, 100, 100, 255]));
+    let mut actual = golden.clone();
+    actual.put_pixel(0, 0, image::Rgba([100 + TOLERANCE, 100, 100, 255]));
+    actual.put_pixel(1, 0, image::Rgba([100, 100 - TOLERANCE - 1, 100, 255]));
+    actual.put_pixel(2, 0, image::Rgba([100, 100, 255, 255]));
+
+    assert_eq!(
+        diff(&actual, &golden, TOLERANCE),
+        ImageDiff {
+            differing_pixels: 2,
+            total_pixels: 16,
+        }
+    );
+    assert_eq!(diff(&golden, &golden, 0).differing_pixels, 0);
+}
+
+#[test]
+fn diff_of_different_sizes_differs_everywhere() {
+    let golden = RgbaImage::new(4, 4);
+    let actual = RgbaImage::new(4, 2);
+    assert_eq!(diff(&actual, &golden, 255).share(), 1.0);
+}